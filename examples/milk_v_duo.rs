@@ -1,5 +1,5 @@
 use metrology_insight::{
-    generate_signals, MetrologyInsight, MetrologyInsightConfig, MetrologyInsightSignal, MetrologyInsightSignalType, ADC_SAMPLES_50HZ_CYCLE, AMPS_TO_COUNTS, VIN_TO_COUNTS
+    generate_signals, AdcFrontEnd, Calibration, ChannelConfig, FilterKind, MetrologyInsight, MetrologyInsightConfig, MetrologyInsightSignalType, SampleFrame, SensorModel, SysfsTemperatureSensor, AMPS_TO_COUNTS, NUMBER_HARMONICS, VIN_TO_COUNTS
 };
 
 use metrology_proto::metrology_insight::Empty;
@@ -10,11 +10,12 @@ use signal_hook::iterator::Signals;
 #[warn(dead_code)]
 use std::fs::OpenOptions;
 use std::os::unix::io::AsRawFd;
+use std::path::PathBuf;
 use std::process::Command;
 use std::sync::mpsc;
 use std::sync::{Arc, Mutex};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use clap::Parser;
 use tonic::{transport::Server, Request, Response, Status};
@@ -49,18 +50,16 @@ struct Args {
     /// Simulate signal samples instead of reading from hardware
     #[arg(short = 's', long = "Simulate Samples")]
     simulate: bool,
-}
 
-const VREF: f64 = 1.8; // 1.88 ADC reference voltage (Milk-V Duo: 1.8V)
-const ADC_RESOLUTION: f64 = 4095.0; // 12-bit ADC resolution (0 - 4095)
-const ADC_INT_DIVISOR: f64 = 0.5; // Internal ADC voltage divider (3.3V → 1.65V)
-const FACTOR_DIVISORS_SCALE: f64 = 1.0 / ADC_INT_DIVISOR; // Total scale factor to undo internal dividers
-const ADC_VOLTAGE_SENSITIVITY: f64 = 1170.0; // ADC Voltage sensitivity (mV/V) (Milk-V Duo: 1170 mV/V)
-const ADC_VOLTAGE_FACTOR: f64 = (ADC_VOLTAGE_SENSITIVITY * VREF * FACTOR_DIVISORS_SCALE) / ADC_RESOLUTION; // Final factor to convert ADC reading to original voltage
-const ADC_CURRENT_SCALE: f64 = 29.03; // ADC Current sensitivity for SCT013-030 (30A/1V) (calculated from burden resistor 62 Ohms and ratio)
-const ADC_CURRENT_FACTOR: f64 = VREF * FACTOR_DIVISORS_SCALE / ADC_RESOLUTION ; // Factor to convert ADC reading to voltage for current sensor
+    /// Calibration file with the gain, offset and phase of each channel
+    #[arg(short = 'c', long = "calibration")]
+    calibration: Option<PathBuf>,
+
+    /// Thermal zone read for the temperature compensation of the calibration
+    #[arg(short = 't', long = "thermal-zone")]
+    thermal_zone: Option<usize>,
+}
 
-const SAMPLES_PER_CYCLE: usize = ADC_SAMPLES_50HZ_CYCLE as usize;
 const ADC_SAMPLE_SECONDS: f64 = 7812.5; // Sampling frequency: fs × cycle time = 7812.5 Hz × 0.02s = 156.25 samples
 
 /* IOCTL Commands */
@@ -95,21 +94,77 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Create a channel to receive signals
     let mut signals = Signals::new(&[SIGUSR1, SIGUSR2]).expect("No se pudo registrar señales");
 
-    let (tx, rx) = mpsc::channel::<(Vec<i32>, Vec<i32>)>();
+    let (tx, rx) = mpsc::channel::<(Vec<i32>, Vec<i32>, u64)>();
 
     let (tx_process_to_print, rx_process_to_print) = mpsc::channel::<()>();
 
-    let config = MetrologyInsightConfig {
+    let mut config = MetrologyInsightConfig {
         avg_sec: 0.02,
         adc_samples_seconds: ADC_SAMPLE_SECONDS,
-        adc_samples_per_cycle: SAMPLES_PER_CYCLE as f64,
-        num_harmonics: 0,
+        num_harmonics: NUMBER_HARMONICS,
+        harmonic_grouping: true,
+        // La cadena de filtros está vacía por defecto: el suavizado de 3 muestras que se aplicaba siempre a
+        // las tramas se configura aquí, en los dos canales para que sus retardos se igualen
+        voltage: ChannelConfig {
+            calc_freq: true,
+            adc_factor: 1.0 / VIN_TO_COUNTS,
+            filters: vec![FilterKind::MovingAverage { window: 3 }],
+            ..Default::default()
+        },
+        current: ChannelConfig {
+            adc_factor: 1.0 / AMPS_TO_COUNTS,
+            filters: vec![FilterKind::MovingAverage { window: 3 }],
+            ..Default::default()
+        },
+        ..Default::default()
     };
 
-    let insight = Arc::new(Mutex::new(MetrologyInsight {
-        socket: Default::default(), // Default socket initialization
-        config: config,
-    }));
+    // Con hardware, los factores salen del front end del ADC y de los sensores de la placa; las señales
+    // simuladas no son códigos del ADC y no se comprueba su rango
+    if !args.simulate {
+        let frontend = AdcFrontEnd::milk_v_duo();
+        let voltage_sensor = SensorModel::zmpt101b();
+        let current_sensor = SensorModel::sct013_030();
+        voltage_sensor.configure(&frontend, &mut config.voltage)?;
+        current_sensor.configure(&frontend, &mut config.current)?;
+        config.adc_max_code = frontend.max_code();
+
+        for sensor in [voltage_sensor, current_sensor] {
+            log::info!(
+                "{}: range {:.1}, clipping at {:.1} peak",
+                sensor.as_str(),
+                sensor.nominal_range(),
+                sensor.clipping_peak(&frontend)
+            );
+        }
+    }
+
+    // La calibración corrige los factores nominales de cada canal
+    if let Some(path) = &args.calibration {
+        let calibration = Calibration::load(path)?;
+        calibration.apply(&mut config);
+        log::info!("Calibration loaded from {}", path.display());
+    }
+
+    // El driver entrega un ciclo nominal por trama: 7812,5 Hz × 0,02 s = 156 muestras
+    let samples_per_cycle = config.samples_per_cycle(config.default_nominal_frequency());
+
+    // Reloj de adquisición: instante del primer muestreo de cada trama
+    let clock = Instant::now();
+    let frame_duration = Duration::from_secs_f64(samples_per_cycle as f64 / ADC_SAMPLE_SECONDS);
+    let timestamp_us = move || clock.elapsed().saturating_sub(frame_duration).as_micros() as u64;
+    let frame_duration_us = frame_duration.as_secs_f64() * 1e6;
+
+    let mut insight = MetrologyInsight::new(config);
+
+    // La temperatura de la zona térmica compensa la deriva de los canales
+    if let Some(zone) = args.thermal_zone {
+        let sensor = SysfsTemperatureSensor::new(zone);
+        log::info!("Temperature read from {}", sensor.path().display());
+        insight.set_temperature_sensor(Some(Box::new(sensor)));
+    }
+
+    let insight = Arc::new(Mutex::new(insight));
 
     thread::spawn(move || {
         if !args.simulate {
@@ -123,7 +178,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 // Mmap to map the device
                 let addr = mmap(
                     std::ptr::null_mut(),  // Suggested address (NULL lets the kernel decide)
-                    SAMPLES_PER_CYCLE * 4, // Size to map: 2 buffers of 354 samples
+                    samples_per_cycle * 4, // Size to map: 2 buffers of 354 samples
                     PROT_READ,             // Permissions (read-only)
                     MAP_SHARED,            // Mapping type
                     fd,                    // File descriptor
@@ -146,10 +201,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 let data_ptr = addr as *const i32;
 
                 // Create the slices taking the offset into account.
-                let voltage_1 = std::slice::from_raw_parts(data_ptr, SAMPLES_PER_CYCLE);
-                let current_1 = std::slice::from_raw_parts(data_ptr.add(SAMPLES_PER_CYCLE), SAMPLES_PER_CYCLE);
-                let voltage_2 = std::slice::from_raw_parts(data_ptr.add(2 * SAMPLES_PER_CYCLE), SAMPLES_PER_CYCLE);
-                let current_2 = std::slice::from_raw_parts(data_ptr.add(3 * SAMPLES_PER_CYCLE), SAMPLES_PER_CYCLE);
+                let voltage_1 = std::slice::from_raw_parts(data_ptr, samples_per_cycle);
+                let current_1 = std::slice::from_raw_parts(data_ptr.add(samples_per_cycle), samples_per_cycle);
+                let voltage_2 = std::slice::from_raw_parts(data_ptr.add(2 * samples_per_cycle), samples_per_cycle);
+                let current_2 = std::slice::from_raw_parts(data_ptr.add(3 * samples_per_cycle), samples_per_cycle);
 
                 // Call ioctl to start the timer for capturing the waveform.
                 let result = start_timer(fd);
@@ -162,13 +217,13 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 for signal in signals.forever() {
                     match signal {
                         SIGUSR1 => {
-                            if tx.send((voltage_1.to_vec(), current_1.to_vec())).is_err() {
+                            if tx.send((voltage_1.to_vec(), current_1.to_vec(), timestamp_us())).is_err() {
                                 log::error!("Error: Receiver has dropped\n");
                                 break;
                             }
                         }
                         SIGUSR2 => {
-                            if tx.send((voltage_2.to_vec(), current_2.to_vec())).is_err() {
+                            if tx.send((voltage_2.to_vec(), current_2.to_vec(), timestamp_us())).is_err() {
                                 log::error!("Error: Receiver has dropped\n");
                                 break;
                             }
@@ -185,7 +240,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 let simulate_signals = generate_signals();
 
                 if tx
-                    .send((simulate_signals[0].clone(), simulate_signals[1].clone()))
+                    .send((simulate_signals[0].clone(), simulate_signals[1].clone(), timestamp_us()))
                     .is_err()
                 {
                     log::error!("Error: Receiver has dropped\n");
@@ -202,7 +257,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
         move || {
             loop {
-                while let Ok((data_voltage_to_consume, data_current_to_consume)) = rx.recv() {
+                while let Ok((data_voltage_to_consume, data_current_to_consume, timestamp_us)) = rx.recv() {
                     /*
                         print!(
                             "{},",
@@ -213,35 +268,32 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                                 .join(", ")
                         );
                     */
-                    // Determinar factores condicionalmente
-                    let (voltage_adc_factor, current_adc_factor, current_adc_scale) = if args.simulate {
-                        (1.0 / VIN_TO_COUNTS, 1.0 / AMPS_TO_COUNTS, 1.0)
-                    } else {
-                        (ADC_VOLTAGE_FACTOR, ADC_CURRENT_FACTOR, ADC_CURRENT_SCALE)
-                    };
-
-                    let mut voltage_signal = MetrologyInsightSignal {
-                        wave: data_voltage_to_consume.to_vec(),
-                        length: SAMPLES_PER_CYCLE,
-                        calc_freq: true,
-                        signal_type: MetrologyInsightSignalType::Voltage,
-                        adc_factor: voltage_adc_factor,
-                        ..Default::default()
+                    // El driver no expone su contador de medias tramas: la secuencia sale del reloj de
+                    // adquisición, de modo que una señal SIGUSR perdida deja un salto en la secuencia
+                    let sequence = (timestamp_us as f64 / frame_duration_us).round() as u64;
+
+                    let voltage_frame = SampleFrame {
+                        channel: MetrologyInsightSignalType::Voltage,
+                        samples: data_voltage_to_consume,
+                        sample_rate: ADC_SAMPLE_SECONDS,
+                        timestamp_us,
+                        sequence,
                     };
 
-                    let mut current_signal = MetrologyInsightSignal {
-                        wave: data_current_to_consume.to_vec(),
-                        length: SAMPLES_PER_CYCLE,
-                        calc_freq: false,
-                        signal_type: MetrologyInsightSignalType::Current,
-                        adc_factor: current_adc_factor,
-                        adc_scale: current_adc_scale,
-                        ..Default::default()
+                    let current_frame = SampleFrame {
+                        channel: MetrologyInsightSignalType::Current,
+                        samples: data_current_to_consume,
+                        sample_rate: ADC_SAMPLE_SECONDS,
+                        timestamp_us,
+                        sequence,
                     };
 
                     let mut c_insight = consumer_insight.lock().unwrap();
 
-                    c_insight.process_and_update_metrics(&mut voltage_signal, &mut current_signal);
+                    // Las tramas del driver son consecutivas: se segmentan en ciclos completos
+                    if let Err(err) = c_insight.push_samples(&voltage_frame, &current_frame) {
+                        log::error!("Error pushing samples: {}", err);
+                    }
 
                     if tx_process_to_print.send(()).is_err() {
                         log::error!("Error: Receiver has dropped");
@@ -1,10 +1,25 @@
 pub mod metrology_insight;
+pub use metrology_insight::calibration::*;
+pub use metrology_insight::creep::*;
 pub use metrology_insight::energy::*;
+pub use metrology_insight::error::*;
+pub use metrology_insight::filter::*;
+pub use metrology_insight::frequency::*;
 pub use metrology_insight::generate_signal::*;
+pub use metrology_insight::harmonic_groups::*;
 pub use metrology_insight::harmonics::*;
+pub use metrology_insight::integrator::*;
+pub use metrology_insight::offset::*;
 pub use metrology_insight::phase::*;
 pub use metrology_insight::power::*;
 pub use metrology_insight::print::*;
+pub use metrology_insight::quality::*;
+pub use metrology_insight::register::*;
+pub use metrology_insight::response::*;
+pub use metrology_insight::sensor::*;
 pub use metrology_insight::signal::*;
+pub use metrology_insight::stream::*;
+pub use metrology_insight::temperature::*;
+pub use metrology_insight::timebase::*;
 pub use metrology_insight::types::*;
 pub use metrology_insight::voltage_current::*;
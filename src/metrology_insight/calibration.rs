@@ -0,0 +1,646 @@
+use crate::{
+    compute_harmonic_phasors, AdcSample, CycleSegmenter, MetrologyError, MetrologyInsightConfig,
+    MetrologyInsightSignalType, SampleFrame,
+};
+use num_complex::Complex;
+use std::path::Path;
+
+pub const CALIBRATION_FILE_VERSION: u32 = 1;
+pub const CALIBRATION_FILE_HEADER: &str = "# metrology_insight calibration";
+pub const TEMPERATURE_REFERENCE: f64 = 25.0; // Temperatura de calibración por defecto (°C)
+
+/// Errors of a sensor measured at one level of the primary signal.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CorrectionPoint {
+    pub level: f64,       // Primary RMS value (V or A)
+    pub ratio_error: f64, // Amplitude error (%): output = primary·(1 + ratio_error/100)
+    pub phase_error: f64, // Phase error (degrees, positive when the output leads the primary)
+}
+
+/// Piecewise-linear table of the errors of a sensor against the primary level.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CorrectionCurve {
+    points: Vec<CorrectionPoint>, // Sorted by level, without repeated levels
+}
+
+/// Drift of a channel with temperature, as polynomials of the distance to the calibration temperature.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TemperatureCompensation {
+    pub reference: f64,  // Temperature at which the drift is zero (°C)
+    pub gain: Vec<f64>,  // Relative gain drift: output = input·(1 + g1·ΔT + g2·ΔT² + ...)
+    pub phase: Vec<f64>, // Phase lead drift (degrees): p1·ΔT + p2·ΔT² + ...
+}
+
+impl Default for TemperatureCompensation {
+    fn default() -> Self {
+        Self {
+            reference: TEMPERATURE_REFERENCE,
+            gain: vec![],
+            phase: vec![],
+        }
+    }
+}
+
+/*
+* @brief Evaluate a polynomial without constant term.
+* @param coefficients Coefficients of x, x², x³, ...
+* @param x Variable
+* @return c1·x + c2·x² + ...
+*/
+fn polynomial(coefficients: &[f64], x: f64) -> f64 {
+    coefficients.iter().rev().fold(0.0, |acc, c| (acc + c) * x)
+}
+
+impl TemperatureCompensation {
+    pub fn is_empty(&self) -> bool {
+        self.gain.is_empty() && self.phase.is_empty()
+    }
+
+    /*
+     * @brief Relative gain drift at a temperature.
+     * @param temperature Temperature in °C
+     * @return Output over input relative to the calibration temperature (1 = no drift)
+     */
+    pub fn gain_drift(&self, temperature: f64) -> f64 {
+        1.0 + polynomial(&self.gain, temperature - self.reference)
+    }
+
+    /*
+     * @brief Phase drift at a temperature.
+     * @param temperature Temperature in °C
+     * @return Extra phase lead in degrees (0 = no drift)
+     */
+    pub fn phase_drift(&self, temperature: f64) -> f64 {
+        polynomial(&self.phase, temperature - self.reference)
+    }
+}
+
+/// Calibration coefficients of one channel.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChannelCalibration {
+    pub gain: f64,                            // Correction of the conversion factor (1 = none)
+    pub offset: f64,                          // ADC code of a null input, subtracted before the conversion
+    pub phase_deg: f64,                       // Phase lead of the channel at the nominal frequency (degrees)
+    pub curve: CorrectionCurve,               // Sensor errors that depend on the level (empty = none)
+    pub temperature: TemperatureCompensation, // Drift with temperature (empty = none)
+}
+
+impl Default for ChannelCalibration {
+    fn default() -> Self {
+        Self {
+            gain: 1.0,
+            offset: 0.0,
+            phase_deg: 0.0,
+            curve: CorrectionCurve::default(),
+            temperature: TemperatureCompensation::default(),
+        }
+    }
+}
+
+impl CorrectionCurve {
+    /*
+     * @brief Build a table from its points.
+     * @param points Measured errors, in any order
+     * @return Table sorted by level, or InvalidParameter if a point is not finite, a level is negative or
+     *         repeated, or an amplitude error reaches -100 %
+     */
+    pub fn new(mut points: Vec<CorrectionPoint>) -> Result<Self, MetrologyError> {
+        let valid = |p: &CorrectionPoint| {
+            p.level.is_finite()
+                && p.level >= 0.0
+                && p.ratio_error.is_finite()
+                && p.ratio_error > -100.0
+                && p.phase_error.is_finite()
+        };
+        if !points.iter().all(valid) {
+            return Err(MetrologyError::InvalidParameter("correction point"));
+        }
+
+        points.sort_by(|a, b| a.level.total_cmp(&b.level));
+        if points.windows(2).any(|w| w[0].level == w[1].level) {
+            return Err(MetrologyError::InvalidParameter("correction point"));
+        }
+
+        Ok(Self { points })
+    }
+
+    /*
+     * @brief Points of the table, sorted by level.
+     */
+    pub fn points(&self) -> &[CorrectionPoint] {
+        &self.points
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    /*
+     * @brief Errors of the sensor at a level.
+     * @param level Primary RMS value (V or A)
+     * @return (amplitude error in %, phase error in degrees); linear between points, those of the first or
+     *         last point outside the table, and zero for an empty table
+     */
+    pub fn errors_at(&self, level: f64) -> (f64, f64) {
+        let (Some(first), Some(last)) = (self.points.first(), self.points.last()) else {
+            return (0.0, 0.0);
+        };
+
+        match self.points.iter().position(|p| p.level >= level) {
+            Some(0) => (first.ratio_error, first.phase_error),
+            Some(i) => {
+                let (low, high) = (&self.points[i - 1], &self.points[i]);
+                let t = (level - low.level) / (high.level - low.level);
+                (
+                    low.ratio_error + t * (high.ratio_error - low.ratio_error),
+                    low.phase_error + t * (high.phase_error - low.phase_error),
+                )
+            }
+            None => (last.ratio_error, last.phase_error),
+        }
+    }
+}
+
+impl ChannelCalibration {
+    /*
+     * @brief Gain that corrects the conversion at a level and a temperature.
+     * @param level Primary RMS value (V or A)
+     * @param temperature Temperature in °C, or None to leave the drift uncompensated
+     * @return Calibrated gain divided by the amplitude error of the sensor at that level and by the drift
+     */
+    pub fn gain_at(&self, level: f64, temperature: Option<f64>) -> f64 {
+        let (ratio_error, _) = self.curve.errors_at(level);
+        let drift = temperature.map_or(1.0, |t| self.temperature.gain_drift(t));
+        self.gain / ((1.0 + ratio_error / 100.0) * drift)
+    }
+
+    /*
+     * @brief Phase lead of the channel at a level and a temperature.
+     * @param level Primary RMS value (V or A)
+     * @param temperature Temperature in °C, or None to leave the drift uncompensated
+     * @return Calibrated phase plus the phase error of the sensor at that level and the drift (degrees)
+     */
+    pub fn phase_at(&self, level: f64, temperature: Option<f64>) -> f64 {
+        let (_, phase_error) = self.curve.errors_at(level);
+        let drift = temperature.map_or(0.0, |t| self.temperature.phase_drift(t));
+        self.phase_deg + phase_error + drift
+    }
+}
+
+/*
+* @brief Parse a point of a correction curve.
+* @param value Text after the '=' sign: level, amplitude error and phase error separated by commas
+* @param key Key, for the error message
+* @return Parsed point
+*/
+fn parse_point(value: &str, key: &str) -> Result<CorrectionPoint, MetrologyError> {
+    let fields: Vec<&str> = value.split(',').collect();
+    if fields.len() != 3 {
+        return Err(MetrologyError::CalibrationFile(format!("invalid value for {}", key)));
+    }
+
+    Ok(CorrectionPoint {
+        level: parse_value(fields[0], key)?,
+        ratio_error: parse_value(fields[1], key)?,
+        phase_error: parse_value(fields[2], key)?,
+    })
+}
+
+/*
+* @brief Parse the coefficients of a polynomial.
+* @param value Text after the '=' sign: coefficients separated by commas, lowest degree first
+* @param key Key, for the error message
+* @return Parsed coefficients
+*/
+fn parse_coefficients(value: &str, key: &str) -> Result<Vec<f64>, MetrologyError> {
+    let coefficients: Vec<f64> = value
+        .split(',')
+        .map(|c| parse_value(c, key))
+        .collect::<Result<_, _>>()?;
+    if coefficients.iter().any(|c| !c.is_finite()) {
+        return Err(MetrologyError::CalibrationFile(format!("invalid value for {}", key)));
+    }
+    Ok(coefficients)
+}
+
+/*
+* @brief Format the coefficients of a polynomial for the calibration file.
+* @param coefficients Coefficients, lowest degree first
+* @return Coefficients separated by commas
+*/
+fn format_coefficients(coefficients: &[f64]) -> String {
+    coefficients.iter().map(|c| c.to_string()).collect::<Vec<_>>().join(",")
+}
+
+/// Calibration of the voltage and current channels, as stored in the calibration file.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Calibration {
+    pub voltage: ChannelCalibration,
+    pub current: ChannelCalibration,
+}
+
+/*
+* @brief CRC-32 (IEEE 802.3, reflected polynomial 0xEDB88320) of a buffer.
+* @param data Bytes to check
+* @return Checksum
+*/
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+/*
+* @brief Parse the value of a key of the calibration file.
+* @param value Text after the '=' sign
+* @param key Key, for the error message
+* @return Parsed value
+*/
+fn parse_value<T: core::str::FromStr>(value: &str, key: &str) -> Result<T, MetrologyError> {
+    value
+        .trim()
+        .parse()
+        .map_err(|_| MetrologyError::CalibrationFile(format!("invalid value for {}", key)))
+}
+
+impl Calibration {
+    /*
+     * @brief Coefficients of the channel that carries a type of signal.
+     * @param signal_type Type of the signal (voltage or current)
+     */
+    pub fn channel(&self, signal_type: MetrologyInsightSignalType) -> &ChannelCalibration {
+        match signal_type {
+            MetrologyInsightSignalType::Voltage => &self.voltage,
+            MetrologyInsightSignalType::Current => &self.current,
+        }
+    }
+
+    /*
+     * @brief Calibration currently set in a configuration.
+     * @param config Pointer to the MetrologyInsightConfig structure.
+     */
+    pub fn from_config(config: &MetrologyInsightConfig) -> Self {
+        Self {
+            voltage: config.voltage.calibration.clone(),
+            current: config.current.calibration.clone(),
+        }
+    }
+
+    /*
+     * @brief Set the coefficients of both channels in a configuration.
+     * @param config Pointer to the MetrologyInsightConfig structure.
+     */
+    pub fn apply(&self, config: &mut MetrologyInsightConfig) {
+        config.voltage.calibration = self.voltage.clone();
+        config.current.calibration = self.current.clone();
+    }
+
+    /*
+     * @brief Write the calibration in the text format of the calibration file.
+     * @return Text with the header, the version, one key=value line per coefficient and the CRC-32 of all of it
+     * @note Each point of a correction curve is a "<channel>.curve=level,ratio_error,phase_error" line. The
+     *       temperature compensation, when set, is written as "<channel>.temp_reference", "<channel>.temp_gain"
+     *       and "<channel>.temp_phase", the last two with the coefficients separated by commas.
+     */
+    pub fn to_file_string(&self) -> String {
+        let mut text = format!("{}\nversion={}\n", CALIBRATION_FILE_HEADER, CALIBRATION_FILE_VERSION);
+        for (name, channel) in [("voltage", &self.voltage), ("current", &self.current)] {
+            text += &format!("{}.gain={}\n", name, channel.gain);
+            text += &format!("{}.offset={}\n", name, channel.offset);
+            text += &format!("{}.phase_deg={}\n", name, channel.phase_deg);
+            for point in channel.curve.points() {
+                text += &format!(
+                    "{}.curve={},{},{}\n",
+                    name, point.level, point.ratio_error, point.phase_error
+                );
+            }
+            if !channel.temperature.is_empty() {
+                let temperature = &channel.temperature;
+                text += &format!("{}.temp_reference={}\n", name, temperature.reference);
+                text += &format!("{}.temp_gain={}\n", name, format_coefficients(&temperature.gain));
+                text += &format!("{}.temp_phase={}\n", name, format_coefficients(&temperature.phase));
+            }
+        }
+
+        let crc = crc32(text.as_bytes());
+        text + &format!("crc32={:08x}\n", crc)
+    }
+
+    /*
+     * @brief Read a calibration from the text format of the calibration file.
+     * @param text Contents of the file
+     * @return Calibration, or CalibrationFile if the checksum, the version or a coefficient is wrong
+     * @note Every coefficient must be present; unknown keys are rejected so that a newer file is not half read.
+     *       The correction curves and the temperature compensation are optional, as they are only written when set.
+     */
+    pub fn from_file_string(text: &str) -> Result<Self, MetrologyError> {
+        let crc_start = text
+            .rfind("crc32=")
+            .ok_or_else(|| MetrologyError::CalibrationFile("missing checksum".to_string()))?;
+        let expected = u32::from_str_radix(text[crc_start + 6..].trim(), 16)
+            .map_err(|_| MetrologyError::CalibrationFile("invalid checksum".to_string()))?;
+        if crc32(&text.as_bytes()[..crc_start]) != expected {
+            return Err(MetrologyError::CalibrationFile("checksum mismatch".to_string()));
+        }
+
+        let mut version = None;
+        let mut values = [None; 6];
+        let mut curves = [vec![], vec![]];
+        let mut temperatures = [TemperatureCompensation::default(), TemperatureCompensation::default()];
+        let keys = [
+            "voltage.gain",
+            "voltage.offset",
+            "voltage.phase_deg",
+            "current.gain",
+            "current.offset",
+            "current.phase_deg",
+        ];
+
+        for line in text[..crc_start].lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| MetrologyError::CalibrationFile(format!("invalid line: {}", line)))?;
+            let key = key.trim();
+
+            if key == "version" {
+                version = Some(parse_value::<u32>(value, key)?);
+            } else if key == "voltage.curve" {
+                curves[0].push(parse_point(value, key)?);
+            } else if key == "current.curve" {
+                curves[1].push(parse_point(value, key)?);
+            } else if let Some(index) = keys.iter().position(|&k| k == key) {
+                values[index] = Some(parse_value::<f64>(value, key)?);
+            } else if let Some((channel, field)) = key.split_once(".temp_") {
+                let temperature = match channel {
+                    "voltage" => &mut temperatures[0],
+                    "current" => &mut temperatures[1],
+                    _ => return Err(MetrologyError::CalibrationFile(format!("unknown key: {}", key))),
+                };
+                match field {
+                    "reference" => temperature.reference = parse_value(value, key)?,
+                    "gain" => temperature.gain = parse_coefficients(value, key)?,
+                    "phase" => temperature.phase = parse_coefficients(value, key)?,
+                    _ => return Err(MetrologyError::CalibrationFile(format!("unknown key: {}", key))),
+                }
+            } else {
+                return Err(MetrologyError::CalibrationFile(format!("unknown key: {}", key)));
+            }
+        }
+
+        match version {
+            Some(CALIBRATION_FILE_VERSION) => {}
+            Some(other) => {
+                return Err(MetrologyError::CalibrationFile(format!(
+                    "unsupported version {}",
+                    other
+                )))
+            }
+            None => return Err(MetrologyError::CalibrationFile("missing version".to_string())),
+        }
+
+        let value = |index: usize| {
+            values[index].ok_or_else(|| MetrologyError::CalibrationFile(format!("missing {}", keys[index])))
+        };
+        let [voltage_temperature, current_temperature] = temperatures;
+        let [voltage_curve, current_curve] = curves.map(|points| {
+            CorrectionCurve::new(points).map_err(|_| MetrologyError::CalibrationFile("invalid curve".to_string()))
+        });
+
+        Ok(Self {
+            voltage: ChannelCalibration {
+                gain: value(0)?,
+                offset: value(1)?,
+                phase_deg: value(2)?,
+                curve: voltage_curve?,
+                temperature: voltage_temperature,
+            },
+            current: ChannelCalibration {
+                gain: value(3)?,
+                offset: value(4)?,
+                phase_deg: value(5)?,
+                curve: current_curve?,
+                temperature: current_temperature,
+            },
+        })
+    }
+
+    /*
+     * @brief Load the calibration file.
+     * @param path Path of the file
+     * @return Calibration, or CalibrationFile if the file cannot be read or is not valid
+     */
+    pub fn load(path: &Path) -> Result<Self, MetrologyError> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|err| MetrologyError::CalibrationFile(format!("{}: {}", path.display(), err)))?;
+        Self::from_file_string(&text)
+    }
+
+    /*
+     * @brief Save the calibration file.
+     * @param path Path of the file, replaced if it exists
+     * @return Ok, or CalibrationFile if the file cannot be written
+     */
+    pub fn save(&self, path: &Path) -> Result<(), MetrologyError> {
+        std::fs::write(path, self.to_file_string())
+            .map_err(|err| MetrologyError::CalibrationFile(format!("{}: {}", path.display(), err)))
+    }
+}
+
+/// Reference values applied to the meter during the calibration.
+#[derive(Debug, Clone, Copy)]
+pub struct CalibrationReference {
+    pub voltage_rms: f64,  // Reference voltage on the primary side (V RMS)
+    pub current_rms: f64,  // Reference current on the primary side (A RMS)
+    pub power_factor: f64, // Reference power factor, current lagging (1 for a resistive load)
+}
+
+/// Sums of one channel over the calibration cycles.
+#[derive(Debug, Clone, Copy, Default)]
+struct ChannelSums {
+    sum: f64,     // Σx (ADC counts)
+    squares: f64, // Σx² (ADC counts²)
+}
+
+/// Guided calibration: accumulates whole cycles under a known reference and computes the coefficients.
+#[derive(Clone)]
+pub struct CalibrationRoutine {
+    config: MetrologyInsightConfig,  // Conversion factors the coefficients are computed for
+    reference: CalibrationReference, // Values applied to the meter
+    required_samples: usize,         // Samples to accumulate
+    segmenter: CycleSegmenter,       // Cuts the stream in whole cycles
+    voltage: ChannelSums,            // Sums of the voltage cycles
+    current: ChannelSums,            // Sums of the current cycles
+    samples: usize,                  // Samples in the accumulated cycles
+    phasor_ratio: Complex<f64>,      // Σ I1/V1 over the cycles
+}
+
+impl CalibrationRoutine {
+    /*
+     * @brief Start a calibration.
+     * @param config Configuration whose conversion factors will be corrected
+     * @param reference Values applied to the meter during the calibration
+     * @param seconds Time to accumulate
+     * @return Routine, or InvalidParameter if the reference, the duration or a transformer ratio are not valid
+     */
+    pub fn new(
+        config: &MetrologyInsightConfig,
+        reference: CalibrationReference,
+        seconds: f64,
+    ) -> Result<Self, MetrologyError> {
+        if reference.voltage_rms <= 0.0 {
+            return Err(MetrologyError::InvalidParameter("voltage_rms"));
+        }
+        if reference.current_rms <= 0.0 {
+            return Err(MetrologyError::InvalidParameter("current_rms"));
+        }
+        if !(0.0..=1.0).contains(&reference.power_factor) {
+            return Err(MetrologyError::InvalidParameter("power_factor"));
+        }
+        if seconds <= 0.0 {
+            return Err(MetrologyError::InvalidParameter("seconds"));
+        }
+        config.voltage.transformer.validate()?;
+        config.current.transformer.validate()?;
+
+        Ok(Self {
+            config: config.clone(),
+            reference,
+            required_samples: (seconds * config.adc_samples_seconds).ceil() as usize,
+            segmenter: CycleSegmenter::new(),
+            voltage: ChannelSums::default(),
+            current: ChannelSums::default(),
+            samples: 0,
+            phasor_ratio: Complex::new(0.0, 0.0),
+        })
+    }
+
+    /*
+     * @brief Add the next pair of frames acquired under the reference.
+     * @param voltage Voltage frame
+     * @param current Current frame acquired at the same instants
+     * @return Ok, or the reason the frames were rejected
+     * @note Frames pushed once the routine is complete are ignored.
+     */
+    pub fn push<T: AdcSample>(
+        &mut self,
+        voltage: &SampleFrame<T>,
+        current: &SampleFrame<T>,
+    ) -> Result<(), MetrologyError> {
+        if self.is_complete() {
+            return Ok(());
+        }
+
+        self.segmenter.push(voltage, current, &self.config)?;
+
+        let fs = self.config.adc_samples_seconds;
+        while let Some(cycle) = self.segmenter.next_cycle(&self.config) {
+            let len = cycle.voltage.len() as f64;
+            let mean_v = cycle.voltage.iter().sum::<f64>() / len;
+            let mean_i = cycle.current.iter().sum::<f64>() / len;
+
+            // Fasores de la fundamental sin la componente continua, sobre el mismo ciclo en ambos canales
+            let ac_v: Vec<f64> = cycle.voltage.iter().map(|x| x - mean_v).collect();
+            let ac_i: Vec<f64> = cycle.current.iter().map(|x| x - mean_i).collect();
+            let v1 = compute_harmonic_phasors(&ac_v, fs / len, fs, 1)?[1];
+            let i1 = compute_harmonic_phasors(&ac_i, fs / len, fs, 1)?[1];
+            if v1.norm() > 0.0 {
+                self.phasor_ratio += i1 / v1;
+            }
+
+            for (sums, samples) in [(&mut self.voltage, &cycle.voltage), (&mut self.current, &cycle.current)] {
+                sums.sum += samples.iter().sum::<f64>();
+                sums.squares += samples.iter().map(|x| x * x).sum::<f64>();
+            }
+            self.samples += cycle.voltage.len();
+        }
+
+        Ok(())
+    }
+
+    /*
+     * @brief Fraction of the calibration time already accumulated.
+     * @return 0 to 1
+     */
+    pub fn progress(&self) -> f64 {
+        (self.samples as f64 / self.required_samples.max(1) as f64).min(1.0)
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.samples >= self.required_samples
+    }
+
+    /*
+     * @brief Compute the coefficients from the accumulated cycles.
+     * @return Calibration, or BufferTooShort if the calibration time has not been reached, or NoFundamental
+     * @note The offset is the mean of each channel. The gain makes the AC RMS value, converted with the factors
+     *       of the configuration, match the reference. The phase is the lead of the current over the reference
+     *       angle, minus the sampling skew already configured; the voltage is the phase reference.
+     */
+    pub fn finish(&self) -> Result<Calibration, MetrologyError> {
+        if !self.is_complete() {
+            return Err(MetrologyError::BufferTooShort {
+                required: self.required_samples,
+                actual: self.samples,
+            });
+        }
+
+        let n = self.samples as f64;
+        let channel = |sums: &ChannelSums, signal_type: MetrologyInsightSignalType, reference: f64| {
+            let mean = sums.sum / n;
+            let rms_counts = (sums.squares / n - mean * mean).max(0.0).sqrt();
+            let config = self.config.channel(signal_type);
+            let scale = match signal_type {
+                MetrologyInsightSignalType::Voltage => config.adc_factor,
+                MetrologyInsightSignalType::Current => config.adc_factor * config.adc_scale,
+            } * config.transformer.ratio();
+            let measured = rms_counts * scale.abs();
+
+            if measured <= f64::EPSILON {
+                return Err(MetrologyError::NoFundamental);
+            }
+
+            Ok(ChannelCalibration {
+                gain: reference / measured,
+                offset: mean,
+                ..Default::default()
+            })
+        };
+
+        let voltage = channel(
+            &self.voltage,
+            MetrologyInsightSignalType::Voltage,
+            self.reference.voltage_rms,
+        )?;
+        let mut current = channel(
+            &self.current,
+            MetrologyInsightSignalType::Current,
+            self.reference.current_rms,
+        )?;
+
+        if self.phasor_ratio.norm() <= f64::EPSILON {
+            return Err(MetrologyError::NoFundamental);
+        }
+
+        // Adelanto medido menos el de la referencia (corriente retrasada acos(PF)) y el desfase ya configurado
+        let nominal = self.config.default_nominal_frequency();
+        let measured_lead = self.phasor_ratio.arg().to_degrees();
+        let reference_lead = -self.reference.power_factor.acos().to_degrees();
+        let skew_deg = (self.config.current.skew_us - self.config.voltage.skew_us) * 1e-6 * nominal * 360.0;
+        let error = measured_lead - reference_lead - skew_deg;
+        current.phase_deg = (error + 180.0).rem_euclid(360.0) - 180.0;
+
+        Ok(Calibration { voltage, current })
+    }
+}
@@ -0,0 +1,175 @@
+use crate::{calculate_rms, EnergyTimeBase, MetrologyInsightSignal, MetrologyInsightSocket};
+
+pub const STARTING_CURRENT: f64 = 0.02; // 0,4 % de Ib = 5 A (IEC 62053-21, clase 1, conexión directa)
+pub const NO_LOAD_POWER: f64 = 1.0; // Potencia aparente mínima para contar energía (VA)
+pub const CREEP_HYSTERESIS: f64 = 0.1; // El medidor se detiene al 90 % de los umbrales de arranque
+pub const CREEP_QUALIFY_SEC: f64 = 0.1; // Tiempo que debe mantenerse una condición para cambiar de estado (s)
+
+/// Thresholds of the no-load (anti-creep) condition, below which the energy registers stop (IEC 62053-21/23).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AntiCreepConfig {
+    pub starting_current: f64, // RMS current the meter starts at (A); 0 disables the current check
+    pub no_load_power: f64,    // Apparent power the meter starts at (VA); 0 disables the power check
+    pub hysteresis: f64,       // Fraction below the thresholds at which a running meter stops (0.1 = 90 %)
+    pub qualify_sec: f64,      // Time a condition must hold before the state changes (s)
+}
+
+impl Default for AntiCreepConfig {
+    fn default() -> Self {
+        Self {
+            starting_current: STARTING_CURRENT,
+            no_load_power: NO_LOAD_POWER,
+            hysteresis: CREEP_HYSTERESIS,
+            qualify_sec: CREEP_QUALIFY_SEC,
+        }
+    }
+}
+
+impl AntiCreepConfig {
+    /*
+     * @brief Configuration without anti-creep: energy is accumulated at any load.
+     */
+    pub fn disabled() -> Self {
+        Self {
+            starting_current: 0.0,
+            no_load_power: 0.0,
+            hysteresis: 0.0,
+            qualify_sec: 0.0,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.starting_current > 0.0 || self.no_load_power > 0.0
+    }
+}
+
+/// Whether the meter is accumulating energy or held in the no-load condition.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CreepState {
+    #[default]
+    NoLoad, // Below the starting thresholds: energy registers stopped
+    Running, // Above the starting thresholds: energy registers accumulating
+}
+
+impl CreepState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CreepState::NoLoad => "No load",
+            CreepState::Running => "Running",
+        }
+    }
+}
+
+/// No-load state of the meter, carried from frame to frame.
+#[derive(Debug, Clone, Default)]
+pub struct CreepDetector {
+    state: CreepState, // Qualified state
+    pending: f64,      // Time the opposite condition has held (s)
+}
+
+impl CreepDetector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn state(&self) -> CreepState {
+        self.state
+    }
+
+    /*
+     * @brief Return to the no-load state, as after power-up.
+     */
+    pub fn reset(&mut self) {
+        self.state = CreepState::NoLoad;
+        self.pending = 0.0;
+    }
+
+    /*
+     * @brief Update the state with the load of the last frame.
+     * @param config Anti-creep thresholds
+     * @param current_rms RMS current of the frame (A)
+     * @param apparent_power Apparent power of the frame (VA)
+     * @param elapsed Time since the previous update (s)
+     * @return State after the frame
+     * @note A stopped meter starts when both values reach their thresholds; a running one stops when either
+     *       falls below its threshold reduced by the hysteresis. Either way the condition must hold for
+     *       qualify_sec before the state changes.
+     */
+    pub fn update(
+        &mut self,
+        config: &AntiCreepConfig,
+        current_rms: f64,
+        apparent_power: f64,
+        elapsed: f64,
+    ) -> CreepState {
+        if !config.is_enabled() {
+            self.state = CreepState::Running;
+            self.pending = 0.0;
+            return self.state;
+        }
+
+        let scale = match self.state {
+            CreepState::NoLoad => 1.0,
+            CreepState::Running => 1.0 - config.hysteresis.clamp(0.0, 1.0),
+        };
+        let loaded = current_rms.abs() >= config.starting_current * scale
+            && apparent_power.abs() >= config.no_load_power * scale;
+        let target = if loaded {
+            CreepState::Running
+        } else {
+            CreepState::NoLoad
+        };
+
+        if target == self.state {
+            self.pending = 0.0;
+        } else {
+            self.pending += elapsed.max(0.0);
+            if self.pending >= config.qualify_sec {
+                self.state = target;
+                self.pending = 0.0;
+            }
+        }
+
+        self.state
+    }
+}
+
+/*
+* @brief RMS value of the last frame of a signal, without the averaging of the socket.
+* @param signal Signal of the socket
+* @param adc_samples_second Number of ADC samples per second.
+*/
+fn frame_rms(signal: &MetrologyInsightSignal, adc_samples_second: f64) -> f64 {
+    calculate_rms(
+        &signal.real_wave,
+        signal.length_cycle,
+        signal.freq_zc,
+        adc_samples_second,
+    )
+}
+
+/*
+* @brief Update the no-load state of the socket with the current and apparent power of its last frame.
+* @param socket Pointer to the MetrologyInsightSocket structure.
+* @param detector No-load state carried between frames.
+* @param config Anti-creep thresholds
+* @param time_base Acquisition time of the last frame registered, not yet advanced to this frame.
+* @param adc_samples_second Number of ADC samples per second.
+* @note The values of the frame are used instead of the averaged ones of the socket, so that the registers
+*       stop as soon as the load is removed. The qualifying time runs on the acquisition time of the energy
+*       registers, so a gap between frames counts as elapsed time.
+*/
+pub fn update_creep_state(
+    socket: &mut MetrologyInsightSocket,
+    detector: &mut CreepDetector,
+    config: &AntiCreepConfig,
+    time_base: &EnergyTimeBase,
+    adc_samples_second: f64,
+) {
+    let elapsed = time_base.elapsed(&socket.voltage_signal, adc_samples_second);
+
+    let voltage_rms = frame_rms(&socket.voltage_signal, adc_samples_second);
+    let current_rms = frame_rms(&socket.current_signal, adc_samples_second);
+
+    socket.creep_state = detector.update(config, current_rms, voltage_rms * current_rms, elapsed);
+}
@@ -1,93 +1,247 @@
-use crate::{ActiveEnergyMetrics, EnergyMetrics, MetrologyInsightSocket, ReactiveEnergyMetrics};
+use crate::{
+    register_rollover, ActiveEnergyMetrics, CreepState, DirectionalEnergyMetrics, EnergyGapPolicy, EnergyMetrics,
+    EnergyPowers, EnergyTimeBase, MetrologyInsightConfig, MetrologyInsightSocket, ReactiveEnergyMetrics,
+};
 
-const JOULES_TO_KWH: f64 = 1.0 / (3600.0 * 1000.0); // Constant to convert Joules to kWh (1 kWh = 3.6e6 J)
+/// Quadrant of the power plane (IEC 62053-23): active power on the X axis, reactive power on the Y axis.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Quadrant {
+    Q1, // Import active, import reactive (inductive)
+    Q2, // Export active, import reactive (capacitive)
+    Q3, // Export active, export reactive (inductive)
+    Q4, // Import active, export reactive (capacitive)
+}
 
-fn elapsed_time_seconds(socket: &MetrologyInsightSocket, adc_samples_second: f64) -> Option<f64> {
-    let samples_count = socket.voltage_signal.real_wave.len() as f64;
-    if samples_count == 0.0 || adc_samples_second == 0.0 {
-        None
-    } else {
-        let sample_duration = 1.0 / adc_samples_second; // duración de 1 muestra
-        Some(samples_count * sample_duration)
+/*
+* @brief Get the quadrant of the power plane.
+* @param real_power Real power in watts
+* @param reactive_power Signed reactive power in VAR
+* @return Quadrant, or None when there is no power flow
+* @note Values on the axes are assigned to the quadrant that contains the positive half-axis (import side).
+*/
+fn power_quadrant(real_power: f64, reactive_power: f64) -> Option<Quadrant> {
+    if real_power == 0.0 && reactive_power == 0.0 {
+        return None;
+    }
+
+    let quadrant = match (real_power >= 0.0, reactive_power >= 0.0) {
+        (true, true) => Quadrant::Q1,
+        (false, true) => Quadrant::Q2,
+        (false, false) => Quadrant::Q3,
+        (true, false) => Quadrant::Q4,
+    };
+
+    Some(quadrant)
+}
+
+impl Quadrant {
+    /*
+     * @brief Index of the quadrant in the register arrays.
+     */
+    fn index(self) -> usize {
+        match self {
+            Quadrant::Q1 => 0,
+            Quadrant::Q2 => 1,
+            Quadrant::Q3 => 2,
+            Quadrant::Q4 => 3,
+        }
     }
 }
 
 /*
-* @brief Calculate the active and reactive energy by quadrant.
-* @param socket Pointer to the MetrologyInsightSocket structure.
-* @param adc_samples_second Number of ADC samples per second.
-* @note This function calculates the active and reactive energy for each quadrant
+* @brief Calculate the active energy by quadrant.
+* @param energy Energy registers of the socket.
+* @param real_power Real power in watts
+* @param reactive_power Signed reactive power in VAR
+* @param elapsed_time Time the power was held (s)
+* @param rollover_ws Value at which the registers roll over (see register_rollover).
+* @note Quadrant registers hold energy magnitudes; the import/export split is done by the register getters.
 */
-fn active_energy_by_quadrant(socket: &mut MetrologyInsightSocket, adc_samples_second: f64) {
-    if let Some(elapsed_time) = elapsed_time_seconds(socket, adc_samples_second) {
-        let energy_joules = socket.power_metrics.real_power * elapsed_time;
-        let energy_kwh = energy_joules * JOULES_TO_KWH;
-
-        if socket.power_metrics.real_power > 0.0 {
-            if socket.power_metrics.reactive_power > 0.0 {
-                socket.energy_metrics.active.q1 += energy_kwh;
-            } else if socket.power_metrics.reactive_power < 0.0 {
-                socket.energy_metrics.active.q4 += energy_kwh;
-            }
-        } else if socket.power_metrics.real_power < 0.0 {
-            if socket.power_metrics.reactive_power > 0.0 {
-                socket.energy_metrics.active.q2 -= energy_kwh * (-1.0);
-            } else if socket.power_metrics.reactive_power < 0.0 {
-                socket.energy_metrics.active.q3 -= energy_kwh * (-1.0);
-            }
-        }
+fn active_energy_by_quadrant(
+    energy: &mut EnergyMetrics,
+    real_power: f64,
+    reactive_power: f64,
+    elapsed_time: f64,
+    rollover_ws: u64,
+) {
+    let energy_ws = (real_power * elapsed_time).abs();
+
+    if let Some(quadrant) = power_quadrant(real_power, reactive_power) {
+        energy.active.registers[quadrant.index()].add(energy_ws, rollover_ws);
     }
 }
 
 /*
 * @brief Calculate the reactive energy by quadrant.
-* @param socket Pointer to the MetrologyInsightSocket structure.
-* @param adc_samples_second Number of ADC samples per second.
-* @note This function calculates the reactive energy for each quadrant.
+* @param energy Energy registers of the socket.
+* @param real_power Real power in watts
+* @param reactive_power Signed reactive power in VAR
+* @param elapsed_time Time the power was held (s)
+* @param rollover_ws Value at which the registers roll over (see register_rollover).
+* @note Quadrant registers hold energy magnitudes; inductive is Q1 + Q3 and capacitive is Q2 + Q4.
 */
-fn reactive_energy_by_quadrant(socket: &mut MetrologyInsightSocket, adc_samples_second: f64) {
-    if let Some(elapsed_time) = elapsed_time_seconds(socket, adc_samples_second) {
-        let energy_joules = socket.power_metrics.reactive_power * elapsed_time;
-        let energy_kwh = energy_joules * JOULES_TO_KWH;
-
-        if socket.power_metrics.real_power > 0.0 {
-            if socket.power_metrics.reactive_power > 0.0 {
-                socket.energy_metrics.reactive.q1 += energy_kwh;
-            } else if socket.power_metrics.reactive_power < 0.0 {
-                socket.energy_metrics.reactive.q4 -= energy_kwh * (-1.0);
-            }
-        } else if socket.power_metrics.real_power < 0.0 {
-            if socket.power_metrics.reactive_power > 0.0 {
-                socket.energy_metrics.reactive.q2 += energy_kwh;
-            } else if socket.power_metrics.reactive_power < 0.0 {
-                socket.energy_metrics.reactive.q3 -= energy_kwh * (-1.0);
-            }
-        }
+fn reactive_energy_by_quadrant(
+    energy: &mut EnergyMetrics,
+    real_power: f64,
+    reactive_power: f64,
+    elapsed_time: f64,
+    rollover_ws: u64,
+) {
+    let energy_vars = (reactive_power * elapsed_time).abs();
+
+    if let Some(quadrant) = power_quadrant(real_power, reactive_power) {
+        energy.reactive.registers[quadrant.index()].add(energy_vars, rollover_ws);
     }
 }
 
 /*
 * @brief Calculate the active and reactive energy by quadrant.
-* @param socket Pointer to the MetrologyInsightSocket structure.
-* @param adc_samples_second Number of ADC samples per second.
+* @param energy Energy registers of the socket.
+* @param real_power Real power in watts
+* @param reactive_power Signed reactive power in VAR
+* @param elapsed_time Time the power was held (s)
+* @param rollover_ws Value at which the registers roll over (see register_rollover).
 * @note This function calculates the active and reactive energy for each quadrant.
 */
-pub fn update_energy_by_quadrant(socket: &mut MetrologyInsightSocket, adc_samples_second: f64) {
-    active_energy_by_quadrant(socket, adc_samples_second);
-    reactive_energy_by_quadrant(socket, adc_samples_second);
+pub fn update_energy_by_quadrant(
+    energy: &mut EnergyMetrics,
+    real_power: f64,
+    reactive_power: f64,
+    elapsed_time: f64,
+    rollover_ws: u64,
+) {
+    active_energy_by_quadrant(energy, real_power, reactive_power, elapsed_time, rollover_ws);
+    reactive_energy_by_quadrant(energy, real_power, reactive_power, elapsed_time, rollover_ws);
 }
 
 /*
-* @brief Calculate the total energy.
+* @brief Register an energy by the direction of a power flow.
+* @param metrics Imported and exported registers
+* @param power Power to register (W or VA)
+* @param direction_power Power whose sign gives the direction (positive: import)
+* @param elapsed_time Time the power was held (s)
+* @param rollover_ws Value at which the registers roll over (see register_rollover).
+*/
+fn directional_energy(
+    metrics: &mut DirectionalEnergyMetrics,
+    power: f64,
+    direction_power: f64,
+    elapsed_time: f64,
+    rollover_ws: u64,
+) {
+    let index = if direction_power >= 0.0 { 0 } else { 1 };
+    metrics.registers[index].add((power * elapsed_time).abs(), rollover_ws);
+}
+
+/*
+* @brief Register the energy of every register for a time at constant powers.
+* @param energy Energy registers of the socket.
+* @param power Powers held during the time
+* @param elapsed_time Time the powers were held (s)
+* @param rollover_ws Value at which the registers roll over (see register_rollover).
+* @note Apparent energy follows the direction of the active power; fundamental and harmonic energy follow
+*       their own powers, since harmonic power can flow against the fundamental.
+*/
+fn register_energy(energy: &mut EnergyMetrics, power: &EnergyPowers, elapsed_time: f64, rollover_ws: u64) {
+    update_energy_by_quadrant(
+        energy,
+        power.real_power,
+        power.reactive_power,
+        elapsed_time,
+        rollover_ws,
+    );
+    directional_energy(
+        &mut energy.apparent,
+        power.apparent_power,
+        power.real_power,
+        elapsed_time,
+        rollover_ws,
+    );
+    directional_energy(
+        &mut energy.fundamental,
+        power.fundamental_power,
+        power.fundamental_power,
+        elapsed_time,
+        rollover_ws,
+    );
+    directional_energy(
+        &mut energy.harmonic,
+        power.harmonic_power,
+        power.harmonic_power,
+        elapsed_time,
+        rollover_ws,
+    );
+}
+
+/*
+* @brief Directional registers with their views refreshed.
+* @param metrics Imported and exported registers
+*/
+fn directional_views(metrics: &DirectionalEnergyMetrics) -> DirectionalEnergyMetrics {
+    DirectionalEnergyMetrics {
+        imported: metrics.imported(),
+        exported: metrics.exported(),
+        balance: metrics.balance(),
+        ..metrics.clone()
+    }
+}
+
+/*
+* @brief Register the energy of the last frame: active and reactive by quadrant, apparent, fundamental and
+*        harmonic by direction.
 * @param socket Pointer to the MetrologyInsightSocket structure.
-* @note This function calculates the total energy by summing the active and reactive energies.
+* @param time_base Acquisition time of the last frame registered, advanced to this frame.
+* @param config Pointer to the MetrologyInsightConfig structure.
+* @note Each frame is registered for the duration of its samples. The time since the previous frame that no
+*       frame covered (frames lost or rejected) is bridged at the power of the previous frame, or counted as
+*       uncovered, as set in energy_gaps; the coverage counters keep both.
+* @note Nothing is accumulated while the socket is in the no-load state (see update_creep_state).
+* @note Apparent, fundamental and harmonic energy share the time base of the quadrant registers. The last
+*       power decomposition is used when that of the frame failed.
+* @note The registers count whole watt-seconds and carry the fractions between frames; the kWh (kvarh)
+*       fields are derived from them after every update.
 */
-pub fn update_total_energy(socket: &mut MetrologyInsightSocket, adc_samples_second: f64) {
-    update_energy_by_quadrant(socket, adc_samples_second);
+pub fn update_total_energy(
+    socket: &mut MetrologyInsightSocket,
+    time_base: &mut EnergyTimeBase,
+    config: &MetrologyInsightConfig,
+) {
+    let rollover_ws = register_rollover(config.register_digits);
+    let gaps = &config.energy_gaps;
+
+    if let Some(timing) = time_base.advance(&socket.voltage_signal, config.adc_samples_seconds, gaps.tolerance_sec) {
+        socket.energy_metrics.coverage.covered_sec += timing.duration;
+
+        if timing.gap > 0.0 {
+            socket.energy_metrics.coverage.gaps += 1;
+            match time_base.last_power() {
+                Some(power) if gaps.policy == EnergyGapPolicy::Bridge && timing.gap <= gaps.max_bridge_sec => {
+                    register_energy(&mut socket.energy_metrics, &power, timing.gap, rollover_ws);
+                    socket.energy_metrics.coverage.bridged_sec += timing.gap;
+                }
+                _ => {
+                    socket.energy_metrics.coverage.uncovered_sec += timing.gap;
+                    log::warn!("Energy not registered for a gap of {:.3} s", timing.gap);
+                }
+            }
+        }
+
+        // En vacío la potencia no se registra, tampoco al rellenar el siguiente hueco
+        let power = if socket.creep_state == CreepState::Running {
+            EnergyPowers::from_socket(socket)
+        } else {
+            EnergyPowers::default()
+        };
+        register_energy(&mut socket.energy_metrics, &power, timing.duration, rollover_ws);
+        time_base.set_last_power(power);
+    }
 
     let active = &mut socket.energy_metrics.active;
     let reactive = &mut socket.energy_metrics.reactive;
 
+    [active.q1, active.q2, active.q3, active.q4] = active.registers.map(|r| r.kwh());
+    [reactive.q1, reactive.q2, reactive.q3, reactive.q4] = reactive.registers.map(|r| r.kwh());
+
     socket.energy_metrics = EnergyMetrics {
         active: ActiveEnergyMetrics {
             imported: active.imported(),
@@ -101,5 +255,9 @@ pub fn update_total_energy(socket: &mut MetrologyInsightSocket, adc_samples_seco
             balance: reactive.balance(),
             ..reactive.clone()
         },
+        apparent: directional_views(&socket.energy_metrics.apparent),
+        fundamental: directional_views(&socket.energy_metrics.fundamental),
+        harmonic: directional_views(&socket.energy_metrics.harmonic),
+        coverage: socket.energy_metrics.coverage.clone(),
     }
 }
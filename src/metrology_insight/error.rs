@@ -0,0 +1,84 @@
+use crate::MetrologyInsightSignalType;
+use core::fmt;
+
+/// Reasons why a frame or a metric could not be computed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MetrologyError {
+    BufferTooShort {
+        required: usize, // Samples needed by the operation
+        actual: usize,   // Samples available
+    },
+    LengthMismatch {
+        voltage: usize, // Samples in the voltage buffer
+        current: usize, // Samples in the current buffer
+    },
+    SignalTooLow {
+        signal_type: MetrologyInsightSignalType, // Signal that failed the check
+        amplitude: f64,                          // Peak-to-peak amplitude found (ADC counts)
+        minimum: f64,                            // Minimum amplitude required (ADC counts)
+    },
+    NoZeroCrossing,                 // No rising zero crossings to measure the frequency
+    EstimatorSettling,              // The frequency estimator has not settled yet
+    FrequencyOutOfRange(f64),       // Measured frequency outside the tolerance of every nominal frequency
+    NoFundamental,                  // The fundamental component is null
+    SpectrumUnavailable,            // The FFT could not be computed for this buffer length
+    InvalidParameter(&'static str), // Parameter out of its valid range
+    CalibrationFile(String),        // The calibration file cannot be read, written or trusted
+    TemperatureSensor(String),      // The temperature sensor cannot be read
+}
+
+impl MetrologyError {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MetrologyError::BufferTooShort { .. } => "Buffer too short",
+            MetrologyError::LengthMismatch { .. } => "Length mismatch",
+            MetrologyError::SignalTooLow { .. } => "Signal too low",
+            MetrologyError::NoZeroCrossing => "No zero crossing",
+            MetrologyError::EstimatorSettling => "Estimator settling",
+            MetrologyError::FrequencyOutOfRange(_) => "Frequency out of range",
+            MetrologyError::NoFundamental => "No fundamental",
+            MetrologyError::SpectrumUnavailable => "Spectrum unavailable",
+            MetrologyError::InvalidParameter(_) => "Invalid parameter",
+            MetrologyError::CalibrationFile(_) => "Calibration file",
+            MetrologyError::TemperatureSensor(_) => "Temperature sensor",
+        }
+    }
+}
+
+impl fmt::Display for MetrologyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MetrologyError::BufferTooShort { required, actual } => {
+                write!(
+                    f,
+                    "buffer too short: {} samples required, {} available",
+                    required, actual
+                )
+            }
+            MetrologyError::LengthMismatch { voltage, current } => {
+                write!(f, "voltage and current lengths differ: {} vs {}", voltage, current)
+            }
+            MetrologyError::SignalTooLow {
+                signal_type,
+                amplitude,
+                minimum,
+            } => {
+                let name = match signal_type {
+                    MetrologyInsightSignalType::Voltage => "voltage",
+                    MetrologyInsightSignalType::Current => "current",
+                };
+                write!(f, "no {} present: amplitude {} below {}", name, amplitude, minimum)
+            }
+            MetrologyError::NoZeroCrossing => write!(f, "no zero crossings found in the signal"),
+            MetrologyError::EstimatorSettling => write!(f, "the frequency estimator has not settled yet"),
+            MetrologyError::FrequencyOutOfRange(freq) => write!(f, "frequency out of range: {:.3} Hz", freq),
+            MetrologyError::NoFundamental => write!(f, "the fundamental component is null"),
+            MetrologyError::SpectrumUnavailable => write!(f, "the spectrum could not be computed"),
+            MetrologyError::InvalidParameter(name) => write!(f, "invalid parameter: {}", name),
+            MetrologyError::CalibrationFile(reason) => write!(f, "calibration file: {}", reason),
+            MetrologyError::TemperatureSensor(reason) => write!(f, "temperature sensor: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for MetrologyError {}
@@ -0,0 +1,520 @@
+use crate::{FilterKind, MetrologyError, MetrologyInsightConfig, MetrologyInsightSignalType, MetrologyInsightSocket};
+use core::f64::consts::PI;
+use core::fmt::Debug;
+use num_complex::Complex;
+use std::collections::VecDeque;
+
+pub const LAGRANGE_ORDER: usize = 3; // Orden del interpolador del retardo fraccionario (4 coeficientes)
+pub const DELAY_RESOLUTION: f64 = 1e-6; // Diferencia de retardo despreciable (muestras)
+
+/// Digital filter applied to the samples of a channel, frame by frame.
+pub trait SignalFilter: Send + Debug {
+    /*
+     * @brief Filter the next frame in place.
+     * @param signal Samples of the frame, contiguous with the previous frame
+     * @note The state is kept between frames; the first frame after a reset is taken as preceded by its
+     *       first sample, so the offset of the ADC does not produce a start-up transient.
+     */
+    fn process(&mut self, signal: &mut [f64]);
+
+    /*
+     * @brief Frequency response of the filter.
+     * @param freq Frequency in Hz
+     * @param adc_samples_second Number of ADC samples per second
+     * @return Complex gain
+     */
+    fn response(&self, freq: f64, adc_samples_second: f64) -> Complex<f64>;
+
+    /*
+     * @brief Delay of the filter at a frequency.
+     * @param freq Frequency in Hz
+     * @param adc_samples_second Number of ADC samples per second
+     * @return Phase delay in samples
+     */
+    fn delay(&self, freq: f64, adc_samples_second: f64) -> f64 {
+        phase_delay(self.response(freq, adc_samples_second), freq, adc_samples_second, 0.0)
+    }
+
+    /*
+     * @brief Forget the state carried between frames.
+     */
+    fn reset(&mut self) {}
+
+    /*
+     * @brief Configuration the filter was built from.
+     */
+    fn kind(&self) -> FilterKind;
+
+    /*
+     * @brief Clone the filter behind a box, so that the processing state can be cloned.
+     */
+    fn clone_box(&self) -> Box<dyn SignalFilter>;
+}
+
+impl Clone for Box<dyn SignalFilter> {
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
+}
+
+/*
+* @brief Create a filter of the chain.
+* @param kind Filter and its coefficients
+* @return Boxed filter, or InvalidParameter if the coefficients cannot give a stable filter
+*/
+pub fn build_signal_filter(kind: &FilterKind) -> Result<Box<dyn SignalFilter>, MetrologyError> {
+    match kind {
+        FilterKind::PassThrough => Ok(Box::new(PassThroughFilter)),
+        FilterKind::MovingAverage { window } => {
+            if *window == 0 {
+                return Err(MetrologyError::InvalidParameter("window"));
+            }
+            Ok(Box::new(MovingAverageFilter::new(*window)))
+        }
+        FilterKind::Fir { taps } => {
+            if taps.is_empty() || taps.iter().any(|t| !t.is_finite()) {
+                return Err(MetrologyError::InvalidParameter("taps"));
+            }
+            Ok(Box::new(FirFilter::new(taps.clone())))
+        }
+        FilterKind::Biquad { b, a } => {
+            // Triángulo de estabilidad de un polinomio de segundo orden
+            if b.iter().chain(a.iter()).any(|c| !c.is_finite()) || a[1].abs() >= 1.0 || a[0].abs() >= 1.0 + a[1] {
+                return Err(MetrologyError::InvalidParameter("biquad"));
+            }
+            Ok(Box::new(BiquadFilter::new(*b, *a)))
+        }
+    }
+}
+
+/*
+* @brief Phase delay of a frequency response, unwrapped around a reference.
+* @param response Complex gain at the frequency
+* @param freq Frequency in Hz
+* @param adc_samples_second Number of ADC samples per second
+* @param reference Expected delay in samples (e.g. the centre of a linear-phase FIR)
+* @return Delay in samples, the one closest to the reference that matches the phase
+*/
+fn phase_delay(response: Complex<f64>, freq: f64, adc_samples_second: f64, reference: f64) -> f64 {
+    let omega = 2.0 * PI * freq / adc_samples_second;
+    if omega <= 0.0 || response.norm() <= f64::EPSILON {
+        return reference;
+    }
+
+    // Fase que queda tras quitar el retardo de referencia, en (-π, π]
+    let residual = (response * Complex::from_polar(1.0, omega * reference)).arg();
+    reference - residual / omega
+}
+
+/*
+* @brief Coefficients of a Lagrange fractional delay.
+* @param delay Delay in samples (not negative)
+* @return FIR taps: zeros for the integer part, then LAGRANGE_ORDER + 1 interpolation coefficients
+* @note The interpolator is centred on the delay (fraction between 1 and 2 samples), where its error is
+*       smallest. An integer delay gives a pure delay.
+*/
+pub fn lagrange_delay_taps(delay: f64) -> Vec<f64> {
+    let delay = delay.max(0.0);
+    let shift = (delay.floor() as usize).saturating_sub(1);
+    let d = delay - shift as f64;
+
+    let mut taps = vec![0.0; shift];
+    taps.extend((0..=LAGRANGE_ORDER).map(|n| {
+        (0..=LAGRANGE_ORDER)
+            .filter(|&k| k != n)
+            .map(|k| (d - k as f64) / (n as f64 - k as f64))
+            .product::<f64>()
+    }));
+    taps
+}
+
+/*
+* @brief Second-order low-pass section (Butterworth with q = 1/√2).
+* @param cutoff Cut-off frequency in Hz
+* @param q Quality factor
+* @param adc_samples_second Number of ADC samples per second
+* @return Biquad configuration
+*/
+pub fn biquad_low_pass(cutoff: f64, q: f64, adc_samples_second: f64) -> FilterKind {
+    let w0 = 2.0 * PI * cutoff / adc_samples_second;
+    let alpha = w0.sin() / (2.0 * q);
+    let a0 = 1.0 + alpha;
+    let b0 = (1.0 - w0.cos()) / 2.0 / a0;
+
+    FilterKind::Biquad {
+        b: [b0, 2.0 * b0, b0],
+        a: [-2.0 * w0.cos() / a0, (1.0 - alpha) / a0],
+    }
+}
+
+/*
+* @brief Delay of a filter chain.
+* @param kinds Filters of the chain
+* @param freq Frequency in Hz
+* @param adc_samples_second Number of ADC samples per second
+* @return Sum of the phase delays in samples, or InvalidParameter if a filter cannot be built
+*/
+pub fn filter_chain_delay(kinds: &[FilterKind], freq: f64, adc_samples_second: f64) -> Result<f64, MetrologyError> {
+    kinds.iter().try_fold(0.0, |delay, kind| {
+        Ok(delay + build_signal_filter(kind)?.delay(freq, adc_samples_second))
+    })
+}
+
+/*
+* @brief Extra delay that aligns a channel with the other one.
+* @param config Pointer to the MetrologyInsightConfig structure.
+* @param socket Pointer to the MetrologyInsightSocket structure (nominal frequency and level of each channel)
+* @param signal_type Channel to align
+* @return Extra delay in samples, or InvalidParameter if a filter cannot be built
+* @note The delay of each channel is the one of its filter chain minus its sampling skew and its calibrated
+*       phase lead (as a time at the nominal frequency), so a channel sampled later or leading is delayed
+*       further. Both channels end aligned in time, which preserves the V/I phase relationship. A linear-phase
+*       chain is aligned at every frequency; an IIR chain only at the nominal frequency.
+* @note The phase lead includes the one of the correction curve at the last RMS value of each channel and the
+*       drift at the temperature of the socket.
+*/
+pub fn filter_compensation(
+    config: &MetrologyInsightConfig,
+    socket: &MetrologyInsightSocket,
+    signal_type: MetrologyInsightSignalType,
+) -> Result<f64, MetrologyError> {
+    let fs = config.adc_samples_seconds;
+    let freq = socket.signal(signal_type).freq_nominal;
+    let channel_delay = |signal_type: MetrologyInsightSignalType| -> Result<f64, MetrologyError> {
+        let channel = config.channel(signal_type);
+        let phase_s = if freq > 0.0 {
+            channel
+                .calibration
+                .phase_at(socket.signal(signal_type).rms, socket.temperature)
+                / (360.0 * freq)
+        } else {
+            0.0
+        };
+        Ok(filter_chain_delay(&channel.filters, freq, fs)? - (channel.skew_us * 1e-6 + phase_s) * fs)
+    };
+    let voltage = channel_delay(MetrologyInsightSignalType::Voltage)?;
+    let current = channel_delay(MetrologyInsightSignalType::Current)?;
+    let mut target = voltage.max(current);
+
+    // El interpolador necesita al menos una muestra de retardo para quedar centrado
+    if [voltage, current]
+        .iter()
+        .any(|d| target - d > DELAY_RESOLUTION && target - d < 1.0)
+    {
+        target += 1.0;
+    }
+
+    let own = match signal_type {
+        MetrologyInsightSignalType::Voltage => voltage,
+        MetrologyInsightSignalType::Current => current,
+    };
+
+    Ok(if target - own > DELAY_RESOLUTION {
+        target - own
+    } else {
+        0.0
+    })
+}
+
+/// Filters of a channel, followed by the delay line that aligns it with the other channel.
+#[derive(Debug, Clone, Default)]
+pub struct FilterChain {
+    kinds: Vec<FilterKind>,              // Configuration the chain was built from
+    filters: Vec<Box<dyn SignalFilter>>, // Filters built from the configuration
+    compensation: f64,                   // Extra delay of the channel (samples)
+    delay_line: Option<FirFilter>,       // Lagrange fractional delay of the compensation
+}
+
+impl FilterChain {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /*
+     * @brief Build the chain from the configuration, if it changed.
+     * @param kinds Filters of the chain, in order
+     * @param compensation Extra delay in samples (see filter_compensation)
+     * @return Ok, or InvalidParameter if a filter cannot be built (the chain is left unchanged)
+     */
+    pub fn configure(&mut self, kinds: &[FilterKind], compensation: f64) -> Result<(), MetrologyError> {
+        if self.kinds != kinds {
+            self.filters = kinds.iter().map(build_signal_filter).collect::<Result<_, _>>()?;
+            self.kinds = kinds.to_vec();
+        }
+
+        // La línea de retardo conserva su historia, ya que la compensación varía con la curva de corrección
+        if (self.compensation - compensation).abs() > DELAY_RESOLUTION {
+            match self.delay_line.as_mut() {
+                Some(delay_line) if compensation > 0.0 => delay_line.set_taps(lagrange_delay_taps(compensation)),
+                _ => self.delay_line = (compensation > 0.0).then(|| FirFilter::new(lagrange_delay_taps(compensation))),
+            }
+            self.compensation = compensation;
+        }
+
+        Ok(())
+    }
+
+    /*
+     * @brief Filter the next frame in place.
+     * @param signal Samples of the frame, contiguous with the previous frame
+     */
+    pub fn process(&mut self, signal: &mut [f64]) {
+        for filter in self.filters.iter_mut() {
+            filter.process(signal);
+        }
+        if let Some(delay_line) = self.delay_line.as_mut() {
+            delay_line.process(signal);
+        }
+    }
+
+    /*
+     * @brief Total delay of the chain, compensation included.
+     * @param freq Frequency in Hz
+     * @param adc_samples_second Number of ADC samples per second
+     * @return Delay in samples
+     */
+    pub fn delay(&self, freq: f64, adc_samples_second: f64) -> f64 {
+        self.filters
+            .iter()
+            .map(|filter| filter.delay(freq, adc_samples_second))
+            .sum::<f64>()
+            + self.compensation
+    }
+
+    /*
+     * @brief Forget the state of every filter.
+     */
+    pub fn reset(&mut self) {
+        for filter in self.filters.iter_mut() {
+            filter.reset();
+        }
+        if let Some(delay_line) = self.delay_line.as_mut() {
+            delay_line.reset();
+        }
+    }
+}
+
+/* ----------------- Pass-through ------------------ */
+
+/// Leaves the samples unchanged.
+#[derive(Debug, Clone, Default)]
+pub struct PassThroughFilter;
+
+impl SignalFilter for PassThroughFilter {
+    fn process(&mut self, _signal: &mut [f64]) {}
+
+    fn response(&self, _freq: f64, _adc_samples_second: f64) -> Complex<f64> {
+        Complex::new(1.0, 0.0)
+    }
+
+    fn kind(&self) -> FilterKind {
+        FilterKind::PassThrough
+    }
+
+    fn clone_box(&self) -> Box<dyn SignalFilter> {
+        Box::new(self.clone())
+    }
+}
+
+/* ----------------- FIR ------------------ */
+
+/// Finite impulse response filter, continued across contiguous frames.
+#[derive(Debug, Clone)]
+pub struct FirFilter {
+    taps: Vec<f64>,         // Coefficients, from the newest sample backwards
+    history: VecDeque<f64>, // Previous inputs, newest first
+}
+
+impl FirFilter {
+    pub fn new(taps: Vec<f64>) -> Self {
+        Self {
+            taps,
+            history: VecDeque::new(),
+        }
+    }
+
+    /*
+     * @brief Replace the coefficients, keeping the newest inputs.
+     * @param taps New coefficients, from the newest sample backwards
+     */
+    pub fn set_taps(&mut self, taps: Vec<f64>) {
+        let memory = taps.len().saturating_sub(1);
+        if self.history.len() > memory {
+            self.history.truncate(memory);
+        } else if let Some(&oldest) = self.history.back() {
+            self.history.resize(memory, oldest);
+        }
+        self.taps = taps;
+    }
+}
+
+impl SignalFilter for FirFilter {
+    fn process(&mut self, signal: &mut [f64]) {
+        let Some(&first) = signal.first() else {
+            return;
+        };
+        let memory = self.taps.len().saturating_sub(1);
+        if self.history.len() < memory {
+            self.history.resize(memory, first);
+        }
+
+        for s in signal.iter_mut() {
+            let x = *s;
+            *s = self.taps[0] * x
+                + self.taps[1..]
+                    .iter()
+                    .zip(&self.history)
+                    .map(|(t, h)| t * h)
+                    .sum::<f64>();
+
+            if memory > 0 {
+                self.history.pop_back();
+                self.history.push_front(x);
+            }
+        }
+    }
+
+    fn response(&self, freq: f64, adc_samples_second: f64) -> Complex<f64> {
+        let omega = 2.0 * PI * freq / adc_samples_second;
+        self.taps
+            .iter()
+            .enumerate()
+            .map(|(k, &t)| Complex::from_polar(t, -omega * k as f64))
+            .sum()
+    }
+
+    fn delay(&self, freq: f64, adc_samples_second: f64) -> f64 {
+        let centre = self.taps.len().saturating_sub(1) as f64 / 2.0;
+        phase_delay(
+            self.response(freq, adc_samples_second),
+            freq,
+            adc_samples_second,
+            centre,
+        )
+    }
+
+    fn reset(&mut self) {
+        self.history.clear();
+    }
+
+    fn kind(&self) -> FilterKind {
+        FilterKind::Fir {
+            taps: self.taps.clone(),
+        }
+    }
+
+    fn clone_box(&self) -> Box<dyn SignalFilter> {
+        Box::new(self.clone())
+    }
+}
+
+/* ----------------- Moving average ------------------ */
+
+/// Boxcar average of the last samples; its delay of (window - 1)/2 samples is compensated by the chain.
+#[derive(Debug, Clone)]
+pub struct MovingAverageFilter {
+    window: usize,  // Samples averaged
+    fir: FirFilter, // Equal taps of 1/window
+}
+
+impl MovingAverageFilter {
+    pub fn new(window: usize) -> Self {
+        let window = window.max(1);
+        Self {
+            window,
+            fir: FirFilter::new(vec![1.0 / window as f64; window]),
+        }
+    }
+}
+
+impl SignalFilter for MovingAverageFilter {
+    fn process(&mut self, signal: &mut [f64]) {
+        self.fir.process(signal);
+    }
+
+    fn response(&self, freq: f64, adc_samples_second: f64) -> Complex<f64> {
+        self.fir.response(freq, adc_samples_second)
+    }
+
+    fn delay(&self, _freq: f64, _adc_samples_second: f64) -> f64 {
+        (self.window - 1) as f64 / 2.0
+    }
+
+    fn reset(&mut self) {
+        self.fir.reset();
+    }
+
+    fn kind(&self) -> FilterKind {
+        FilterKind::MovingAverage { window: self.window }
+    }
+
+    fn clone_box(&self) -> Box<dyn SignalFilter> {
+        Box::new(self.clone())
+    }
+}
+
+/* ----------------- Biquad ------------------ */
+
+/// Second-order IIR section (transposed direct form II), continued across contiguous frames.
+#[derive(Debug, Clone)]
+pub struct BiquadFilter {
+    b: [f64; 3],     // Numerator b0, b1, b2
+    a: [f64; 2],     // Denominator a1, a2 (a0 = 1)
+    state: [f64; 2], // Delay elements of the transposed structure
+    primed: bool,    // The state holds at least one sample
+}
+
+impl BiquadFilter {
+    pub fn new(b: [f64; 3], a: [f64; 2]) -> Self {
+        Self {
+            b,
+            a,
+            state: [0.0; 2],
+            primed: false,
+        }
+    }
+}
+
+impl SignalFilter for BiquadFilter {
+    fn process(&mut self, signal: &mut [f64]) {
+        let Some(&first) = signal.first() else {
+            return;
+        };
+        let [b0, b1, b2] = self.b;
+        let [a1, a2] = self.a;
+
+        // Estado de régimen permanente para una entrada constante igual a la primera muestra
+        if !self.primed {
+            let y = first * (b0 + b1 + b2) / (1.0 + a1 + a2);
+            self.state = [(b1 + b2) * first - (a1 + a2) * y, b2 * first - a2 * y];
+            self.primed = true;
+        }
+
+        for s in signal.iter_mut() {
+            let x = *s;
+            let y = b0 * x + self.state[0];
+            self.state = [b1 * x - a1 * y + self.state[1], b2 * x - a2 * y];
+            *s = y;
+        }
+    }
+
+    fn response(&self, freq: f64, adc_samples_second: f64) -> Complex<f64> {
+        let z1 = Complex::from_polar(1.0, -2.0 * PI * freq / adc_samples_second); // z^-1
+        let z2 = z1 * z1;
+
+        (self.b[0] + self.b[1] * z1 + self.b[2] * z2) / (1.0 + self.a[0] * z1 + self.a[1] * z2)
+    }
+
+    fn reset(&mut self) {
+        self.state = [0.0; 2];
+        self.primed = false;
+    }
+
+    fn kind(&self) -> FilterKind {
+        FilterKind::Biquad { b: self.b, a: self.a }
+    }
+
+    fn clone_box(&self) -> Box<dyn SignalFilter> {
+        Box::new(self.clone())
+    }
+}
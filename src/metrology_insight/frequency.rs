@@ -0,0 +1,602 @@
+use crate::{compute_fft, FrequencyEstimate, FrequencyEstimatorKind, MetrologyError};
+use core::f64::consts::PI;
+use core::fmt::Debug;
+
+pub const ZERO_CROSSING_MAX_POINTS: usize = 3; // Maximum number of zero crossing points to store // Para 1 ciclo, 2 cruces por cero (ascendente + descendente)
+pub const FREQ_ZC_DEBOUNCE: u32 = 2;
+
+pub const ZERO_CROSSING_TIMING_ERROR: f64 = 0.05; // Error supuesto de la interpolación lineal (muestras)
+pub const QUALITY_REFERENCE: f64 = 1e-3; // Incertidumbre relativa con la que la calidad vale 0.5
+
+pub const IPDFT_WINDOW_CYCLES: usize = 10; // Ciclos nominales en la ventana del DFT interpolado
+
+pub const PLL_NATURAL_FREQUENCY: f64 = 10.0; // Frecuencia natural del lazo (Hz)
+pub const PLL_DAMPING: f64 = 0.707;
+pub const PLL_SOGI_GAIN: f64 = 1.414;
+pub const PLL_SETTLING_CYCLES: usize = 10; // Ciclos nominales antes de dar la frecuencia por válida
+
+pub const EKF_MEASUREMENT_NOISE: f64 = 1e-2; // Varianza de la medida normalizada (ruido y armónicos)
+pub const EKF_PHASE_NOISE: f64 = 1e-6; // Varianza del proceso de fase (rad²)
+pub const EKF_FREQUENCY_NOISE: f64 = 1e-3; // Varianza del proceso de pulsación ((rad/s)²)
+pub const EKF_AMPLITUDE_NOISE: f64 = 1e-6; // Varianza del proceso de amplitud normalizada
+pub const EKF_SETTLING_CYCLES: usize = 10; // Ciclos nominales antes de dar la frecuencia por válida
+
+/// Estimates the fundamental frequency of a signal, frame by frame.
+pub trait FrequencyEstimator: Send + Debug {
+    /*
+     * @brief Estimate the frequency of the last frame.
+     * @param signal Signal buffer of the last frame, in physical units and without DC offset
+     * @param adc_samples_second Number of ADC samples per second
+     * @param nominal Nominal frequency used to seed and bound the estimator
+     * @return Frequency estimate, or the reason it cannot be given for this frame
+     * @note Stateful estimators assume that consecutive frames are contiguous in time.
+     */
+    fn estimate(
+        &mut self,
+        signal: &[f64],
+        adc_samples_second: f64,
+        nominal: f64,
+    ) -> Result<FrequencyEstimate, MetrologyError>;
+
+    /*
+     * @brief Forget the state carried between frames.
+     */
+    fn reset(&mut self) {}
+
+    /*
+     * @brief Method implemented by the estimator.
+     */
+    fn kind(&self) -> FrequencyEstimatorKind;
+
+    /*
+     * @brief Clone the estimator behind a box, so that the processing state can be cloned.
+     */
+    fn clone_box(&self) -> Box<dyn FrequencyEstimator>;
+}
+
+impl Clone for Box<dyn FrequencyEstimator> {
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
+}
+
+/*
+* @brief Create the estimator selected in the configuration.
+* @param kind Estimation method
+* @return Boxed estimator with its default settings
+*/
+pub fn build_frequency_estimator(kind: FrequencyEstimatorKind) -> Box<dyn FrequencyEstimator> {
+    match kind {
+        FrequencyEstimatorKind::ZeroCrossing => Box::new(ZeroCrossingEstimator::default()),
+        FrequencyEstimatorKind::InterpolatedDft => Box::new(InterpolatedDftEstimator::default()),
+        FrequencyEstimatorKind::Pll => Box::new(PllEstimator::default()),
+        FrequencyEstimatorKind::Kalman => Box::new(KalmanEstimator::default()),
+    }
+}
+
+/*
+* @brief Map a standard uncertainty to a quality figure.
+* @param uncertainty Standard uncertainty in Hz
+* @param frequency Estimated frequency in Hz
+* @return Quality between 0 (useless) and 1 (exact); 0.5 at a relative uncertainty of QUALITY_REFERENCE
+*/
+fn quality_from_uncertainty(uncertainty: f64, frequency: f64) -> f64 {
+    if frequency <= 0.0 || !uncertainty.is_finite() {
+        return 0.0;
+    }
+
+    1.0 / (1.0 + (uncertainty / frequency) / QUALITY_REFERENCE)
+}
+
+/*
+* @brief Build an estimate, bounding the frequency to half and twice the nominal.
+* @param frequency Estimated frequency in Hz
+* @param uncertainty Standard uncertainty in Hz
+* @param nominal Nominal frequency
+* @return Frequency estimate with its quality
+*/
+fn bounded_estimate(frequency: f64, uncertainty: f64, nominal: f64) -> Result<FrequencyEstimate, MetrologyError> {
+    if !frequency.is_finite() || frequency <= nominal * 0.5 || frequency >= nominal * 2.0 {
+        return Err(MetrologyError::FrequencyOutOfRange(frequency));
+    }
+
+    Ok(FrequencyEstimate {
+        frequency,
+        uncertainty,
+        quality: quality_from_uncertainty(uncertainty, frequency),
+    })
+}
+
+/* ----------------- Zero Crossing ------------------ */
+
+/// Interpolated rising zero crossings, continued across contiguous frames.
+#[derive(Debug, Clone)]
+pub struct ZeroCrossingEstimator {
+    pub max_crossings: usize,       // Crossings used per frame
+    pub debounce: u32,              // Samples ignored after a crossing
+    previous_sample: Option<f64>,   // Last sample of the previous frame
+    previous_crossing: Option<f64>, // Last crossing of the previous frame, relative to the start of the next one
+}
+
+impl Default for ZeroCrossingEstimator {
+    fn default() -> Self {
+        Self {
+            max_crossings: ZERO_CROSSING_MAX_POINTS,
+            debounce: FREQ_ZC_DEBOUNCE,
+            previous_sample: None,
+            previous_crossing: None,
+        }
+    }
+}
+
+impl FrequencyEstimator for ZeroCrossingEstimator {
+    /*
+     * @brief Calculate the zero crossing frequency of a signal.
+     * @return Frequency in Hz, or NoZeroCrossing if fewer than two rising crossings were found
+     * @note The last sample and the last crossing of each frame are kept, so a crossing at the frame edge and
+     *       a period that spans two frames are measured too. A crossing older than two nominal periods is
+     *       dropped.
+     * @note The uncertainty is the spread of the periods when there are several, or the timing error of the
+     *       linear interpolation when there is only one.
+     */
+    fn estimate(
+        &mut self,
+        signal: &[f64],
+        adc_samples_second: f64,
+        nominal: f64,
+    ) -> Result<FrequencyEstimate, MetrologyError> {
+        let num_samples = signal.len();
+        if num_samples < 2 {
+            return Err(MetrologyError::BufferTooShort {
+                required: 2,
+                actual: num_samples,
+            });
+        }
+
+        let mut debounce: u32 = 0;
+        let mut interpolation_points: Vec<f64> = Vec::with_capacity(self.max_crossings + 1);
+        let mut last_crossing: Option<f64> = None;
+
+        // Cruce de la trama anterior, si no es demasiado antiguo
+        let max_age = 2.0 * adc_samples_second / nominal;
+        if let Some(xp) = self.previous_crossing.filter(|&xp| -xp < max_age) {
+            interpolation_points.push(xp);
+        }
+
+        // La última muestra de la trama anterior permite detectar un cruce en el borde
+        let start: isize = if self.previous_sample.is_some() { -1 } else { 0 };
+        let sample = |p: isize| {
+            if p < 0 {
+                self.previous_sample.unwrap_or(0.0)
+            } else {
+                signal[p as usize]
+            }
+        };
+
+        for p in start..(num_samples as isize - 1) {
+            let y1: f64 = sample(p);
+            let y2: f64 = sample(p + 1);
+
+            // Cruce por cero ascendente con interpolación lineal
+            if debounce == 0 && y1 < 0.0 && y2 >= 0.0 && (y2 - y1).abs() > f64::EPSILON {
+                let xp = p as f64 - y1 / (y2 - y1);
+
+                if interpolation_points.len() <= self.max_crossings {
+                    interpolation_points.push(xp);
+                }
+                last_crossing = Some(xp);
+                debounce = self.debounce;
+            }
+
+            debounce = debounce.saturating_sub(1);
+        }
+
+        self.previous_sample = signal.last().copied();
+        self.previous_crossing = last_crossing
+            .or(self.previous_crossing)
+            .map(|xp| xp - num_samples as f64);
+
+        // Frecuencia de cada periodo entre cruces consecutivos
+        let frequencies: Vec<f64> = interpolation_points
+            .windows(2)
+            .map(|w| w[1] - w[0])
+            .filter(|&delta| delta > 0.0)
+            .map(|delta| adc_samples_second / delta)
+            .collect();
+
+        if frequencies.is_empty() {
+            return Err(MetrologyError::NoZeroCrossing);
+        }
+
+        let count = frequencies.len() as f64;
+        let frequency = frequencies.iter().sum::<f64>() / count;
+
+        let uncertainty = if frequencies.len() > 1 {
+            let variance = frequencies.iter().map(|f| (f - frequency).powi(2)).sum::<f64>() / (count - 1.0);
+            (variance / count).sqrt()
+        } else {
+            // Dos cruces, cada uno con su error de interpolación
+            frequency * ZERO_CROSSING_TIMING_ERROR * 2f64.sqrt() / (adc_samples_second / frequency)
+        };
+
+        bounded_estimate(frequency, uncertainty, nominal)
+    }
+
+    fn reset(&mut self) {
+        self.previous_sample = None;
+        self.previous_crossing = None;
+    }
+
+    fn kind(&self) -> FrequencyEstimatorKind {
+        FrequencyEstimatorKind::ZeroCrossing
+    }
+
+    fn clone_box(&self) -> Box<dyn FrequencyEstimator> {
+        Box::new(self.clone())
+    }
+}
+
+/* ----------------- Interpolated DFT ------------------ */
+
+/// Hann-windowed DFT over several cycles with two-bin interpolation of the fundamental peak.
+#[derive(Debug, Clone)]
+pub struct InterpolatedDftEstimator {
+    pub cycles: usize, // Nominal cycles in the analysis window
+    history: Vec<f64>, // Last samples, up to one window
+}
+
+impl Default for InterpolatedDftEstimator {
+    fn default() -> Self {
+        Self {
+            cycles: IPDFT_WINDOW_CYCLES,
+            history: vec![],
+        }
+    }
+}
+
+impl FrequencyEstimator for InterpolatedDftEstimator {
+    /*
+     * @brief Estimate the frequency from the fundamental peak of a Hann-windowed spectrum.
+     * @return Frequency estimate, or EstimatorSettling until one window of samples is available
+     * @note With the Hann window the offset of the peak from bin k is δ = (2α - 1)/(α + 1), where α is the
+     *       ratio between the largest neighbour and the peak. The uncertainty scales the bin width by the
+     *       ratio between the energy outside the main lobe, within the search band, and the peak energy.
+     */
+    fn estimate(
+        &mut self,
+        signal: &[f64],
+        adc_samples_second: f64,
+        nominal: f64,
+    ) -> Result<FrequencyEstimate, MetrologyError> {
+        if nominal <= 0.0 || adc_samples_second <= 0.0 {
+            return Err(MetrologyError::InvalidParameter("nominal"));
+        }
+
+        let window_len = (self.cycles as f64 * adc_samples_second / nominal).round() as usize;
+        self.history.extend_from_slice(signal);
+        if self.history.len() > window_len {
+            let excess = self.history.len() - window_len;
+            self.history.drain(..excess);
+        }
+        if self.history.len() < window_len || window_len < 4 {
+            return Err(MetrologyError::EstimatorSettling);
+        }
+
+        let n = window_len as f64;
+        let mut windowed: Vec<f64> = self
+            .history
+            .iter()
+            .enumerate()
+            .map(|(i, &x)| x * 0.5 * (1.0 - (2.0 * PI * i as f64 / n).cos()))
+            .collect();
+        let spectrum = compute_fft(&mut windowed).ok_or(MetrologyError::SpectrumUnavailable)?;
+        let magnitudes: Vec<f64> = spectrum.iter().map(|c| c.norm()).collect();
+
+        // Búsqueda del pico entre la mitad y el doble de la frecuencia nominal
+        let bin_width = adc_samples_second / n;
+        let first = ((nominal * 0.5 / bin_width).floor() as usize).max(2);
+        let last = ((nominal * 2.0 / bin_width).ceil() as usize).min(magnitudes.len().saturating_sub(3));
+        if first >= last {
+            return Err(MetrologyError::BufferTooShort {
+                required: window_len * 2,
+                actual: window_len,
+            });
+        }
+
+        let k = (first..=last)
+            .max_by(|&a, &b| magnitudes[a].total_cmp(&magnitudes[b]))
+            .ok_or(MetrologyError::NoFundamental)?;
+        let peak = magnitudes[k];
+        if peak < f64::EPSILON {
+            return Err(MetrologyError::NoFundamental);
+        }
+
+        let (neighbour, direction) = if magnitudes[k + 1] >= magnitudes[k - 1] {
+            (magnitudes[k + 1], 1.0)
+        } else {
+            (magnitudes[k - 1], -1.0)
+        };
+        let alpha = neighbour / peak;
+        let delta = (2.0 * alpha - 1.0) / (alpha + 1.0);
+        let frequency = (k as f64 + direction * delta) * bin_width;
+
+        // Energía fuera del lóbulo principal (en la banda de búsqueda) frente a la del pico
+        let main_lobe = magnitudes[k - 2..=k + 2].iter().map(|m| m * m).sum::<f64>();
+        let band = magnitudes[first - 1..=last + 1].iter().map(|m| m * m).sum::<f64>();
+        let noise_ratio = ((band - main_lobe).max(0.0) / main_lobe).sqrt();
+        let uncertainty = bin_width * noise_ratio / (2.0 * self.cycles as f64).sqrt();
+
+        bounded_estimate(frequency, uncertainty, nominal)
+    }
+
+    fn reset(&mut self) {
+        self.history.clear();
+    }
+
+    fn kind(&self) -> FrequencyEstimatorKind {
+        FrequencyEstimatorKind::InterpolatedDft
+    }
+
+    fn clone_box(&self) -> Box<dyn FrequencyEstimator> {
+        Box::new(self.clone())
+    }
+}
+
+/* ----------------- Software PLL ------------------ */
+
+/// Single-phase PLL with a SOGI quadrature generator and a PI loop filter.
+#[derive(Debug, Clone)]
+pub struct PllEstimator {
+    pub natural_frequency: f64, // Loop natural frequency (Hz)
+    pub damping: f64,           // Loop damping factor
+    pub sogi_gain: f64,         // SOGI gain k
+    omega: f64,                 // Estimated angular frequency (rad/s)
+    theta: f64,                 // Estimated phase (rad)
+    integrator: f64,            // PI integral term (rad/s)
+    input: [f64; 2],            // v[n-1], v[n-2]
+    direct: [f64; 2],           // d[n-1], d[n-2]
+    quadrature: [f64; 2],       // q[n-1], q[n-2]
+    samples: usize,             // Samples processed since the last reset
+}
+
+impl Default for PllEstimator {
+    fn default() -> Self {
+        Self {
+            natural_frequency: PLL_NATURAL_FREQUENCY,
+            damping: PLL_DAMPING,
+            sogi_gain: PLL_SOGI_GAIN,
+            omega: 0.0,
+            theta: 0.0,
+            integrator: 0.0,
+            input: [0.0; 2],
+            direct: [0.0; 2],
+            quadrature: [0.0; 2],
+            samples: 0,
+        }
+    }
+}
+
+impl FrequencyEstimator for PllEstimator {
+    /*
+     * @brief Track the frequency sample by sample and report its mean over the frame.
+     * @return Frequency estimate, or EstimatorSettling during the first PLL_SETTLING_CYCLES cycles
+     * @note The SOGI is discretised with the bilinear transform at the tracked frequency. The phase error is
+     *       normalised by the amplitude, so the loop gains do not depend on the signal level.
+     * @note The uncertainty is the standard deviation of the tracked frequency within the frame.
+     */
+    fn estimate(
+        &mut self,
+        signal: &[f64],
+        adc_samples_second: f64,
+        nominal: f64,
+    ) -> Result<FrequencyEstimate, MetrologyError> {
+        if nominal <= 0.0 || adc_samples_second <= 0.0 {
+            return Err(MetrologyError::InvalidParameter("nominal"));
+        }
+        if signal.is_empty() {
+            return Err(MetrologyError::BufferTooShort { required: 1, actual: 0 });
+        }
+
+        let ts = 1.0 / adc_samples_second;
+        let omega_nominal = 2.0 * PI * nominal;
+        let wn = 2.0 * PI * self.natural_frequency;
+        let kp = 2.0 * self.damping * wn;
+        let ki = wn * wn;
+
+        if self.samples == 0 {
+            self.omega = omega_nominal;
+        }
+
+        let mut sum = 0.0;
+        let mut sum_sq = 0.0;
+
+        for &v in signal {
+            // SOGI discreto (Tustin) a la pulsación estimada
+            let x = 2.0 * self.sogi_gain * self.omega * ts;
+            let y = (self.omega * ts).powi(2);
+            let den = x + y + 4.0;
+            let a1 = 2.0 * (4.0 - y) / den;
+            let a2 = (x - y - 4.0) / den;
+
+            let d = (x / den) * (v - self.input[1]) + a1 * self.direct[0] + a2 * self.direct[1];
+            let q = (self.sogi_gain * y / den) * (v + 2.0 * self.input[0] + self.input[1])
+                + a1 * self.quadrature[0]
+                + a2 * self.quadrature[1];
+
+            self.input = [v, self.input[0]];
+            self.direct = [d, self.direct[0]];
+            self.quadrature = [q, self.quadrature[0]];
+
+            // Detector de fase: sin(φ - θ) normalizado por la amplitud
+            let amplitude = (d * d + q * q).sqrt();
+            let error = if amplitude > f64::EPSILON {
+                (d * self.theta.cos() + q * self.theta.sin()) / amplitude
+            } else {
+                0.0
+            };
+
+            self.integrator += ki * error * ts;
+            self.omega = (omega_nominal + self.integrator + kp * error).clamp(omega_nominal * 0.5, omega_nominal * 2.0);
+            self.theta = (self.theta + self.omega * ts).rem_euclid(2.0 * PI);
+
+            let f = self.omega / (2.0 * PI);
+            sum += f;
+            sum_sq += f * f;
+        }
+
+        self.samples += signal.len();
+        let settling = (PLL_SETTLING_CYCLES as f64 * adc_samples_second / nominal) as usize;
+        if self.samples < settling {
+            return Err(MetrologyError::EstimatorSettling);
+        }
+
+        let count = signal.len() as f64;
+        let frequency = sum / count;
+        let uncertainty = (sum_sq / count - frequency * frequency).max(0.0).sqrt();
+
+        bounded_estimate(frequency, uncertainty, nominal)
+    }
+
+    fn reset(&mut self) {
+        *self = Self {
+            natural_frequency: self.natural_frequency,
+            damping: self.damping,
+            sogi_gain: self.sogi_gain,
+            ..Default::default()
+        };
+    }
+
+    fn kind(&self) -> FrequencyEstimatorKind {
+        FrequencyEstimatorKind::Pll
+    }
+
+    fn clone_box(&self) -> Box<dyn FrequencyEstimator> {
+        Box::new(self.clone())
+    }
+}
+
+/* ----------------- Extended Kalman Filter ------------------ */
+
+fn matrix_mul3(a: &[[f64; 3]; 3], b: &[[f64; 3]; 3]) -> [[f64; 3]; 3] {
+    core::array::from_fn(|i| core::array::from_fn(|j| (0..3).map(|k| a[i][k] * b[k][j]).sum()))
+}
+
+fn transpose3(a: &[[f64; 3]; 3]) -> [[f64; 3]; 3] {
+    core::array::from_fn(|i| core::array::from_fn(|j| a[j][i]))
+}
+
+/// Extended Kalman filter on the state [phase, angular frequency, amplitude] of y = a·sin(θ).
+#[derive(Debug, Clone)]
+pub struct KalmanEstimator {
+    pub measurement_noise: f64,  // R, on the normalised signal
+    pub process_noise: [f64; 3], // Diagonal of Q for phase, angular frequency and amplitude
+    state: [f64; 3],             // θ (rad), ω (rad/s), a (normalised)
+    covariance: [[f64; 3]; 3],   // P
+    samples: usize,              // Samples processed since the last reset
+}
+
+impl Default for KalmanEstimator {
+    fn default() -> Self {
+        Self {
+            measurement_noise: EKF_MEASUREMENT_NOISE,
+            process_noise: [EKF_PHASE_NOISE, EKF_FREQUENCY_NOISE, EKF_AMPLITUDE_NOISE],
+            state: [0.0; 3],
+            covariance: [[0.0; 3]; 3],
+            samples: 0,
+        }
+    }
+}
+
+impl FrequencyEstimator for KalmanEstimator {
+    /*
+     * @brief Track phase, frequency and amplitude sample by sample.
+     * @return Frequency estimate at the end of the frame, or EstimatorSettling during the first
+     *         EKF_SETTLING_CYCLES cycles
+     * @note Each frame is normalised by its peak, so the noise settings do not depend on the signal level.
+     * @note The uncertainty is the standard deviation of ω taken from the state covariance.
+     */
+    fn estimate(
+        &mut self,
+        signal: &[f64],
+        adc_samples_second: f64,
+        nominal: f64,
+    ) -> Result<FrequencyEstimate, MetrologyError> {
+        if nominal <= 0.0 || adc_samples_second <= 0.0 {
+            return Err(MetrologyError::InvalidParameter("nominal"));
+        }
+
+        let peak = signal.iter().fold(0.0f64, |acc, x| acc.max(x.abs()));
+        if peak < f64::EPSILON {
+            return Err(MetrologyError::NoFundamental);
+        }
+
+        let ts = 1.0 / adc_samples_second;
+        let omega_nominal = 2.0 * PI * nominal;
+
+        if self.samples == 0 {
+            self.state = [0.0, omega_nominal, 1.0];
+            self.covariance = [
+                [PI * PI, 0.0, 0.0],
+                [0.0, (0.1 * omega_nominal).powi(2), 0.0],
+                [0.0, 0.0, 1.0],
+            ];
+        }
+
+        for &x in signal {
+            let y = x / peak;
+
+            // Predicción: θ += ω·T, P = F·P·Fᵀ + Q
+            self.state[0] += self.state[1] * ts;
+            let transition = [[1.0, ts, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+            let mut predicted = matrix_mul3(&matrix_mul3(&transition, &self.covariance), &transpose3(&transition));
+            for (i, q) in self.process_noise.iter().enumerate() {
+                predicted[i][i] += q;
+            }
+
+            // Actualización con h(x) = a·sin(θ), H = [a·cos(θ), 0, sin(θ)]
+            let (sin, cos) = self.state[0].sin_cos();
+            let h = [self.state[2] * cos, 0.0, sin];
+            let ph: [f64; 3] = core::array::from_fn(|i| (0..3).map(|j| predicted[i][j] * h[j]).sum());
+            let innovation_var = (0..3).map(|i| h[i] * ph[i]).sum::<f64>() + self.measurement_noise;
+            let gain: [f64; 3] = core::array::from_fn(|i| ph[i] / innovation_var);
+            let innovation = y - self.state[2] * sin;
+
+            for (value, k) in self.state.iter_mut().zip(gain.iter()) {
+                *value += k * innovation;
+            }
+            for i in 0..3 {
+                for j in 0..3 {
+                    self.covariance[i][j] = predicted[i][j] - gain[i] * ph[j];
+                }
+            }
+
+            self.state[0] = self.state[0].rem_euclid(2.0 * PI);
+            self.state[1] = self.state[1].clamp(omega_nominal * 0.5, omega_nominal * 2.0);
+        }
+
+        self.samples += signal.len();
+        let settling = (EKF_SETTLING_CYCLES as f64 * adc_samples_second / nominal) as usize;
+        if self.samples < settling {
+            return Err(MetrologyError::EstimatorSettling);
+        }
+
+        let frequency = self.state[1] / (2.0 * PI);
+        let uncertainty = self.covariance[1][1].max(0.0).sqrt() / (2.0 * PI);
+
+        bounded_estimate(frequency, uncertainty, nominal)
+    }
+
+    fn reset(&mut self) {
+        self.state = [0.0; 3];
+        self.covariance = [[0.0; 3]; 3];
+        self.samples = 0;
+    }
+
+    fn kind(&self) -> FrequencyEstimatorKind {
+        FrequencyEstimatorKind::Kalman
+    }
+
+    fn clone_box(&self) -> Box<dyn FrequencyEstimator> {
+        Box::new(self.clone())
+    }
+}
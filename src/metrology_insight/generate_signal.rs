@@ -47,6 +47,22 @@ fn offset(deg: f64) -> f64 {
 }
 
 pub fn generate_signals() -> Vec<Vec<i32>> {
+    generate_signals_with_current_offset(90.0 + IPHASE)
+}
+
+/*
+* @brief Generate voltage and current signals for a load with a given displacement angle.
+* @param phase_deg Angle by which the current lags the voltage, in degrees.
+* @return Vector with the voltage samples first and the current samples second.
+* @note Positive angles give an inductive (lagging) load and negative angles a capacitive (leading) one.
+* @note Angles beyond ±90° make the current flow against the voltage (exported active power).
+*/
+pub fn generate_load_signals(phase_deg: f64) -> Vec<Vec<i32>> {
+    // sin(wt - phi) = cos(wt - phi - 90°)
+    generate_signals_with_current_offset(-90.0 - phase_deg)
+}
+
+fn generate_signals_with_current_offset(current_offset_deg: f64) -> Vec<Vec<i32>> {
     let mut rng = rand::thread_rng();
     let samples = Array1::range(0.0, N_SAMPLES as f64, 1.0);
 
@@ -90,7 +106,7 @@ pub fn generate_signals() -> Vec<Vec<i32>> {
     let mut signal_i: Vec<f64> = samples
         .iter()
         .map(|&s| {
-            current(IPEAK) * (offset(phase_offset + 90.0) + offset(IPHASE) + 2.0 * PI * F / FS * s).cos()
+            current(IPEAK) * (offset(phase_offset + current_offset_deg) + 2.0 * PI * F / FS * s).cos()
                 + signal_noise_i[s as usize]
         })
         .collect();
@@ -112,7 +128,7 @@ pub fn generate_signals() -> Vec<Vec<i32>> {
 
             signal_i.iter_mut().enumerate().for_each(|(i, s)| {
                 *s += current(ipeak)
-                    * (offset(phase_offset + 90.0) + offset(IPHASE) + (2.0 * PI * freq / FS * samples[i])).cos();
+                    * (offset(phase_offset + current_offset_deg) + (2.0 * PI * freq / FS * samples[i])).cos();
             });
         }
     }
@@ -1,3 +1,4 @@
+use crate::{FrequencyResponse, HarmonicComponent, MetrologyError};
 use core::f64::consts::PI;
 use num_complex::Complex;
 use realfft::RealFftPlanner;
@@ -23,91 +24,170 @@ pub fn resample_signal(signal: &[f64], new_len: usize) -> Vec<f64> {
     resampled
 }
 
-// Cálculo de THD y armónicos
-fn calculate_harmonics_and_thd(magnitudes: &[f64], fundamental_mag: f64, fundamental_bin: usize) -> ([f64; 21], f64) {
-    let mut harmonics = [0.0; 21];
-    let mut harmonic_power_sum = 0.0;
+/*
+* @brief Convert a THD percentage to decibels.
+* @param thd_percent THD in percent of the fundamental
+* @return THD in dB relative to the fundamental
+*/
+pub fn thd_percent_to_db(thd_percent: f64) -> f64 {
+    20.0 * (thd_percent / 100.0).log10()
+}
 
-    // Solo consideramos armónicos impares (1°, 3°, 5°, ... 41°)
-    for i in 0..harmonics.len() {
-        let harmonic_order = 2 * i + 1;
-        let harmonic_bin = harmonic_order * fundamental_bin;
+/*
+* @brief Calculate the harmonics and THD of one cycle of a signal.
+* @param signal Signal buffer holding at least one cycle
+* @param freq Fundamental frequency in Hz
+* @param fs Sampling frequency in Hz
+* @param num_harmonics Highest harmonic order to report (even and odd orders)
+* @param response Frequency response of the sensor, removed from the harmonics before anything is computed
+* @return (components for orders 1..=num_harmonics, THD in percent), or the reason they cannot be computed
+* @note Orders at or above Nyquist are reported with zero amplitude so the vector always has num_harmonics entries.
+*/
+pub fn compute_harmonics_and_thd(
+    signal: &[f64],
+    freq: f64,
+    fs: f64,
+    num_harmonics: usize,
+    response: &FrequencyResponse,
+) -> Result<(Vec<HarmonicComponent>, f64), MetrologyError> {
+    if num_harmonics == 0 {
+        return Err(MetrologyError::InvalidParameter("num_harmonics"));
+    }
 
-        if let Some(mag) = magnitudes.get(harmonic_bin) {
-            // Almacenar armónico como porcentaje del fundamental
-            harmonics[i] = (*mag / fundamental_mag) * 100.0;
+    let mut phasors = compute_harmonic_phasors(signal, freq, fs, num_harmonics)?;
+    response.correct_phasors(&mut phasors, freq);
 
-            // Excluir el fundamental (i=0) para el cálculo del THD
-            if i > 0 {
-                harmonic_power_sum += mag.powi(2);
-            }
-        }
+    // Protección por si el fundamental es nulo
+    let fundamental_rms = phasors.get(1).ok_or(MetrologyError::NoFundamental)?.norm();
+    if fundamental_rms < f64::EPSILON {
+        return Err(MetrologyError::NoFundamental);
     }
 
-    // Calcular THD: sqrt(suma de potencias armónicas) / fundamental
-    let mut thd = (harmonic_power_sum.sqrt() / fundamental_mag) * 100.0;
-    thd = 20.0 * (thd / 100.0).log10();
-    (harmonics, thd)
+    let harmonics: Vec<HarmonicComponent> = (1..=num_harmonics)
+        .map(|order| match phasors.get(order) {
+            Some(phasor) => HarmonicComponent {
+                order,
+                rms: phasor.norm(),
+                percent: (phasor.norm() / fundamental_rms) * 100.0,
+                phase: phasor.arg().to_degrees(),
+            },
+            None => HarmonicComponent {
+                order,
+                ..Default::default()
+            },
+        })
+        .collect();
+
+    // THD: sqrt(suma de potencias armónicas) / fundamental
+    let harmonic_power_sum = harmonics.iter().skip(1).map(|h| h.rms.powi(2)).sum::<f64>();
+    let thd_percent = (harmonic_power_sum.sqrt() / fundamental_rms) * 100.0;
+
+    Ok((harmonics, thd_percent))
 }
 
-// Función principal para cálculo de armónicos y THD
-pub fn compute_harmonics_and_thd(signal: &mut [f64], freq: f64, fs: f64) -> Option<([f64; 21], f64)> {
-    // (armónicos, THD)
-    let sample_rate = fs; // frecuencia de muestreo, deberías pasarla como parámetro si cambia
-    let bin_freq = sample_rate / signal.len() as f64;
-
-    // 1. Preprocesamiento de la señal
-    apply_window(signal);
-    remove_mean(signal);
+/*
+* @brief Check that a buffer holds one fundamental period and split the period in samples.
+* @param signal Signal buffer
+* @param freq Fundamental frequency in Hz
+* @param fs Sampling frequency in Hz
+* @return (whole samples in one period, fractional part of the last sample)
+*/
+pub fn cycle_window(signal: &[f64], freq: f64, fs: f64) -> Result<(usize, f64), MetrologyError> {
+    if freq <= 0.0 {
+        return Err(MetrologyError::InvalidParameter("frequency"));
+    }
+    if fs <= 0.0 {
+        return Err(MetrologyError::InvalidParameter("adc_samples_second"));
+    }
 
-    // 2. Realizar FFT
-    let spectrum = compute_fft(signal)?;
+    let samples_per_cycle = fs / freq;
+    let whole_samples = samples_per_cycle.floor() as usize;
 
-    // 3. Calcular magnitudes
-    let mut magnitudes = vec![0.0; spectrum.len()];
-    for (i, bin) in spectrum.iter().enumerate() {
-        magnitudes[i] = (bin.re * bin.re + bin.im * bin.im).sqrt();
+    if whole_samples == 0 || signal.len() < whole_samples {
+        return Err(MetrologyError::BufferTooShort {
+            required: whole_samples.max(1),
+            actual: signal.len(),
+        });
     }
 
-    // Debug magnitudes
-    //for (i, mag) in magnitudes.iter().enumerate().take(20) {
-    //    println!("Bin {} → Mag: {:.3}", i, mag);
-    //}
-
-    // 4. Identificar bin fundamental (50/60 Hz)
-    let fundamental_bin: usize = (freq / bin_freq).round() as usize;
-    let fundamental_mag = magnitudes.get(fundamental_bin).copied()?;
+    Ok((whole_samples, samples_per_cycle - whole_samples as f64))
+}
 
-    // Protección por si el espectro está vacío
-    if fundamental_mag < f64::EPSILON {
-        return None;
-    }
+/*
+* @brief Calculate the RMS phasors of the harmonics of a signal.
+* @param signal Signal buffer holding at least one cycle
+* @param freq Fundamental frequency in Hz
+* @param fs Sampling frequency in Hz
+* @param max_order Highest harmonic order to compute
+* @return Vector indexed by harmonic order (index 0 is the DC component) with the RMS phasor of each order
+* @note The DFT is evaluated at the exact harmonic frequencies over one fundamental period, so no resampling
+*       or window is needed. The fractional last sample is weighted and wrapped to the start of the buffer.
+* @note Phases are referred to a cosine starting at the first sample. Orders at or above Nyquist are skipped.
+*/
+pub fn compute_harmonic_phasors(
+    signal: &[f64],
+    freq: f64,
+    fs: f64,
+    max_order: usize,
+) -> Result<Vec<Complex<f64>>, MetrologyError> {
+    let (whole_samples, fraction) = cycle_window(signal, freq, fs)?;
+    let samples_per_cycle = whole_samples as f64 + fraction;
+
+    // Último armónico por debajo de Nyquist
+    let nyquist_order = ((fs / 2.0) / freq).ceil() as usize - 1;
+    let max_order = max_order.min(nyquist_order);
+
+    let mut phasors = Vec::with_capacity(max_order + 1);
+
+    for order in 0..=max_order {
+        let omega = 2.0 * PI * order as f64 * freq / fs;
+
+        let mut acc = Complex::new(0.0, 0.0);
+        for (n, &x) in signal.iter().take(whole_samples).enumerate() {
+            acc += Complex::from_polar(x, -omega * n as f64);
+        }
 
-    // 6. Calcular THD y armónicos impares
-    let (harmonics, thd) = calculate_harmonics_and_thd(&magnitudes, fundamental_mag, fundamental_bin);
+        // Fracción de muestra que completa el periodo
+        if fraction > 0.0 {
+            let x = signal.get(whole_samples).copied().unwrap_or(signal[0]);
+            acc += Complex::from_polar(x * fraction, -omega * whole_samples as f64);
+        }
 
-    Some((harmonics, thd))
-}
+        let scale = if order == 0 {
+            1.0 / samples_per_cycle
+        } else {
+            2f64.sqrt() / samples_per_cycle
+        };
 
-// Aplicar ventana de Hann para reducir fugas espectrales
-fn apply_window(signal: &mut [f64]) {
-    let n = signal.len();
-    for i in 0..n {
-        let window = 0.5 * (1.0 - (2.0 * PI * i as f64 / (n as f64 - 1.0)).cos());
-        signal[i] *= window;
+        phasors.push(acc * scale);
     }
+
+    Ok(phasors)
 }
 
-// Remover componente DC
-fn remove_mean(signal: &mut [f64]) {
-    let mean = signal.iter().sum::<f64>() / signal.len() as f64;
-    for sample in signal.iter_mut() {
-        *sample -= mean;
+/*
+* @brief Calculate the RMS value of one fundamental period of a signal.
+* @param signal Signal buffer holding at least one cycle
+* @param freq Fundamental frequency in Hz
+* @param fs Sampling frequency in Hz
+* @return RMS value over the same window used by compute_harmonic_phasors
+* @note Use it together with the phasors so that the non-fundamental RMS, sqrt(X² - X1²), is not biased.
+*/
+pub fn compute_cycle_rms(signal: &[f64], freq: f64, fs: f64) -> Result<f64, MetrologyError> {
+    let (whole_samples, fraction) = cycle_window(signal, freq, fs)?;
+    let samples_per_cycle = whole_samples as f64 + fraction;
+
+    let mut square = signal.iter().take(whole_samples).map(|x| x * x).sum::<f64>();
+    if fraction > 0.0 {
+        let x = signal.get(whole_samples).copied().unwrap_or(signal[0]);
+        square += x * x * fraction;
     }
+
+    Ok((square / samples_per_cycle).sqrt())
 }
 
 // Implementación compatible std/no_std para FFT
-fn compute_fft(signal: &mut [f64]) -> Option<Vec<Complex<f64>>> {
+pub fn compute_fft(signal: &mut [f64]) -> Option<Vec<Complex<f64>>> {
     #[cfg(feature = "std")]
     {
         let mut planner = RealFftPlanner::<f64>::new();
@@ -1,10 +1,25 @@
+pub mod calibration;
+pub mod creep;
 pub mod energy;
+pub mod error;
+pub mod filter;
+pub mod frequency;
 pub mod generate_signal;
+pub mod harmonic_groups;
 pub mod harmonics;
+pub mod integrator;
+pub mod offset;
 pub mod phase;
 pub mod power;
 pub mod print;
 pub mod processing;
+pub mod quality;
+pub mod register;
+pub mod response;
+pub mod sensor;
 pub mod signal;
+pub mod stream;
+pub mod temperature;
+pub mod timebase;
 pub mod types;
 pub mod voltage_current;
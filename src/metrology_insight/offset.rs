@@ -0,0 +1,98 @@
+pub const OFFSET_TIME_CONSTANT: f64 = 1.0; // Constante de tiempo del seguimiento del offset (s)
+
+/// Tracker of the DC level of a channel, carried from frame to frame.
+#[derive(Debug, Clone, Default)]
+pub struct OffsetTracker {
+    offset: f64,       // Tracked DC level (signal units)
+    initialized: bool, // A frame has been tracked since the last reset
+}
+
+/*
+* @brief Mean of the whole cycles of a frame.
+* @param signal Signal buffer
+* @param samples_per_cycle Samples in one cycle of the fundamental
+* @return Mean over the longest run of whole cycles, or over the whole frame if it is shorter than a cycle
+* @note Averaging whole cycles cancels every harmonic, so the result is not biased by asymmetric waveforms
+*       (e.g. half-wave rectifier loads) or by noise spikes, unlike the midpoint of the peaks.
+*/
+fn whole_cycle_mean(signal: &[f64], samples_per_cycle: f64) -> f64 {
+    let cycles = if samples_per_cycle > 0.0 {
+        (signal.len() as f64 / samples_per_cycle).floor()
+    } else {
+        0.0
+    };
+    let len = if cycles >= 1.0 {
+        ((cycles * samples_per_cycle).round() as usize).clamp(1, signal.len())
+    } else {
+        signal.len()
+    };
+
+    signal[..len].iter().sum::<f64>() / len as f64
+}
+
+impl OffsetTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /*
+     * @brief Forget the tracked level; the next frame sets it again.
+     */
+    pub fn reset(&mut self) {
+        self.offset = 0.0;
+        self.initialized = false;
+    }
+
+    /*
+     * @brief Tracked DC level.
+     * @return Level in the units of the tracked signal
+     */
+    pub fn offset(&self) -> f64 {
+        self.offset
+    }
+
+    /*
+     * @brief Update the tracked level with the next frame.
+     * @param signal Signal buffer of the frame
+     * @param samples_per_cycle Samples in one cycle of the fundamental
+     * @param adc_samples_second Number of ADC samples per second
+     * @return Tracked level after the frame
+     * @note Single-pole IIR on the whole-cycle mean of each frame, with a time constant of
+     *       OFFSET_TIME_CONSTANT seconds; the first frame after a reset sets the level directly.
+     */
+    pub fn update(&mut self, signal: &[f64], samples_per_cycle: f64, adc_samples_second: f64) -> f64 {
+        if signal.is_empty() {
+            return self.offset;
+        }
+
+        let mean = whole_cycle_mean(signal, samples_per_cycle);
+
+        if self.initialized && adc_samples_second > 0.0 {
+            let duration = signal.len() as f64 / adc_samples_second;
+            let alpha = 1.0 - (-duration / OFFSET_TIME_CONSTANT).exp();
+            self.offset += alpha * (mean - self.offset);
+        } else {
+            self.offset = mean;
+            self.initialized = true;
+        }
+
+        self.offset
+    }
+
+    /*
+     * @brief Update the tracked level with a frame and remove it from the frame.
+     * @param signal Signal buffer of the frame, modified in place
+     * @param samples_per_cycle Samples in one cycle of the fundamental
+     * @param adc_samples_second Number of ADC samples per second
+     * @return Level removed from the frame
+     */
+    pub fn remove(&mut self, signal: &mut [f64], samples_per_cycle: f64, adc_samples_second: f64) -> f64 {
+        let offset = self.update(signal, samples_per_cycle, adc_samples_second);
+
+        for s in signal.iter_mut() {
+            *s -= offset;
+        }
+
+        offset
+    }
+}
@@ -1,4 +1,34 @@
-use crate::{MetrologyInsightSocket, PhaseAngleMetrics, PhaseDirection};
+use crate::{
+    compute_harmonic_phasors, FrequencyResponse, MetrologyError, MetrologyInsightSocket, PhaseAngleMethod,
+    PhaseAngleMetrics, PhaseDirection,
+};
+use core::f64::consts::PI;
+
+/// Harmonics weaker than this fraction of their fundamental get no V/I angle (it would be noise).
+const HARMONIC_ANGLE_MIN_RATIO: f64 = 5e-3;
+
+/*
+* @brief Wrap an angle to the range [0, 360) degrees.
+* @param angle Angle in degrees
+* @return Wrapped angle in degrees
+*/
+fn wrap_angle_360(angle: f64) -> f64 {
+    angle.rem_euclid(360.0)
+}
+
+/*
+* @brief Wrap an angle to the range (-180, 180] degrees.
+* @param angle Angle in degrees
+* @return Wrapped angle in degrees
+*/
+fn wrap_angle_180(angle: f64) -> f64 {
+    let wrapped = wrap_angle_360(angle);
+    if wrapped > 180.0 {
+        wrapped - 360.0
+    } else {
+        wrapped
+    }
+}
 
 /* ----------------- Phase Angle Functions ------------------ */
 
@@ -27,61 +57,96 @@ fn phase_angle_from_pf_and_react_power(power_factor: f64, react_power: f64) -> f
     phase_rad.to_degrees()
 }
 
-#[allow(dead_code)]
 /*
-* @brief Calculate the phase angle from voltage and current signals.
-* @param voltage Voltage signal
-* @param current Current signal
-* @return Phase angle in degrees
-* @note The phase angle is positive for inductive loads and negative for capacitive loads.
+* @brief Calculate the absolute phase angles from the rising zero crossings of the signals.
+* @param voltage_signal Voltage signal
+* @param current_signal Current signal
+* @param samples_per_cycle Number of samples per cycle
+* @return (voltage angle, current angle) in degrees [0, 360), or None if a signal has no rising zero crossing
+* @note Crossings are interpolated linearly, and the angles use the same cosine reference as the phasors.
 */
-fn phase_angle_from_signals(voltage: &[f64], current: &[f64]) -> f64 {
-    let dot = voltage.iter().zip(current.iter()).map(|(v, i)| v * i).sum::<f64>();
-    let v_mag = voltage.iter().map(|v| v * v).sum::<f64>().sqrt();
-    let i_mag = current.iter().map(|i| i * i).sum::<f64>().sqrt();
+fn absolute_phase_angles_from_signals(
+    voltage_signal: &[f64],
+    current_signal: &[f64],
+    samples_per_cycle: f64,
+) -> Option<(f64, f64)> {
+    fn find_first_rising_zero_crossing(signal: &[f64]) -> Option<f64> {
+        signal.windows(2).enumerate().find_map(|(i, pair)| {
+            if pair[0] < 0.0 && pair[1] >= 0.0 {
+                Some(i as f64 - pair[0] / (pair[1] - pair[0]))
+            } else {
+                None
+            }
+        })
+    }
 
-    if v_mag == 0.0 || i_mag == 0.0 {
-        return 0.0; // evitar división por cero
+    // Un seno que cruza por cero en x tiene fase -90° - x respecto al coseno
+    fn crossing_to_angle(crossing: f64, samples_per_cycle: f64) -> f64 {
+        wrap_angle_360(-90.0 - (crossing / samples_per_cycle) * 360.0)
     }
 
-    let cos_phi = (dot / (v_mag * i_mag)).clamp(-1.0, 1.0);
-    cos_phi.acos().to_degrees()
+    if samples_per_cycle <= 0.0 {
+        return None;
+    }
+
+    let v_crossing = find_first_rising_zero_crossing(voltage_signal)?;
+    let c_crossing = find_first_rising_zero_crossing(current_signal)?;
+
+    Some((
+        crossing_to_angle(v_crossing, samples_per_cycle),
+        crossing_to_angle(c_crossing, samples_per_cycle),
+    ))
 }
 
 /*
-* @brief Calculate the phase angle from voltage and current signals.
-* @param voltage Voltage signal
-* @param current Current signal
-* @param samples_per_cycle Number of samples per cycle
-* @return Phase angle in degrees
-* @note The phase angle is positive for inductive loads and negative for capacitive loads.
+* @brief Calculate the phase angles from the spectral phasors of the signals.
+* @param voltage_signal Voltage signal
+* @param current_signal Current signal
+* @param frequency Fundamental frequency in Hz
+* @param adc_samples_second Number of ADC samples per second
+* @param max_order Highest harmonic order for the per-harmonic angles
+* @param responses Frequency responses of the voltage and current sensors, removed from the harmonics
+* @return (voltage angle, current angle, V-I angle per harmonic order starting at the fundamental), or None.
+*         The angle is NaN for the orders where the voltage or the current harmonic is too weak to have one.
+* @note The absolute angles are those of the fundamental DFT bin, so they have sub-sample resolution.
 */
-fn absolute_phase_angles_from_signals(
+fn spectral_phase_angles_from_signals(
     voltage_signal: &[f64],
     current_signal: &[f64],
-    samples_per_cycle: f64,
-) -> (f64, f64) {
-    fn find_first_zero_crossing(signal: &[f64]) -> Option<usize> {
-        for i in 1..signal.len() {
-            if (signal[i - 1] < 0.0 && signal[i] >= 0.0) || (signal[i - 1] > 0.0 && signal[i] <= 0.0) {
-                return Some(i);
-            }
-        }
-        None
-    }
+    frequency: f64,
+    adc_samples_second: f64,
+    max_order: usize,
+    responses: [&FrequencyResponse; 2],
+) -> Option<(f64, f64, Vec<f64>)> {
+    let mut v_phasors = compute_harmonic_phasors(voltage_signal, frequency, adc_samples_second, max_order).ok()?;
+    let mut i_phasors = compute_harmonic_phasors(current_signal, frequency, adc_samples_second, max_order).ok()?;
+    responses[0].correct_phasors(&mut v_phasors, frequency);
+    responses[1].correct_phasors(&mut i_phasors, frequency);
 
-    fn sample_index_to_angle(sample_index: usize, samples_per_cycle: f64) -> f64 {
-        let angle_deg = (sample_index as f64 / samples_per_cycle) * 360.0;
-        angle_deg % 360.0
+    let v1 = *v_phasors.get(1)?;
+    let i1 = *i_phasors.get(1)?;
+    if v1.norm() == 0.0 || i1.norm() == 0.0 {
+        return None;
     }
 
-    let v_index = find_first_zero_crossing(voltage_signal).unwrap_or(0);
-    let c_index = find_first_zero_crossing(current_signal).unwrap_or(0);
-
-    let v_angle = sample_index_to_angle(v_index, samples_per_cycle);
-    let c_angle = sample_index_to_angle(c_index, samples_per_cycle);
+    let harmonic_angles = v_phasors
+        .iter()
+        .zip(i_phasors.iter())
+        .skip(1)
+        .map(|(v, i)| {
+            if v.norm() < v1.norm() * HARMONIC_ANGLE_MIN_RATIO || i.norm() < i1.norm() * HARMONIC_ANGLE_MIN_RATIO {
+                f64::NAN
+            } else {
+                wrap_angle_180((v.arg() - i.arg()).to_degrees())
+            }
+        })
+        .collect();
 
-    (v_angle, c_angle)
+    Some((
+        wrap_angle_360(v1.arg().to_degrees()),
+        wrap_angle_360(i1.arg().to_degrees()),
+        harmonic_angles,
+    ))
 }
 
 #[allow(dead_code)]
@@ -125,17 +190,45 @@ fn phase_angle_from_power_values(apparent_power: f64, active_power: f64, react_p
 * @brief Calculate the phase angles from voltage and current signals.
 * @param voltage_signal Voltage signal
 * @param current_signal Current signal
-* @param samples_per_cycle Number of samples per cycle
+* @param frequency Fundamental frequency in Hz
+* @param adc_samples_second Number of ADC samples per second
+* @param num_harmonics Highest harmonic order for the per-harmonic angles
+* @param responses Frequency responses of the voltage and current sensors, removed from the harmonics
 * @return PhaseAngleMetrics structure containing the phase angles and direction
 * @note The phase angle is positive for inductive loads and negative for capacitive loads.
+* @note The spectral phasors are used when available; the zero crossings are the fallback.
 */
 fn all_phase_angles_from_signals(
     voltage_signal: &[f64],
     current_signal: &[f64],
-    samples_per_cycle: f64,
+    frequency: f64,
+    adc_samples_second: f64,
+    num_harmonics: usize,
+    responses: [&FrequencyResponse; 2],
 ) -> PhaseAngleMetrics {
-    let (v_angle, c_angle) = absolute_phase_angles_from_signals(&voltage_signal, &current_signal, samples_per_cycle);
-    let c2v_angle: f64 = phase_angle_from_signals(&voltage_signal, &current_signal);
+    let spectral = spectral_phase_angles_from_signals(
+        voltage_signal,
+        current_signal,
+        frequency,
+        adc_samples_second,
+        num_harmonics,
+        responses,
+    );
+
+    let (v_angle, c_angle, harmonic_angles, method) = match spectral {
+        Some((v_angle, c_angle, harmonic_angles)) => {
+            (v_angle, c_angle, harmonic_angles, PhaseAngleMethod::SpectralPhasor)
+        }
+        None => {
+            match absolute_phase_angles_from_signals(voltage_signal, current_signal, adc_samples_second / frequency) {
+                Some((v_angle, c_angle)) => (v_angle, c_angle, vec![], PhaseAngleMethod::ZeroCrossing),
+                None => (0.0, 0.0, vec![], PhaseAngleMethod::None),
+            }
+        }
+    };
+
+    // Positivo cuando la corriente retrasa a la tensión
+    let c2v_angle = wrap_angle_180(v_angle - c_angle);
 
     let direction = if c2v_angle > 1e-6 {
         PhaseDirection::Inductive
@@ -150,21 +243,62 @@ fn all_phase_angles_from_signals(
         v_angle,
         c_angle,
         direction,
+        harmonic_angles,
+        method,
     }
 }
 
 /*
 * @brief Update the phase angles in the MetrologyInsightSocket structure.
 * @param socket Pointer to the MetrologyInsightSocket structure.
-* @param voltage_signal Voltage signal
-* @param current_signal Current signal
-* @param samples_per_cycle Number of samples per cycle
+* @param adc_samples_second Number of ADC samples per second
+* @param num_harmonics Highest harmonic order for the per-harmonic angles
+* @param responses Frequency responses of the voltage and current sensors, removed from the harmonics
 * @note This function updates the phase angles in the MetrologyInsightSocket structure.
 */
-pub fn update_phase_angles(socket: &mut MetrologyInsightSocket, samples_per_cycle: f64) {
+pub fn update_phase_angles(
+    socket: &mut MetrologyInsightSocket,
+    adc_samples_second: f64,
+    num_harmonics: usize,
+    responses: [&FrequencyResponse; 2],
+) {
     socket.phase_angles = all_phase_angles_from_signals(
         &socket.voltage_signal.real_wave,
         &socket.current_signal.real_wave,
-        samples_per_cycle,
+        socket.voltage_signal.fundamental_frequency(),
+        adc_samples_second,
+        num_harmonics,
+        responses,
     );
 }
+
+/*
+* @brief Estimate the sampling skew of the current channel from a purely resistive reference load.
+* @param voltage_signal Voltage signal holding at least one cycle
+* @param current_signal Current signal sampled at the same instants
+* @param frequency Fundamental frequency in Hz
+* @param adc_samples_second Number of ADC samples per second
+* @return Skew of the current channel relative to the voltage channel (µs), or the reason it cannot be estimated
+* @note With a resistive load V and I are in phase, so any lead of the current fundamental is the time by which
+*       it was sampled later (positive) or earlier (negative). The sensor phase error at that frequency is
+*       included. If the signals were already compensated the result is the residual to add to skew_us.
+*/
+pub fn estimate_channel_skew(
+    voltage_signal: &[f64],
+    current_signal: &[f64],
+    frequency: f64,
+    adc_samples_second: f64,
+) -> Result<f64, MetrologyError> {
+    let v_phasors = compute_harmonic_phasors(voltage_signal, frequency, adc_samples_second, 1)?;
+    let i_phasors = compute_harmonic_phasors(current_signal, frequency, adc_samples_second, 1)?;
+
+    let (v1, i1) = match (v_phasors.get(1), i_phasors.get(1)) {
+        (Some(&v1), Some(&i1)) if v1.norm() > 0.0 && i1.norm() > 0.0 => (v1, i1),
+        _ => return Err(MetrologyError::NoFundamental),
+    };
+
+    // Adelanto de la corriente en radianes, en (-π, π]
+    let lead = (i1 / v1).arg();
+
+    Ok(lead / (2.0 * PI * frequency) * 1e6)
+}
@@ -1,4 +1,7 @@
-use crate::{MetrologyInsightSignal, MetrologyInsightSocket, PowerMetrics};
+use crate::{
+    compute_cycle_rms, compute_harmonic_phasors, FrequencyResponse, MetrologyError, MetrologyInsightSignal,
+    MetrologyInsightSocket, PowerDecomposition, PowerMetrics,
+};
 
 #[allow(dead_code)]
 
@@ -11,42 +14,75 @@ use crate::{MetrologyInsightSignal, MetrologyInsightSocket, PowerMetrics};
 * @param power_factor Power factor
 * @return Real power in watts
 * @note The power factor should be between -1 and 1.
- */
+*/
 fn real_power_from_rms_and_power_factor(voltage_rms: f64, current_rms: f64, power_factor: f64) -> f64 {
     voltage_rms * current_rms * power_factor
 }
 
+/*
+* @brief Check that the voltage and current buffers can be multiplied sample by sample.
+* @param signal_v Voltage signal
+* @param signal_i Current signal
+* @return Number of samples, or the reason the buffers cannot be used
+*/
+fn matching_length(signal_v: &[f64], signal_i: &[f64]) -> Result<usize, MetrologyError> {
+    if signal_v.is_empty() || signal_i.is_empty() {
+        return Err(MetrologyError::BufferTooShort {
+            required: 1,
+            actual: signal_v.len().min(signal_i.len()),
+        });
+    }
+    if signal_v.len() != signal_i.len() {
+        return Err(MetrologyError::LengthMismatch {
+            voltage: signal_v.len(),
+            current: signal_i.len(),
+        });
+    }
+
+    Ok(signal_v.len())
+}
+
 /*
 * @brief Calculate the real power from RMS voltage and RMS current.
 * @param voltage_rms RMS voltage
 * @param current_rms RMS current
-* @return Real power in watts
+* @return Real power in watts, or the reason it cannot be computed
 * @note This function assumes a power factor of 1.0 (purely resistive load).
 */
-fn real_power_from_signals(signal_v: &[f64], signal_i: &[f64]) -> f64 {
-    if signal_v.is_empty() || signal_v.len() != signal_i.len() {
-        return 0.0; // o mejor: Option<f64> para manejar error
-    }
-    signal_v.iter().zip(signal_i.iter()).map(|(&v, &i)| v * i).sum::<f64>() / signal_v.len() as f64
+fn real_power_from_signals(signal_v: &[f64], signal_i: &[f64]) -> Result<f64, MetrologyError> {
+    let n = matching_length(signal_v, signal_i)?;
+
+    Ok(signal_v.iter().zip(signal_i.iter()).map(|(&v, &i)| v * i).sum::<f64>() / n as f64)
 }
 
 /* ----------------- React Power Functions ------------------ */
 
 /*
-* @brief Calculate the apparent power from RMS voltage and RMS current.
-* @param voltage_rms RMS voltage
-* @param current_rms RMS current
-* @return Apparent power in volt-amperes (VA)
-* @note This function assumes a power factor of 1.0 (purely resistive load).
+* @brief Calculate the signed reactive power from voltage and current signals.
+* @param signal_v Voltage signal
+* @param signal_i Current signal
+* @param frequency Fundamental frequency in Hz
+* @param adc_samples_second Number of ADC samples per second
+* @return Reactive power in volt-amperes reactive (VAR), or the reason it cannot be computed
+* @note Q1 = Im(V1·I1*) from the fundamental RMS phasors of one cycle, so the result does not depend on the buffer
+*       holding a whole number of samples per cycle and the harmonics do not leak into it.
+* @note The result is positive for inductive loads (current lags) and negative for capacitive loads (current leads).
 */
-fn reactive_power_from_apparent_and_active(apparent_power: f64, active_power: f64) -> f64 {
-    let mut react_power: f64 = 0.0;
+fn reactive_power_from_signals(
+    signal_v: &[f64],
+    signal_i: &[f64],
+    frequency: f64,
+    adc_samples_second: f64,
+) -> Result<f64, MetrologyError> {
+    matching_length(signal_v, signal_i)?;
 
-    if active_power < apparent_power {
-        react_power = (apparent_power.powi(2) - active_power.powi(2)).sqrt();
+    let v_phasors = compute_harmonic_phasors(signal_v, frequency, adc_samples_second, 1)?;
+    let i_phasors = compute_harmonic_phasors(signal_i, frequency, adc_samples_second, 1)?;
+    if v_phasors.len() < 2 || i_phasors.len() < 2 {
+        return Err(MetrologyError::NoFundamental);
     }
 
-    react_power
+    Ok((v_phasors[1] * i_phasors[1].conj()).im)
 }
 
 /* ----------------- Apparent Power Functions ------------------ */
@@ -88,27 +124,169 @@ fn power_factor_from_apparent_and_real(apparent_power: f64, real_power: f64) ->
 fn calculate_all_power_metrics(
     voltage_signal: &mut MetrologyInsightSignal,
     current_signal: &mut MetrologyInsightSignal,
-) -> PowerMetrics {
+    adc_samples_second: f64,
+) -> Result<PowerMetrics, MetrologyError> {
     // Real power a partir de RMS y factor de potencia
-    let real_power = real_power_from_signals(&voltage_signal.real_wave, &current_signal.real_wave);
+    let real_power = real_power_from_signals(&voltage_signal.real_wave, &current_signal.real_wave)?;
 
     // Potencia aparente a partir de RMS
     let apparent_power = apparent_power_from_rms(voltage_signal.rms, current_signal.rms);
 
-    // Potencia reactiva a partir de aparente y real
-    let reactive_power = reactive_power_from_apparent_and_active(apparent_power, real_power);
+    // Potencia reactiva con signo a partir de los fasores del fundamental
+    let reactive_power = reactive_power_from_signals(
+        &voltage_signal.real_wave,
+        &current_signal.real_wave,
+        voltage_signal.fundamental_frequency(),
+        adc_samples_second,
+    )?;
 
     // Factor de potencia recalculado para asegurar coherencia
     let power_factor_calc = power_factor_from_apparent_and_real(apparent_power, real_power);
 
-    PowerMetrics {
+    Ok(PowerMetrics {
         real_power,
         reactive_power,
         apparent_power,
         power_factor: power_factor_calc,
+    })
+}
+
+/*
+* @brief Update the power metrics in the MetrologyInsightSocket structure.
+* @param socket Pointer to the MetrologyInsightSocket structure.
+* @param adc_samples_second Number of ADC samples per second.
+* @return Ok if the metrics were updated, or the reason they were kept unchanged
+* @note The reactive power is signed: positive when inductive, negative when capacitive.
+*/
+pub fn update_power_metrics(
+    socket: &mut MetrologyInsightSocket,
+    adc_samples_second: f64,
+) -> Result<(), MetrologyError> {
+    socket.power_metrics = calculate_all_power_metrics(
+        &mut socket.voltage_signal,
+        &mut socket.current_signal,
+        adc_samples_second,
+    )?;
+
+    Ok(())
+}
+
+/* ----------------- IEEE 1459 Power Decomposition ------------------ */
+
+/*
+* @brief Calculate the IEEE 1459 power decomposition from voltage and current signals.
+* @param voltage_signal Voltage signal
+* @param current_signal Current signal
+* @param real_power Total real power in watts
+* @param adc_samples_second Number of ADC samples per second
+* @param max_order Highest harmonic order included in the harmonic active power
+* @param responses Frequency responses of the voltage and current sensors, removed from the harmonics
+* @return PowerDecomposition structure, or the reason the fundamental phasors cannot be computed
+* @note The fundamental and harmonic terms come from the per-harmonic RMS phasors of one cycle.
+* @note VH and IH are the non-fundamental RMS values, sqrt(X² - X1²), so they include interharmonics and noise.
+*       The total RMS values get the change of energy of the corrected harmonics.
+*/
+fn calculate_power_decomposition(
+    voltage_signal: &MetrologyInsightSignal,
+    current_signal: &MetrologyInsightSignal,
+    real_power: f64,
+    adc_samples_second: f64,
+    max_order: usize,
+    responses: [&FrequencyResponse; 2],
+) -> Result<PowerDecomposition, MetrologyError> {
+    let frequency = voltage_signal.fundamental_frequency();
+
+    // El fundamental se calcula siempre, aunque el análisis armónico esté desactivado
+    let max_order = max_order.max(1);
+    let mut v_phasors = compute_harmonic_phasors(&voltage_signal.real_wave, frequency, adc_samples_second, max_order)?;
+    let mut i_phasors = compute_harmonic_phasors(&current_signal.real_wave, frequency, adc_samples_second, max_order)?;
+    if v_phasors.len() < 2 || i_phasors.len() < 2 {
+        return Err(MetrologyError::NoFundamental);
     }
+
+    // Valores RMS totales sobre la misma ventana que los fasores
+    let mut v_rms = compute_cycle_rms(&voltage_signal.real_wave, frequency, adc_samples_second)?;
+    let mut i_rms = compute_cycle_rms(&current_signal.real_wave, frequency, adc_samples_second)?;
+
+    // Corrección de la respuesta en frecuencia de los sensores, también en la energía total
+    for (phasors, rms, response) in [
+        (&mut v_phasors, &mut v_rms, responses[0]),
+        (&mut i_phasors, &mut i_rms, responses[1]),
+    ] {
+        let before = phasors.iter().skip(2).map(|p| p.norm_sqr()).sum::<f64>();
+        response.correct_phasors(phasors, frequency);
+        let after = phasors.iter().skip(2).map(|p| p.norm_sqr()).sum::<f64>();
+        *rms = (rms.powi(2) + after - before).max(0.0).sqrt();
+    }
+
+    // Potencias del fundamental: S1 = V1·I1*
+    let s1_complex = v_phasors[1] * i_phasors[1].conj();
+    let v1 = v_phasors[1].norm();
+    let i1 = i_phasors[1].norm();
+
+    // Potencia activa armónica: suma de Vh·Ih·cos θh
+    let harmonic_active_power = v_phasors
+        .iter()
+        .zip(i_phasors.iter())
+        .skip(2)
+        .map(|(v, i)| (v * i.conj()).re)
+        .sum::<f64>();
+
+    // Componentes no fundamentales
+    let vh = (v_rms.powi(2) - v1.powi(2)).max(0.0).sqrt();
+    let ih = (i_rms.powi(2) - i1.powi(2)).max(0.0).sqrt();
+
+    let apparent_power = v_rms * i_rms;
+    let fundamental_apparent_power = v1 * i1;
+    let current_distortion_power = v1 * ih;
+    let voltage_distortion_power = vh * i1;
+    let harmonic_apparent_power = vh * ih;
+
+    Ok(PowerDecomposition {
+        fundamental_active_power: s1_complex.re,
+        fundamental_reactive_power: s1_complex.im,
+        fundamental_apparent_power,
+        harmonic_active_power,
+        current_distortion_power,
+        voltage_distortion_power,
+        harmonic_apparent_power,
+        non_fundamental_apparent_power: (current_distortion_power.powi(2)
+            + voltage_distortion_power.powi(2)
+            + harmonic_apparent_power.powi(2))
+        .sqrt(),
+        nonactive_power: (apparent_power.powi(2) - real_power.powi(2)).max(0.0).sqrt(),
+        displacement_power_factor: power_factor_from_apparent_and_real(fundamental_apparent_power, s1_complex.re),
+        distortion_factor: if apparent_power > 0.0 {
+            (fundamental_apparent_power / apparent_power).min(1.0)
+        } else {
+            0.0
+        },
+    })
 }
 
-pub fn update_power_metrics(socket: &mut MetrologyInsightSocket) {
-    socket.power_metrics = calculate_all_power_metrics(&mut socket.voltage_signal, &mut socket.current_signal);
+/*
+* @brief Update the IEEE 1459 power decomposition in the MetrologyInsightSocket structure.
+* @param socket Pointer to the MetrologyInsightSocket structure.
+* @param adc_samples_second Number of ADC samples per second.
+* @param num_harmonics Highest harmonic order included in the harmonic active power.
+* @param responses Frequency responses of the voltage and current sensors, removed from the harmonics.
+* @return Ok if the decomposition was updated, or the reason it was kept unchanged
+* @note Must run after update_power_metrics, as it reuses the total real power.
+*/
+pub fn update_power_decomposition(
+    socket: &mut MetrologyInsightSocket,
+    adc_samples_second: f64,
+    num_harmonics: usize,
+    responses: [&FrequencyResponse; 2],
+) -> Result<(), MetrologyError> {
+    socket.power_decomposition = calculate_power_decomposition(
+        &socket.voltage_signal,
+        &socket.current_signal,
+        socket.power_metrics.real_power,
+        adc_samples_second,
+        num_harmonics,
+        responses,
+    )?;
+
+    Ok(())
 }
@@ -1,4 +1,4 @@
-use crate::MetrologyInsightSocket;
+use crate::{HarmonicComponent, MetrologyInsightSocket};
 
 /*
 * @brief Functions to print the data of the Metrology Insight device.
@@ -9,7 +9,22 @@ pub fn print_voltage_signal(data: &MetrologyInsightSocket) {
     log::info!("Voltage:");
     log::info!("  Peak: {:.3} V", data.voltage_signal.peak);
     log::info!("  RMS: {:.3} V", data.voltage_signal.rms);
-    log::info!("  Frequency: {:.3} Hz\n", data.voltage_signal.freq_zc);
+    if data.voltage_signal.transformer_ratio != 1.0 {
+        log::info!(
+            "  Secondary: {:.3} V RMS, {:.3} V peak (ratio {:.2})",
+            data.voltage_signal.secondary_rms,
+            data.voltage_signal.secondary_peak,
+            data.voltage_signal.transformer_ratio
+        );
+    }
+    log::info!("  DC: {:.3} V", data.voltage_signal.dc_offset);
+    log::info!(
+        "  Frequency: {:.3} Hz ± {:.4} Hz (quality {:.2}, {})\n",
+        data.voltage_signal.freq_zc,
+        data.voltage_signal.freq_uncertainty,
+        data.voltage_signal.freq_quality,
+        data.voltage_signal.freq_estimator.as_str()
+    );
 }
 
 /*
@@ -21,7 +36,22 @@ pub fn print_current_signal(data: &MetrologyInsightSocket) {
     log::info!("Current:");
     log::info!("  Peak: {:.3} A", data.current_signal.peak);
     log::info!("  RMS: {:.3} A", data.current_signal.rms);
-    log::info!("  Frequency: {:.3} Hz\n", data.current_signal.freq_zc);
+    if data.current_signal.transformer_ratio != 1.0 {
+        log::info!(
+            "  Secondary: {:.3} A RMS, {:.3} A peak (ratio {:.2})",
+            data.current_signal.secondary_rms,
+            data.current_signal.secondary_peak,
+            data.current_signal.transformer_ratio
+        );
+    }
+    log::info!("  DC: {:.3} A", data.current_signal.dc_offset);
+    log::info!(
+        "  Frequency: {:.3} Hz ± {:.4} Hz (quality {:.2}, {})\n",
+        data.current_signal.freq_zc,
+        data.current_signal.freq_uncertainty,
+        data.current_signal.freq_quality,
+        data.current_signal.freq_estimator.as_str()
+    );
 }
 
 /*
@@ -30,28 +60,60 @@ pub fn print_current_signal(data: &MetrologyInsightSocket) {
 * @note This function prints the current signal data.
 */
 pub fn print_harmonics(data: &MetrologyInsightSocket) {
-    log::info!("Voltage Harmonics:");
-    log::info!("  THD: {:.3} DB", data.voltage_signal.thd);
-    log::info!(
-        "  Harmonics: [{}]",
-        data.voltage_signal
-            .harmonics
+    fn format_harmonics(harmonics: &[HarmonicComponent]) -> String {
+        harmonics
             .iter()
-            .map(|h| format!("{:.3}", h))
+            .map(|h| format!("H{}: {:.3}% ({:.3})", h.order, h.percent, h.rms))
             .collect::<Vec<_>>()
             .join(", ")
+    }
+
+    log::info!("Voltage Harmonics:");
+    log::info!(
+        "  THD: {:.3} % ({:.3} DB)",
+        data.voltage_signal.thd_percent,
+        data.voltage_signal.thd
     );
+    log::info!("  Harmonics: [{}]", format_harmonics(&data.voltage_signal.harmonics));
     log::info!("Current Harmonics:");
-    log::info!("  THD: {:.3} DB", data.current_signal.thd);
     log::info!(
-        "  Harmonics: [{}]",
-        data.current_signal
-            .harmonics
+        "  THD: {:.3} % ({:.3} DB)",
+        data.current_signal.thd_percent,
+        data.current_signal.thd
+    );
+    log::info!("  Harmonics: [{}]", format_harmonics(&data.current_signal.harmonics));
+}
+
+/*
+* @brief Print the IEC 61000-4-7 harmonic groups
+* @param data Pointer to the MetrologyInsightSocket structure.
+* @note Only the harmonic groups are printed; subgroups and interharmonics are exported through the proto.
+*/
+pub fn print_harmonic_groups(data: &MetrologyInsightSocket) {
+    fn format_groups(groups: &[f64]) -> String {
+        groups
             .iter()
-            .map(|h| format!("{:.3}", h))
+            .enumerate()
+            .map(|(i, g)| format!("G{}: {:.3}", i + 1, g))
             .collect::<Vec<_>>()
             .join(", ")
+    }
+
+    let voltage = &data.voltage_signal.harmonic_groups;
+    let current = &data.current_signal.harmonic_groups;
+    log::info!("Harmonic Groups (IEC 61000-4-7, {} cycles):", voltage.window_cycles);
+    log::info!(
+        "  Voltage THDG: {:.3} % THDS: {:.3} %",
+        voltage.thdg_percent,
+        voltage.thds_percent
+    );
+    log::info!("  Voltage Groups: [{}]", format_groups(&voltage.harmonic_groups));
+    log::info!(
+        "  Current THDG: {:.3} % THDS: {:.3} %",
+        current.thdg_percent,
+        current.thds_percent
     );
+    log::info!("  Current Groups: [{}]\n", format_groups(&current.harmonic_groups));
 }
 
 /*
@@ -64,7 +126,50 @@ pub fn print_power(data: &MetrologyInsightSocket) {
     log::info!("  Active: {:.3} W", data.power_metrics.real_power);
     log::info!("  Reactive: {:.3} VAR", data.power_metrics.reactive_power);
     log::info!("  Apparent: {:.3} VA", data.power_metrics.apparent_power);
-    log::info!("  Factor: {:.3}\n", data.power_metrics.power_factor);
+    log::info!("  Factor: {:.3}", data.power_metrics.power_factor);
+    log::info!("  Meter: {}\n", data.creep_state.as_str());
+}
+
+/*
+* @brief Print the IEEE 1459 power decomposition
+* @param data Pointer to the MetrologyInsightSocket structure.
+* @note This function prints the fundamental and non-fundamental power components.
+*/
+pub fn print_power_decomposition(data: &MetrologyInsightSocket) {
+    let decomposition = &data.power_decomposition;
+    log::info!("Power Decomposition (IEEE 1459):");
+    log::info!(
+        "  Fundamental Active (P1): {:.3} W",
+        decomposition.fundamental_active_power
+    );
+    log::info!(
+        "  Fundamental Reactive (Q1): {:.3} VAR",
+        decomposition.fundamental_reactive_power
+    );
+    log::info!(
+        "  Fundamental Apparent (S1): {:.3} VA",
+        decomposition.fundamental_apparent_power
+    );
+    log::info!("  Harmonic Active (PH): {:.3} W", decomposition.harmonic_active_power);
+    log::info!(
+        "  Current Distortion (DI): {:.3} VAR",
+        decomposition.current_distortion_power
+    );
+    log::info!(
+        "  Voltage Distortion (DV): {:.3} VAR",
+        decomposition.voltage_distortion_power
+    );
+    log::info!(
+        "  Harmonic Apparent (SH): {:.3} VA",
+        decomposition.harmonic_apparent_power
+    );
+    log::info!(
+        "  Non-fundamental Apparent (SN): {:.3} VA",
+        decomposition.non_fundamental_apparent_power
+    );
+    log::info!("  Nonactive (N): {:.3} VAR", decomposition.nonactive_power);
+    log::info!("  Displacement Factor: {:.3}", decomposition.displacement_power_factor);
+    log::info!("  Distortion Factor: {:.3}\n", decomposition.distortion_factor);
 }
 
 /*
@@ -77,7 +182,8 @@ pub fn print_phase_angle(data: &MetrologyInsightSocket) {
     log::info!("  Current to Voltage Angle: {:.2}º", data.phase_angles.c2v_angle);
     log::info!("  Voltage Angle: {:.2}º", data.phase_angles.v_angle);
     log::info!("  Current Angle: {:.2}º", data.phase_angles.c_angle);
-    log::info!("  Phase direction: {}\n", data.phase_angles.direction_description());
+    log::info!("  Phase direction: {}", data.phase_angles.direction_description());
+    log::info!("  Method: {}\n", data.phase_angles.method.as_str());
 }
 
 /*
@@ -115,6 +221,80 @@ pub fn print_reactive_energy(data: &MetrologyInsightSocket) {
     log::info!("  Reactive Energy Q4: {:.3} kWh\n", data.energy_metrics.reactive.q4);
 }
 
+/*
+* @brief Print the apparent, fundamental and harmonic energy.
+* @param data Pointer to the MetrologyInsightSocket structure.
+*/
+pub fn print_component_energy(data: &MetrologyInsightSocket) {
+    let energy = &data.energy_metrics;
+    log::info!("Component Energy:");
+    for (name, unit, metrics) in [
+        ("Apparent", "kVAh", &energy.apparent),
+        ("Fundamental", "kWh", &energy.fundamental),
+        ("Harmonic", "kWh", &energy.harmonic),
+    ] {
+        log::info!(
+            "  {}: {:.3} {} imported, {:.3} {} exported",
+            name,
+            metrics.imported,
+            unit,
+            metrics.exported,
+            unit
+        );
+    }
+    log::info!("");
+}
+
+/*
+* @brief Print the time accounted by the energy registers.
+* @param data Pointer to the MetrologyInsightSocket structure.
+*/
+pub fn print_energy_coverage(data: &MetrologyInsightSocket) {
+    let coverage = &data.energy_metrics.coverage;
+    log::info!("Energy Coverage:");
+    log::info!("  Covered: {:.3} s", coverage.covered_sec);
+    log::info!("  Bridged: {:.3} s", coverage.bridged_sec);
+    log::info!(
+        "  Uncovered: {:.3} s ({} gaps)\n",
+        coverage.uncovered_sec,
+        coverage.gaps
+    );
+}
+
+/*
+* @brief Print the temperature used by the temperature compensation.
+* @param data Pointer to the MetrologyInsightSocket structure.
+* @note Nothing is printed without a temperature sensor.
+*/
+pub fn print_temperature(data: &MetrologyInsightSocket) {
+    if let Some(temperature) = data.temperature {
+        log::info!("Temperature: {:.1} ºC\n", temperature);
+    }
+}
+
+/*
+* @brief Print the quality flags of both signals.
+* @param data Pointer to the MetrologyInsightSocket structure.
+* @note Nothing is printed while every measurement is good.
+*/
+pub fn print_quality(data: &MetrologyInsightSocket) {
+    if data.quality.is_empty() {
+        return;
+    }
+
+    log::info!("Quality:");
+    for (name, quality) in [
+        ("Voltage", data.voltage_signal.quality),
+        ("Current", data.current_signal.quality),
+        ("Metrics", data.quality),
+    ] {
+        if !quality.is_empty() {
+            log::info!("  {}: {}", name, quality.names().join(", "));
+        }
+    }
+    log::info!("");
+}
+
 /*
 * @brief Print all data from the Metrology Insight device.
 * @param data Pointer to the MetrologyInsightSocket structure.
@@ -124,8 +304,14 @@ pub fn print_all(data: &MetrologyInsightSocket) {
     print_voltage_signal(data);
     print_current_signal(data);
     print_harmonics(data);
+    print_harmonic_groups(data);
     print_power(data);
+    print_power_decomposition(data);
     print_phase_angle(data);
     print_active_energy(data);
     print_reactive_energy(data);
+    print_component_energy(data);
+    print_energy_coverage(data);
+    print_temperature(data);
+    print_quality(data);
 }
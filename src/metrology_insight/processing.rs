@@ -1,38 +1,229 @@
 use crate::{
-    print_all, process_signal, update_phase_angles, update_power_metrics, update_total_energy, MetrologyInsight,
-    MetrologyInsightSignal,
+    print_all, try_process_signal, update_creep_state, update_harmonic_groups, update_phase_angles,
+    update_power_decomposition, update_power_metrics, update_total_energy, AdcSample, MetrologyError, MetrologyInsight,
+    MetrologyInsightConfig, MetrologyInsightSignalType, MetrologyInsightSocket, MetrologyInsightState, QualityFlags,
+    SampleFrame, TemperatureSensor,
 };
 
 impl MetrologyInsight {
     /*
-     * @brief Process the voltage and current signals.
-     * @param voltage_signal Pointer to the voltage signal.
-     * @param current_signal Pointer to the current signal.
+     * @brief Create a new instance with an empty socket and processing state.
+     * @param config Configuration of the instance.
+     */
+    pub fn new(config: MetrologyInsightConfig) -> Self {
+        let mut socket = MetrologyInsightSocket::default();
+        socket.voltage_signal.freq_nominal = config.default_nominal_frequency();
+        socket.current_signal.freq_nominal = config.default_nominal_frequency();
+        socket.current_signal.signal_type = MetrologyInsightSignalType::Current;
+
+        Self {
+            socket,
+            state: MetrologyInsightState::new(&config),
+            config,
+            temperature_sensor: None,
+        }
+    }
+
+    /*
+     * @brief Set the sensor that gives the temperature of the temperature compensation.
+     * @param sensor Temperature sensor, or None to stop compensating the drift
+     * @note The sensor is read once per processed frame and the value is stored in the socket. A failed read
+     *       keeps the last temperature.
      */
-    pub fn process_and_update_metrics(
+    pub fn set_temperature_sensor(&mut self, sensor: Option<Box<dyn TemperatureSensor>>) {
+        self.temperature_sensor = sensor;
+        if self.temperature_sensor.is_none() {
+            self.socket.temperature = None;
+        }
+    }
+
+    /*
+     * @brief Read the temperature sensor, if any, into the socket.
+     */
+    fn update_temperature(&mut self) {
+        if let Some(sensor) = self.temperature_sensor.as_mut() {
+            match sensor.read() {
+                Ok(temperature) => self.socket.temperature = Some(temperature),
+                Err(err) => log::debug!("Temperature not updated: {}", err),
+            }
+        }
+    }
+
+    /*
+     * @brief Process a frame of the voltage and current channels.
+     * @param voltage_frame Samples of the voltage channel.
+     * @param current_frame Samples of the current channel, acquired at the same instants.
+     * @note Errors are only logged. Use try_process_and_update_metrics to handle them.
+     */
+    pub fn process_and_update_metrics<T: AdcSample>(
         &mut self,
-        voltage_signal: &mut MetrologyInsightSignal,
-        current_signal: &mut MetrologyInsightSignal,
+        voltage_frame: &SampleFrame<T>,
+        current_frame: &SampleFrame<T>,
     ) {
-        process_signal(
+        if let Err(err) = self.try_process_and_update_metrics(voltage_frame, current_frame) {
+            log::debug!("Metrology update incomplete: {}", err);
+        }
+    }
+
+    /*
+     * @brief Process a frame of the voltage and current channels, reporting the first error found.
+     * @param voltage_frame Samples of the voltage channel.
+     * @param current_frame Samples of the current channel, acquired at the same instants.
+     * @return Ok if every metric was updated, or the first error found
+     * @note Both frames are always processed. If either is rejected, phase, power and energy are not updated,
+     *       so that a stale buffer is never combined with a new one.
+     * @note A failed power decomposition does not stop the energy update; its error is returned afterwards.
+     * @note Energy is integrated over the acquisition time of the frames: the time of a rejected frame is
+     *       handled as a gap by the next frame that reaches the registers.
+     * @note Energy is only accumulated once the current and the apparent power have reached the anti-creep
+     *       thresholds of the configuration.
+     * @note A rejected frame raises STALE (and the flag matching its reason) on its signal. The quality of the
+     *       socket gathers the flags of both signals and those of the combined metrics.
+     */
+    pub fn try_process_and_update_metrics<T: AdcSample>(
+        &mut self,
+        voltage_frame: &SampleFrame<T>,
+        current_frame: &SampleFrame<T>,
+    ) -> Result<(), MetrologyError> {
+        if voltage_frame.channel != MetrologyInsightSignalType::Voltage
+            || current_frame.channel != MetrologyInsightSignalType::Current
+        {
+            return Err(MetrologyError::InvalidParameter("channel"));
+        }
+
+        self.update_temperature();
+
+        let voltage_result = try_process_signal(&mut self.socket, voltage_frame, &self.config, &mut self.state.voltage);
+
+        let current_result = try_process_signal(&mut self.socket, current_frame, &self.config, &mut self.state.current);
+
+        // Una trama rechazada deja los valores anteriores: se marcan como obsoletos
+        if let Err(err) = &voltage_result {
+            self.socket.voltage_signal.quality |= QualityFlags::from_rejection(err);
+        }
+        if let Err(err) = &current_result {
+            self.socket.current_signal.quality |= QualityFlags::from_rejection(err);
+        }
+
+        if self.config.harmonic_grouping {
+            update_harmonic_groups(
+                &mut self.socket.voltage_signal,
+                &mut self.state.voltage.grouping_buffer,
+                voltage_result.is_ok(),
+                self.config.adc_samples_seconds,
+                self.config.num_harmonics,
+                &self.config.voltage.frequency_response,
+            );
+
+            update_harmonic_groups(
+                &mut self.socket.current_signal,
+                &mut self.state.current.grouping_buffer,
+                current_result.is_ok(),
+                self.config.adc_samples_seconds,
+                self.config.num_harmonics,
+                &self.config.current.frequency_response,
+            );
+        }
+
+        self.socket.quality = self.socket.voltage_signal.quality | self.socket.current_signal.quality;
+
+        voltage_result?;
+        current_result?;
+
+        let responses = [
+            &self.config.voltage.frequency_response,
+            &self.config.current.frequency_response,
+        ];
+
+        update_phase_angles(
+            &mut self.socket,
+            self.config.adc_samples_seconds,
+            self.config.num_harmonics,
+            responses,
+        );
+
+        if let Err(err) = update_power_metrics(&mut self.socket, self.config.adc_samples_seconds) {
+            self.socket.quality |= QualityFlags::STALE;
+            return Err(err);
+        }
+
+        let decomposition_result = update_power_decomposition(
             &mut self.socket,
-            voltage_signal,
             self.config.adc_samples_seconds,
-            self.config.avg_sec,
+            self.config.num_harmonics,
+            responses,
         );
 
-        process_signal(
+        if decomposition_result.is_err() {
+            self.socket.quality |= QualityFlags::HARMONICS_FAILED;
+        }
+
+        update_creep_state(
             &mut self.socket,
-            current_signal,
+            &mut self.state.creep,
+            &self.config.anti_creep,
+            &self.state.time_base,
             self.config.adc_samples_seconds,
-            self.config.avg_sec,
         );
 
-        update_phase_angles(&mut self.socket, self.config.adc_samples_per_cycle);
+        update_total_energy(&mut self.socket, &mut self.state.time_base, &self.config);
+
+        decomposition_result
+    }
+
+    /*
+     * @brief Feed a frame of the continuous sample stream and process every cycle it completes.
+     * @param voltage_frame Samples of the voltage channel (any length).
+     * @param current_frame Samples of the current channel, acquired at the same instants.
+     * @return Number of cycles processed, or LengthMismatch if the frames differ in length (they are dropped)
+     * @note Cycles are cut at the rising zero crossings of the voltage and may span several frames, so
+     *       frames from the driver do not need to be aligned with the signal. Each cycle is processed as
+     *       a frame by process_and_update_metrics, with the acquisition time of its first sample; errors of a
+     *       single cycle are only logged.
+     * @note When samples are discarded (no voltage, cycle out of range, gap in the sequence numbers or in the
+     *       timestamps) the state that assumes contiguous frames is reset before the next cycle.
+     */
+    pub fn push_samples<T: AdcSample>(
+        &mut self,
+        voltage_frame: &SampleFrame<T>,
+        current_frame: &SampleFrame<T>,
+    ) -> Result<usize, MetrologyError> {
+        if voltage_frame.channel != MetrologyInsightSignalType::Voltage
+            || current_frame.channel != MetrologyInsightSignalType::Current
+        {
+            return Err(MetrologyError::InvalidParameter("channel"));
+        }
+
+        self.state.stream.push(voltage_frame, current_frame, &self.config)?;
+
+        let mut cycles = 0;
+        while let Some(cycle) = self.state.stream.next_cycle(&self.config) {
+            if !cycle.contiguous {
+                self.state.voltage.reset();
+                self.state.current.reset();
+            }
+
+            let voltage_cycle = SampleFrame {
+                channel: MetrologyInsightSignalType::Voltage,
+                samples: cycle.voltage,
+                sample_rate: voltage_frame.sample_rate,
+                timestamp_us: cycle.timestamp_us,
+                sequence: cycle.sequence,
+            };
+
+            let current_cycle = SampleFrame {
+                channel: MetrologyInsightSignalType::Current,
+                samples: cycle.current,
+                sample_rate: current_frame.sample_rate,
+                timestamp_us: cycle.timestamp_us,
+                sequence: cycle.sequence,
+            };
 
-        update_power_metrics(&mut self.socket);
+            self.process_and_update_metrics(&voltage_cycle, &current_cycle);
+            cycles += 1;
+        }
 
-        update_total_energy(&mut self.socket, self.config.adc_samples_seconds);
+        Ok(cycles)
     }
 
     /*
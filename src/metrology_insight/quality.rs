@@ -0,0 +1,133 @@
+use crate::MetrologyError;
+use core::ops::{BitOr, BitOrAssign};
+
+pub const UNDER_RANGE_FRACTION: f64 = 0.02; // Amplitud pico a pico mínima, como fracción del rango del ADC
+
+/// Conditions that make a measurement suspect, as a bitfield.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct QualityFlags(u32);
+
+impl QualityFlags {
+    pub const SATURATION: Self = Self(1 << 0); // Samples at either end of the ADC range
+    pub const UNDER_RANGE: Self = Self(1 << 1); // Signal too small for the ADC range
+    pub const STALE: Self = Self(1 << 2); // Values left from an earlier frame (the last one was rejected)
+    pub const FREQUENCY_FALLBACK: Self = Self(1 << 3); // Frequency not measured, last known or nominal kept
+    pub const INSUFFICIENT_CYCLES: Self = Self(1 << 4); // Frame shorter than one cycle of the fundamental
+    pub const HARMONICS_FAILED: Self = Self(1 << 5); // Harmonic analysis failed, earlier values kept
+
+    const NAMES: [(Self, &'static str); 6] = [
+        (Self::SATURATION, "Saturation"),
+        (Self::UNDER_RANGE, "Under range"),
+        (Self::STALE, "Stale"),
+        (Self::FREQUENCY_FALLBACK, "Frequency fallback"),
+        (Self::INSUFFICIENT_CYCLES, "Insufficient cycles"),
+        (Self::HARMONICS_FAILED, "Harmonics failed"),
+    ];
+
+    pub fn empty() -> Self {
+        Self(0)
+    }
+
+    pub fn bits(&self) -> u32 {
+        self.0
+    }
+
+    /*
+     * @brief Flags from their bits, as carried in the protocol.
+     * @param bits Bitfield; unknown bits are dropped
+     */
+    pub fn from_bits(bits: u32) -> Self {
+        let known = Self::NAMES.iter().fold(0, |acc, (flag, _)| acc | flag.0);
+        Self(bits & known)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /*
+     * @brief Check whether every flag of another set is raised.
+     * @param other Flags to check
+     */
+    pub fn contains(&self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /*
+     * @brief Raise or clear some flags.
+     * @param other Flags to change
+     * @param value true to raise them, false to clear them
+     */
+    pub fn set(&mut self, other: Self, value: bool) {
+        if value {
+            self.0 |= other.0;
+        } else {
+            self.0 &= !other.0;
+        }
+    }
+
+    /*
+     * @brief Names of the raised flags.
+     */
+    pub fn names(&self) -> Vec<&'static str> {
+        Self::NAMES
+            .iter()
+            .filter(|(flag, _)| self.contains(*flag))
+            .map(|(_, name)| *name)
+            .collect()
+    }
+
+    /*
+     * @brief Flags of a frame that was rejected.
+     * @param error Reason the frame was rejected
+     * @return STALE, plus UNDER_RANGE or INSUFFICIENT_CYCLES when the reason points to one of them
+     */
+    pub fn from_rejection(error: &MetrologyError) -> Self {
+        match error {
+            MetrologyError::SignalTooLow { .. } => Self::STALE | Self::UNDER_RANGE,
+            MetrologyError::BufferTooShort { .. } => Self::STALE | Self::INSUFFICIENT_CYCLES,
+            _ => Self::STALE,
+        }
+    }
+}
+
+impl BitOr for QualityFlags {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for QualityFlags {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+/*
+* @brief Check the raw samples of a frame against the ADC range.
+* @param samples Raw samples (ADC codes)
+* @param adc_max_code Highest ADC code, or 0 to skip the check
+* @return SATURATION if a sample sits at 0 or at the highest code, UNDER_RANGE if the peak-to-peak amplitude is
+*         below UNDER_RANGE_FRACTION of the range
+* @note Only unipolar codes (0 to adc_max_code, biased at mid-scale) can be checked: signed samples would always
+*       reach 0. Leave adc_max_code at 0 for signed input.
+*/
+pub fn check_adc_range(samples: &[f64], adc_max_code: f64) -> QualityFlags {
+    let mut flags = QualityFlags::empty();
+    if adc_max_code <= 0.0 || samples.is_empty() {
+        return flags;
+    }
+
+    let (min, max) = samples
+        .iter()
+        .fold((f64::MAX, f64::MIN), |(min, max), &x| (min.min(x), max.max(x)));
+
+    flags.set(QualityFlags::SATURATION, min <= 0.0 || max >= adc_max_code);
+    flags.set(
+        QualityFlags::UNDER_RANGE,
+        max - min < adc_max_code * UNDER_RANGE_FRACTION,
+    );
+    flags
+}
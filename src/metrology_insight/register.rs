@@ -0,0 +1,82 @@
+pub const WS_PER_KWH: u64 = 3_600_000; // Vatios-segundo en un kWh
+pub const REGISTER_DIGITS: u32 = 6; // Dígitos enteros de kWh de los registros (rebosan en 999999 kWh)
+pub const MAX_REGISTER_DIGITS: u32 = 12; // 10^12 kWh en Ws todavía cabe en un u64
+
+/*
+* @brief Value at which an energy register rolls over to zero.
+* @param digits Whole kWh digits of the register, 0 for a register that never rolls over
+* @return Rollover in watt-seconds, or 0 for no rollover
+* @note The digits are limited to MAX_REGISTER_DIGITS so that the rollover fits in the register.
+*/
+pub fn register_rollover(digits: u32) -> u64 {
+    if digits == 0 {
+        0
+    } else {
+        10u64.pow(digits.min(MAX_REGISTER_DIGITS)) * WS_PER_KWH
+    }
+}
+
+/// Energy register in whole watt-seconds (var-seconds for reactive energy), with the fraction of the last
+/// increment carried to the next one.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct EnergyRegister {
+    ws: u64,        // Registered energy (Ws)
+    remainder: f64, // Fraction of a Ws not registered yet, in [0, 1)
+}
+
+impl EnergyRegister {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /*
+     * @brief Register holding a stored value, e.g. restored from non-volatile memory.
+     * @param ws Registered energy in watt-seconds
+     */
+    pub fn from_ws(ws: u64) -> Self {
+        Self { ws, remainder: 0.0 }
+    }
+
+    pub fn ws(&self) -> u64 {
+        self.ws
+    }
+
+    pub fn remainder(&self) -> f64 {
+        self.remainder
+    }
+
+    /*
+     * @brief Registered energy in kWh (kvarh).
+     * @note Derived from the whole watt-seconds; the remainder is not included.
+     */
+    pub fn kwh(&self) -> f64 {
+        self.ws as f64 / WS_PER_KWH as f64
+    }
+
+    /*
+     * @brief Add an energy increment to the register.
+     * @param energy_ws Increment in watt-seconds; negative or non-finite increments are ignored
+     * @param rollover_ws Value at which the register rolls over to zero (see register_rollover), 0 for none
+     * @return true if the register rolled over
+     * @note Only whole watt-seconds are registered: the fraction is carried, so that increments of any size
+     *       add up without losing precision however large the register grows.
+     */
+    pub fn add(&mut self, energy_ws: f64, rollover_ws: u64) -> bool {
+        if !(energy_ws.is_finite() && energy_ws > 0.0) {
+            return false;
+        }
+
+        let total = self.remainder + energy_ws;
+        let whole = total.floor();
+        self.remainder = total - whole;
+
+        let sum = self.ws.saturating_add(whole as u64);
+        if rollover_ws > 0 && sum >= rollover_ws {
+            self.ws = sum % rollover_ws;
+            true
+        } else {
+            self.ws = sum;
+            false
+        }
+    }
+}
@@ -0,0 +1,152 @@
+use crate::MetrologyError;
+use num_complex::Complex;
+
+/// Response of a sensor measured at one frequency.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ResponsePoint {
+    pub freq: f64,      // Frequency (Hz)
+    pub gain: f64,      // Output over input, in any reference (only the ratio to the fundamental is used)
+    pub phase_deg: f64, // Phase of the output (degrees, positive when it leads the input)
+}
+
+/// Gain and phase of a sensor against frequency, linear between the measured points.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FrequencyResponse {
+    points: Vec<ResponsePoint>, // Sorted by frequency, without repeated frequencies
+}
+
+/*
+* @brief Build a response from a constant table of a preset.
+* @param table Frequency, gain and phase of each point
+* @return Response of the preset
+*/
+fn preset(table: &[(f64, f64, f64)]) -> FrequencyResponse {
+    FrequencyResponse {
+        points: table
+            .iter()
+            .map(|&(freq, gain, phase_deg)| ResponsePoint { freq, gain, phase_deg })
+            .collect(),
+    }
+}
+
+impl FrequencyResponse {
+    /*
+     * @brief Build a response from its points.
+     * @param points Measured response, in any order
+     * @return Response sorted by frequency, or InvalidParameter if a point is not finite, a frequency is not
+     *         positive or is repeated, or a gain is not positive
+     */
+    pub fn new(mut points: Vec<ResponsePoint>) -> Result<Self, MetrologyError> {
+        let valid = |p: &ResponsePoint| {
+            p.freq.is_finite() && p.freq > 0.0 && p.gain.is_finite() && p.gain > 0.0 && p.phase_deg.is_finite()
+        };
+        if !points.iter().all(valid) {
+            return Err(MetrologyError::InvalidParameter("response point"));
+        }
+
+        points.sort_by(|a, b| a.freq.total_cmp(&b.freq));
+        if points.windows(2).any(|w| w[0].freq == w[1].freq) {
+            return Err(MetrologyError::InvalidParameter("response point"));
+        }
+
+        Ok(Self { points })
+    }
+
+    /*
+     * @brief Typical response of a YHDC SCT013 split-core current transformer with its burden resistor.
+     * @note Approximate values; a table measured on the installed sensor gives better accuracy.
+     */
+    pub fn sct013() -> Self {
+        preset(&[
+            (50.0, 1.000, 1.0),
+            (100.0, 1.000, 0.6),
+            (250.0, 0.999, 0.3),
+            (500.0, 0.997, 0.0),
+            (1000.0, 0.990, -0.6),
+            (2000.0, 0.970, -1.5),
+            (3500.0, 0.930, -3.0),
+        ])
+    }
+
+    /*
+     * @brief Typical response of a ZMPT101B voltage transformer module.
+     * @note Approximate values; a table measured on the installed sensor gives better accuracy.
+     */
+    pub fn zmpt101b() -> Self {
+        preset(&[
+            (50.0, 1.000, 0.3),
+            (150.0, 0.995, -0.5),
+            (250.0, 0.985, -1.2),
+            (500.0, 0.960, -2.8),
+            (1000.0, 0.900, -6.0),
+            (2000.0, 0.780, -12.0),
+            (3500.0, 0.620, -20.0),
+        ])
+    }
+
+    /*
+     * @brief Points of the response, sorted by frequency.
+     */
+    pub fn points(&self) -> &[ResponsePoint] {
+        &self.points
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    /*
+     * @brief Response of the sensor at a frequency.
+     * @param freq Frequency in Hz
+     * @return Complex gain; linear between points, that of the first or last point outside the table, and 1
+     *         for an empty table
+     */
+    pub fn at(&self, freq: f64) -> Complex<f64> {
+        let (Some(first), Some(last)) = (self.points.first(), self.points.last()) else {
+            return Complex::new(1.0, 0.0);
+        };
+
+        let (gain, phase_deg) = match self.points.iter().position(|p| p.freq >= freq) {
+            Some(0) => (first.gain, first.phase_deg),
+            Some(i) => {
+                let (low, high) = (&self.points[i - 1], &self.points[i]);
+                let t = (freq - low.freq) / (high.freq - low.freq);
+                (
+                    low.gain + t * (high.gain - low.gain),
+                    low.phase_deg + t * (high.phase_deg - low.phase_deg),
+                )
+            }
+            None => (last.gain, last.phase_deg),
+        };
+
+        Complex::from_polar(gain, phase_deg.to_radians())
+    }
+
+    /*
+     * @brief Response at a frequency relative to the one at the fundamental.
+     * @param freq Frequency in Hz
+     * @param fundamental Fundamental frequency in Hz
+     * @return Complex gain, 1 at the fundamental
+     * @note The fundamental is already corrected by the calibration of the channel, so only the difference
+     *       of the other frequencies to it is corrected.
+     */
+    pub fn relative(&self, freq: f64, fundamental: f64) -> Complex<f64> {
+        self.at(freq) / self.at(fundamental)
+    }
+
+    /*
+     * @brief Remove the response from the harmonic phasors of a signal.
+     * @param phasors Phasors indexed by harmonic order (see compute_harmonic_phasors), modified in place
+     * @param fundamental Fundamental frequency in Hz
+     * @note The DC component and the fundamental are left unchanged.
+     */
+    pub fn correct_phasors(&self, phasors: &mut [Complex<f64>], fundamental: f64) {
+        if self.points.is_empty() {
+            return;
+        }
+
+        for (order, phasor) in phasors.iter_mut().enumerate().skip(2) {
+            *phasor /= self.relative(order as f64 * fundamental, fundamental);
+        }
+    }
+}
@@ -0,0 +1,345 @@
+use crate::{ChannelConfig, FrequencyResponse, MetrologyError, MetrologyInsightSignalType, SensorType};
+
+/// Analog front end between a sensor output and the ADC codes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AdcFrontEnd {
+    pub resolution_bits: u32, // Resolution of the ADC
+    pub vref: f64,            // Reference voltage of the ADC (V)
+    pub divider: f64,         // Voltage at the ADC input over voltage at the pin (1 = no divider)
+    pub bias: f64,            // Voltage at the pin for a null sensor output (V)
+}
+
+impl Default for AdcFrontEnd {
+    fn default() -> Self {
+        Self::milk_v_duo()
+    }
+}
+
+impl AdcFrontEnd {
+    /*
+     * @brief Front end of the Milk-V Duo SAR ADC: 12 bits, 1.8 V reference, internal 1/2 divider, sensors biased
+     *        at half of the 3.3 V supply.
+     */
+    pub fn milk_v_duo() -> Self {
+        Self {
+            resolution_bits: 12,
+            vref: 1.8,
+            divider: 0.5,
+            bias: 1.65,
+        }
+    }
+
+    /*
+     * @brief Check that the front end can convert codes to volts.
+     * @return Ok, or InvalidParameter if a value is out of its range
+     */
+    pub fn validate(&self) -> Result<(), MetrologyError> {
+        if self.resolution_bits == 0 || self.resolution_bits > 32 {
+            return Err(MetrologyError::InvalidParameter("resolution_bits"));
+        }
+        if !(self.vref.is_finite() && self.vref > 0.0) {
+            return Err(MetrologyError::InvalidParameter("vref"));
+        }
+        if !(self.divider.is_finite() && self.divider > 0.0) {
+            return Err(MetrologyError::InvalidParameter("divider"));
+        }
+        if !(self.bias.is_finite() && self.bias >= 0.0 && self.bias <= self.pin_full_scale()) {
+            return Err(MetrologyError::InvalidParameter("bias"));
+        }
+        Ok(())
+    }
+
+    /*
+     * @brief Highest ADC code.
+     */
+    pub fn max_code(&self) -> f64 {
+        ((1u64 << self.resolution_bits) - 1) as f64
+    }
+
+    /*
+     * @brief Voltage at the pin that gives the highest ADC code (V).
+     */
+    pub fn pin_full_scale(&self) -> f64 {
+        self.vref / self.divider
+    }
+
+    /*
+     * @brief Voltage at the pin of one ADC code (V).
+     */
+    pub fn volts_per_count(&self) -> f64 {
+        self.pin_full_scale() / self.max_code()
+    }
+
+    /*
+     * @brief ADC code of a null sensor output.
+     */
+    pub fn zero_code(&self) -> f64 {
+        self.bias / self.volts_per_count()
+    }
+
+    /*
+     * @brief Largest swing of the sensor output around the bias before the ADC clips (V peak).
+     */
+    pub fn headroom(&self) -> f64 {
+        self.bias.min(self.pin_full_scale() - self.bias)
+    }
+}
+
+/// Rated primary and secondary values of the instrument transformer ahead of the sensor (e.g. 400/5 A, 20000/110 V).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TransformerRatio {
+    pub primary: f64,   // Rated primary value (V or A)
+    pub secondary: f64, // Rated secondary value (V or A)
+}
+
+impl Default for TransformerRatio {
+    fn default() -> Self {
+        Self {
+            primary: 1.0,
+            secondary: 1.0,
+        }
+    }
+}
+
+impl TransformerRatio {
+    /*
+     * @brief Instrument transformer from its rated values.
+     * @param primary Rated primary value (V or A)
+     * @param secondary Rated secondary value (V or A)
+     * @return Ratio, or InvalidParameter if a value is not finite and positive
+     */
+    pub fn new(primary: f64, secondary: f64) -> Result<Self, MetrologyError> {
+        let transformer = Self { primary, secondary };
+        transformer.validate()?;
+        Ok(transformer)
+    }
+
+    /*
+     * @brief Check that the rated values give a usable ratio.
+     * @return Ok, or InvalidParameter if a value is not finite and positive
+     */
+    pub fn validate(&self) -> Result<(), MetrologyError> {
+        if [self.primary, self.secondary].iter().all(|v| v.is_finite() && *v > 0.0) {
+            Ok(())
+        } else {
+            Err(MetrologyError::InvalidParameter("transformer"))
+        }
+    }
+
+    /*
+     * @brief Primary value per unit of secondary value.
+     * @return Ratio; only meaningful once the values have been validated
+     */
+    pub fn ratio(&self) -> f64 {
+        self.primary / self.secondary
+    }
+
+    /*
+     * @brief Refer a primary-side value to the secondary.
+     * @param value Value on the primary side
+     * @return Value at the secondary of the transformer
+     */
+    pub fn to_secondary(&self, value: f64) -> f64 {
+        value / self.ratio()
+    }
+}
+
+/// Sensor connected to a channel, with the values needed to convert its output.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SensorModel {
+    Sct013 {
+        turns: f64,       // Turns of the split-core CT
+        burden_ohms: f64, // Burden resistor, internal or external
+        rated_a: f64,     // Rated primary current (A RMS)
+    },
+    Zmpt101b {
+        ratio: f64,   // Primary volts per volt of module output
+        rated_v: f64, // Rated primary voltage (V RMS)
+    },
+    Shunt {
+        resistance_ohms: f64, // Shunt resistor
+        gain: f64,            // Gain of the amplifier after the shunt
+        rated_a: f64,         // Rated current (A RMS)
+    },
+    Rogowski {
+        sensitivity: f64, // Coil output at the nominal frequency (V per A), after the integrator
+        rated_a: f64,     // Rated current (A RMS)
+    },
+}
+
+impl SensorModel {
+    /*
+     * @brief SCT013-000 (100 A, 2000 turns, current output) with an external burden resistor.
+     * @param burden_ohms Burden resistor
+     */
+    pub fn sct013_000(burden_ohms: f64) -> Self {
+        SensorModel::Sct013 {
+            turns: 2000.0,
+            burden_ohms,
+            rated_a: 100.0,
+        }
+    }
+
+    /*
+     * @brief SCT013-030 (30 A, 1800 turns, internal 62 Ω burden: about 1 V at the rated current).
+     */
+    pub fn sct013_030() -> Self {
+        SensorModel::Sct013 {
+            turns: 1800.0,
+            burden_ohms: 62.0,
+            rated_a: 30.0,
+        }
+    }
+
+    /*
+     * @brief ZMPT101B module as adjusted on the Milk-V Duo board (250 V range).
+     */
+    pub fn zmpt101b() -> Self {
+        SensorModel::Zmpt101b {
+            ratio: 1170.0,
+            rated_v: 250.0,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SensorModel::Sct013 { .. } => "SCT013",
+            SensorModel::Zmpt101b { .. } => "ZMPT101B",
+            SensorModel::Shunt { .. } => "Shunt",
+            SensorModel::Rogowski { .. } => "Rogowski",
+        }
+    }
+
+    /*
+     * @brief Type of signal the sensor measures.
+     */
+    pub fn signal_type(&self) -> MetrologyInsightSignalType {
+        match self {
+            SensorModel::Zmpt101b { .. } => MetrologyInsightSignalType::Voltage,
+            _ => MetrologyInsightSignalType::Current,
+        }
+    }
+
+    /*
+     * @brief Type of sensor, as used by the processing of the channel.
+     */
+    pub fn sensor_type(&self) -> SensorType {
+        match self {
+            SensorModel::Sct013 { .. } => SensorType::CurrentTransformer,
+            SensorModel::Zmpt101b { .. } => SensorType::VoltageTransformer,
+            SensorModel::Shunt { .. } => SensorType::Shunt,
+            SensorModel::Rogowski { .. } => SensorType::Rogowski,
+        }
+    }
+
+    /*
+     * @brief Check that the values of the sensor are usable.
+     * @return Ok, or InvalidParameter if a value is not finite and positive
+     */
+    pub fn validate(&self) -> Result<(), MetrologyError> {
+        let values: &[f64] = match self {
+            SensorModel::Sct013 {
+                turns,
+                burden_ohms,
+                rated_a,
+            } => &[*turns, *burden_ohms, *rated_a],
+            SensorModel::Zmpt101b { ratio, rated_v } => &[*ratio, *rated_v],
+            SensorModel::Shunt {
+                resistance_ohms,
+                gain,
+                rated_a,
+            } => &[*resistance_ohms, *gain, *rated_a],
+            SensorModel::Rogowski { sensitivity, rated_a } => &[*sensitivity, *rated_a],
+        };
+
+        if values.iter().all(|v| v.is_finite() && *v > 0.0) {
+            Ok(())
+        } else {
+            Err(MetrologyError::InvalidParameter("sensor model"))
+        }
+    }
+
+    /*
+     * @brief Primary units (V or A) per volt of sensor output.
+     * @note For a Rogowski coil it holds at the nominal frequency, once the output is integrated.
+     */
+    pub fn units_per_volt(&self) -> f64 {
+        match *self {
+            SensorModel::Sct013 { turns, burden_ohms, .. } => turns / burden_ohms,
+            SensorModel::Zmpt101b { ratio, .. } => ratio,
+            SensorModel::Shunt {
+                resistance_ohms, gain, ..
+            } => 1.0 / (resistance_ohms * gain),
+            SensorModel::Rogowski { sensitivity, .. } => 1.0 / sensitivity,
+        }
+    }
+
+    /*
+     * @brief Nominal range of the sensor.
+     * @return Rated RMS value of the primary (V or A)
+     */
+    pub fn nominal_range(&self) -> f64 {
+        match *self {
+            SensorModel::Sct013 { rated_a, .. } => rated_a,
+            SensorModel::Zmpt101b { rated_v, .. } => rated_v,
+            SensorModel::Shunt { rated_a, .. } => rated_a,
+            SensorModel::Rogowski { rated_a, .. } => rated_a,
+        }
+    }
+
+    /*
+     * @brief Primary value at which the ADC clips.
+     * @param frontend Front end the sensor is connected to
+     * @return Peak value of the primary (V or A) that drives the ADC to either end of its range
+     */
+    pub fn clipping_peak(&self, frontend: &AdcFrontEnd) -> f64 {
+        frontend.headroom() * self.units_per_volt()
+    }
+
+    /*
+     * @brief Conversion factors of a channel that reads this sensor through a front end.
+     * @param frontend Front end the sensor is connected to
+     * @return (adc_factor, adc_scale): volts per code and units per volt for a current sensor, and units per
+     *         code with a scale of 1 for a voltage sensor, as expected by raw_to_physical
+     */
+    pub fn adc_factors(&self, frontend: &AdcFrontEnd) -> (f64, f64) {
+        match self.signal_type() {
+            MetrologyInsightSignalType::Voltage => (frontend.volts_per_count() * self.units_per_volt(), 1.0),
+            MetrologyInsightSignalType::Current => (frontend.volts_per_count(), self.units_per_volt()),
+        }
+    }
+
+    /*
+     * @brief Typical frequency response of the sensor.
+     * @return Preset response of the model, or a flat one if there is none
+     */
+    pub fn frequency_response(&self) -> FrequencyResponse {
+        match self {
+            SensorModel::Sct013 { .. } => FrequencyResponse::sct013(),
+            SensorModel::Zmpt101b { .. } => FrequencyResponse::zmpt101b(),
+            _ => FrequencyResponse::default(),
+        }
+    }
+
+    /*
+     * @brief Set up a channel to read this sensor through a front end.
+     * @param frontend Front end the sensor is connected to
+     * @param channel Options of the channel that carries the signal the sensor measures
+     * @return Ok, or InvalidParameter if the front end or the sensor are not valid (the channel is unchanged)
+     * @note Sets adc_factor, adc_scale, sensor_type, frequency_response and the calibrated offset (the code of
+     *       the bias). A calibration applied afterwards replaces the offset.
+     */
+    pub fn configure(&self, frontend: &AdcFrontEnd, channel: &mut ChannelConfig) -> Result<(), MetrologyError> {
+        frontend.validate()?;
+        self.validate()?;
+
+        let (adc_factor, adc_scale) = self.adc_factors(frontend);
+        channel.adc_factor = adc_factor;
+        channel.adc_scale = adc_scale;
+        channel.sensor_type = self.sensor_type();
+        channel.frequency_response = self.frequency_response();
+        channel.calibration.offset = frontend.zero_code();
+
+        Ok(())
+    }
+}
@@ -1,150 +1,57 @@
 use crate::{
-    calculate_rms, compute_harmonics_and_thd, resample_signal, MetrologyInsightSignal, MetrologyInsightSignalType,
-    MetrologyInsightSocket, ADC_SAMPLES_50HZ_CYCLE, ADC_SAMPLES_60HZ_CYCLE, FFT_RESOLUTION, FREQ_NOMINAL_50,
-    FREQ_NOMINAL_60,
+    build_frequency_estimator, calculate_rms, check_adc_range, compute_harmonics_and_thd, filter_compensation,
+    thd_percent_to_db, AdcSample, ChannelConfig, ChannelState, FrequencyEstimate, HarmonicComponent, MetrologyError,
+    MetrologyInsightConfig, MetrologyInsightSignalType, MetrologyInsightSocket, QualityFlags, SampleFrame, SensorType,
 };
 
-pub const ZERO_CROSSING_MAX_POINTS: usize = 3; // Maximum number of zero crossing points to store // Para 1 ciclo, 2 cruces por cero (ascendente + descendente)
-pub const FREQ_ZC_DEBOUNCE: u32 = 2;
-
 pub const EXTRA_SAMPLES: usize = 0; /* Extra samples to a cycle to get zero crossing */
-
-#[allow(dead_code)]
-/*
-* @brief Calculate the moving average of a signal in-place
-* @param signal Mutable slice of the signal to calculate the moving average
-* @param window_size Size of the moving average window
-* @note The input signal slice will be modified with the moving average values
-*/
-fn moving_average(signal: &mut [i32], window_size: usize) {
-    let len = signal.len();
-    let original = signal.to_vec(); // Clonamos para no modificar mientras calculamos
-
-    for i in 0..len {
-        let start = if i >= window_size { i - window_size } else { 0 };
-        let end = i + 1;
-        let sum: i32 = original[start..end].iter().copied().sum();
-        signal[i] = sum / (end - start) as i32; // Promedio entero
-    }
-}
-
-/*
-* @brief Is the frequency within the tolerance range?
-* @param freq Frequency to check
-* @param nominal Nominal frequency
-* @return true if the frequency is within the tolerance range, false otherwise
-* @note This function checks if the frequency is within the tolerance range of 5% of the nominal frequency.
-*/
-fn is_frequency_in_tolerance(freq: f64, nominal: f64) -> bool {
-    freq < (1.07 * nominal) && freq > (0.95 * nominal)
-}
+pub const SAMPLE_RATE_TOLERANCE: f64 = 1e-6; // Desviación relativa admitida entre la trama y la configuración
 
 /*
 * @brief Calculate the nominal frequency of a signal.
 * @param freq_zc Frequency of the signal
-* @param length Pointer to the length of the signal
-* @param nominal_freq Nominal frequency
+* @param config Pointer to the MetrologyInsightConfig structure.
 * @return Nominal frequency
-* @note This function calculates the nominal frequency of a signal.
-
+* @note The candidate whose tolerance band contains the frequency is selected; if none does, the default
+*       (first) nominal frequency of the configuration is used.
 */
-fn calculate_nominal_frequency(freq_zc: f64, length: &mut usize, nominal_freq: f64) -> f64 {
-    let mut freq_nominal = FREQ_NOMINAL_50;
-
-    *length = ADC_SAMPLES_50HZ_CYCLE as usize;
-
-    if is_frequency_in_tolerance(freq_zc, FREQ_NOMINAL_60) {
-        freq_nominal = FREQ_NOMINAL_60;
-
-        if nominal_freq != FREQ_NOMINAL_60 {
-            *length = ADC_SAMPLES_60HZ_CYCLE;
-        }
-    }
-
-    freq_nominal
+fn calculate_nominal_frequency(freq_zc: f64, config: &MetrologyInsightConfig) -> f64 {
+    config
+        .select_nominal_frequency(freq_zc)
+        .unwrap_or_else(|| config.default_nominal_frequency())
 }
 
 /*
-* @brief Calculate the zero crossing frequency of a signal.
-* @param signal Pointer to the signal buffer
-* @param adc_samples_second Number of ADC samples per second
-* @return Frequency in Hz
-* @note This function calculates the zero crossing frequency of a signal.
+* @brief Estimate the frequency of a frame with the estimator selected for its channel.
+* @param state Processing state of the channel
+* @param config Pointer to the MetrologyInsightConfig structure.
+* @param signal_type Type of the signal (voltage or current)
+* @param real_wave Signal buffer in physical units
+* @param nominal Nominal frequency selected for the channel
+* @return Estimate, None if the estimator cannot give one for this frame yet, or the reason the frame is rejected
+* @note The estimator is rebuilt when the configuration selects a different method.
 */
-fn calculate_zero_crossing_frequency(signal: &[f64], adc_samples_second: f64) -> f64 {
-    let num_samples = signal.len();
-    let mut num_crossing: usize = 0;
-    let mut debounce: u32 = 0;
-    let mut frequency: f64 = -1.0;
-    let mut interpolation_points: Vec<f64> = vec![0.0; ZERO_CROSSING_MAX_POINTS];
-
-    for p in 0..(num_samples - 1) {
-        let y1: f64 = signal[p];
-        let y2: f64 = signal[p + 1];
-
-        // Cruce por cero sin necesidad de tolerancia (enteros, directamente)
-        //if debounce == 0 && (y1 > 0 && y2 <= 0 || y1 < 0 && y2 >= 0) {
-        if debounce == 0 && signal[p] < 0.0 && signal[p + 1] >= 0.0 {
-            // Interpolación
-            let x1 = p;
-            let x2 = p + 1;
-
-            let y1f = y1;
-            let y2f = y2;
-
-            // Interpolación lineal para cruce por cero
-            if (y2f - y1f).abs() > f64::EPSILON {
-                let xp = x1 as f64 + (0.0 - y1f) * (x2 - x1) as f64 / (y2f - y1f);
-
-                if num_crossing < ZERO_CROSSING_MAX_POINTS {
-                    interpolation_points[num_crossing] = xp;
-                    num_crossing += 1;
-                }
-                debounce = FREQ_ZC_DEBOUNCE;
-            }
-        }
-
-        if debounce > 0 {
-            debounce -= 1;
-        }
+fn estimate_frequency(
+    state: &mut ChannelState,
+    config: &MetrologyInsightConfig,
+    signal_type: MetrologyInsightSignalType,
+    real_wave: &[f64],
+    nominal: f64,
+) -> Result<Option<FrequencyEstimate>, MetrologyError> {
+    let kind = config.channel(signal_type).frequency_estimator;
+    if state.frequency_estimator.kind() != kind {
+        state.frequency_estimator = build_frequency_estimator(kind);
     }
 
-    // Calcular frecuencia
-    if num_crossing > 1 {
-        let mut freq_sum = 0.0;
-        let mut freq_count = 0;
-
-        for p in 0..(num_crossing - 1) {
-            let delta = interpolation_points[p + 1] - interpolation_points[p];
-            if delta > 0.0 {
-                freq_sum += 1.0 / (delta / adc_samples_second);
-                freq_count += 1;
-            }
-        }
-
-        if freq_count > 0 {
-            //frequency = (freq_sum / freq_count as f64) / 2.0; // Corregimos doble cruce por ciclo
-            frequency = freq_sum / freq_count as f64; // No dividimos por 2
-        }
+    match state
+        .frequency_estimator
+        .estimate(real_wave, config.adc_samples_seconds, nominal)
+    {
+        Ok(estimate) if config.select_nominal_frequency(estimate.frequency).is_some() => Ok(Some(estimate)),
+        Ok(estimate) => Err(MetrologyError::FrequencyOutOfRange(estimate.frequency)),
+        Err(MetrologyError::NoZeroCrossing) | Err(MetrologyError::EstimatorSettling) => Ok(None),
+        Err(err) => Err(err),
     }
-
-    frequency
-}
-
-/*
-* @brief Limit the length of a signal to a multiple of the cycle length.
-* @param length Length of the signal
-* @param frequency Frequency of the signal
-* @param adc_samples_second Number of ADC samples per second
-* @return Length of the signal limited to a multiple of the cycle length
-* @note This function limits the length of a signal to a multiple of the cycle length.
-*/
-fn limit_length_to_cycles(length: usize, frequency: f64, adc_samples_second: f64) -> usize {
-    let one_cycle: usize = (adc_samples_second / frequency).round() as usize;
-
-    let length_cycles = (length / one_cycle) * one_cycle;
-
-    length_cycles.min(length)
 }
 
 /*
@@ -163,205 +70,360 @@ pub fn update_average(in_value: f64, out_value: &mut f64, avg: f64) {
     }
 }
 
+/*
+* @brief Average the harmonic components of a signal.
+* @param in_harmonics Harmonic components of the last cycle
+* @param out_harmonics Averaged harmonic components
+* @param avg Average value
+* @note RMS and percentage are averaged; the phase is the one of the last cycle, since angles cannot be
+*       averaged linearly. The output is reset when the number of orders changes.
+*/
+pub fn update_harmonics_average(
+    in_harmonics: &[HarmonicComponent],
+    out_harmonics: &mut Vec<HarmonicComponent>,
+    avg: f64,
+) {
+    if out_harmonics.len() != in_harmonics.len() {
+        *out_harmonics = in_harmonics.to_vec();
+        return;
+    }
+
+    for (input, output) in in_harmonics.iter().zip(out_harmonics.iter_mut()) {
+        output.order = input.order;
+        update_average(input.rms, &mut output.rms, avg);
+        update_average(input.percent, &mut output.percent, avg);
+        output.phase = input.phase;
+    }
+}
+
 /*
 * @brief Remove the offset from a signal.
 * @param signal Pointer to the signal buffer
+* @return Ok, or BufferTooShort if the buffer is empty
 * @note This function removes the offset from a signal.
 * @note The offset is calculated as the average of the maximum and minimum values of the signal.
 */
-pub fn remove_signal_offset(signal: &mut [i32]) {
-    let max = *signal.iter().max().unwrap();
-    let min = *signal.iter().min().unwrap();
-    let offset = (max + min) / 2;
+pub fn remove_signal_offset(signal: &mut [f64]) -> Result<(), MetrologyError> {
+    if signal.is_empty() {
+        return Err(MetrologyError::BufferTooShort { required: 1, actual: 0 });
+    }
+
+    let max = signal.iter().copied().fold(f64::MIN, f64::max);
+    let min = signal.iter().copied().fold(f64::MAX, f64::min);
+    let offset = (max + min) / 2.0;
 
     for s in signal.iter_mut() {
         *s -= offset;
     }
+
+    Ok(())
 }
 
 /*
 * @brief Check if the signal is valid.
 * @param signal Pointer to the signal buffer
 * @param signal_type Type of the signal (voltage or current)
-* @return true if the signal is valid, false otherwise
+* @return Ok if the signal is valid, or the reason it is not
 * @note This function checks if the signal is valid.
 */
-fn is_signal_valid(signal: &[i32], signal_type: MetrologyInsightSignalType) -> bool {
+fn check_signal(signal: &[f64], signal_type: MetrologyInsightSignalType) -> Result<(), MetrologyError> {
     if signal.len() < 2 {
-        return false;
+        return Err(MetrologyError::BufferTooShort {
+            required: 2,
+            actual: signal.len(),
+        });
     }
 
-    let min_amplitude = signal_type.min_amplitude() as i32;
+    let min_amplitude = signal_type.min_amplitude();
 
     let (min_val, max_val) = signal
         .iter()
-        .fold((i32::MAX, i32::MIN), |(min, max), &x| (min.min(x), max.max(x)));
+        .fold((f64::MAX, f64::MIN), |(min, max), &x| (min.min(x), max.max(x)));
 
     let amplitude = max_val - min_val;
 
-    amplitude >= min_amplitude
+    if amplitude >= min_amplitude {
+        Ok(())
+    } else {
+        Err(MetrologyError::SignalTooLow {
+            signal_type,
+            amplitude,
+            minimum: min_amplitude,
+        })
+    }
+}
+
+/*
+* @brief Convierte un valor ADC crudo a unidades físicas (voltios o amperios)
+* @param value Valor ADC
+* @param channel Parámetros de conversión y calibración del canal
+* @param signal_type Tipo de señal (tensión o corriente)
+* @param level Valor RMS del primario en el que se evalúa la curva de corrección (V o A)
+* @param temperature Temperatura para la compensación de la deriva (°C), o None para no compensarla
+* @return Valor en unidades físicas
+* @note Se resta el offset calibrado, se aplica el factor (y la escala de corriente) y se corrige la ganancia,
+*       incluidos el error de relación del sensor en ese nivel y la deriva con la temperatura. Por último se
+*       multiplica por la relación del transformador de medida, de modo que el valor queda en el primario.
+* @note En un canal Rogowski el valor ya viene integrado, y el paso alto del integrador ha quitado la
+*       continua junto con el offset: no se resta de nuevo.
+*/
+pub fn raw_to_physical(
+    value: f64,
+    channel: &ChannelConfig,
+    signal_type: MetrologyInsightSignalType,
+    level: f64,
+    temperature: Option<f64>,
+) -> f64 {
+    let calibration = &channel.calibration;
+    let offset = if channel.sensor_type == SensorType::Rogowski {
+        0.0
+    } else {
+        calibration.offset
+    };
+    let volts = (value - offset) * channel.adc_factor;
+    let ratio = channel.transformer.ratio();
+
+    if signal_type == MetrologyInsightSignalType::Current {
+        volts * channel.adc_scale * calibration.gain_at(level, temperature) * ratio
+    } else {
+        volts * calibration.gain_at(level, temperature) * ratio
+    }
 }
 
 /*
 * @brief Convierte valores ADC crudos a unidades físicas (voltios o amperios)
-* @param signal Estructura con datos y parámetros de calibración
+* @param wave Muestras ADC
+* @param channel Parámetros de conversión y calibración del canal
+* @param signal_type Tipo de señal (tensión o corriente)
+* @param level Valor RMS del primario en el que se evalúa la curva de corrección (V o A)
+* @param temperature Temperatura para la compensación de la deriva (°C), o None para no compensarla
 * @return Vector de valores en unidades físicas
 *
 * @note Secuencia de procesamiento:
-* 1. Convertir raw ADC a voltaje (incluyendo offset)
-* 2. Remover offset DC solo para señales de corriente
-* 3. Aplicar factor de escala para obtener unidad final
+* 1. Restar el offset calibrado (código ADC con entrada nula), salvo en un canal Rogowski ya integrado
+* 2. Convertir raw ADC a voltaje en la entrada del ADC
+* 3. Aplicar factor de escala para obtener la corriente
+* 4. Corregir la ganancia calibrada, el error de relación del sensor en el nivel dado y la deriva térmica
+* 5. Referir el valor al primario del transformador de medida
 */
-pub fn convert_raw_to_physical(signal: &MetrologyInsightSignal) -> Vec<f64> {
-    // Convertimos las muestras ADC a voltajes reales
-    let voltages: Vec<f64> = signal
-        .wave
-        .iter()
-        .map(|&valor| (valor as f64) * signal.adc_factor)
-        .collect();
+pub fn convert_raw_to_physical(
+    wave: &[f64],
+    channel: &ChannelConfig,
+    signal_type: MetrologyInsightSignalType,
+    level: f64,
+    temperature: Option<f64>,
+) -> Vec<f64> {
+    wave.iter()
+        .map(|&valor| raw_to_physical(valor, channel, signal_type, level, temperature))
+        .collect()
+}
 
-    // Aplicamos el cálculo de corriente y corregimos el offset
-    if signal.is_current() {
-        voltages.iter().map(|&v| v * signal.adc_scale).collect()
-    } else {
-        voltages
-    }
+/*
+* @brief Process a frame of a signal.
+* @param socket Pointer to the MetrologyInsightSocket structure.
+* @param frame Raw samples of the channel.
+* @param config Pointer to the MetrologyInsightConfig structure.
+* @param state Processing state of the frame's channel.
+* @return true if the frame was valid and the socket was updated, false otherwise
+* @note This function processes a frame. Use try_process_signal to know why a frame was rejected.
+*/
+pub fn process_signal<T: AdcSample>(
+    socket: &mut MetrologyInsightSocket,
+    frame: &SampleFrame<T>,
+    config: &MetrologyInsightConfig,
+    state: &mut ChannelState,
+) -> bool {
+    try_process_signal(socket, frame, config, state).is_ok()
 }
 
-pub fn signal_integrate(s: &[f64], frequency_zc: f64, adc_samples_second: f64) -> Vec<f64> {
-    let mut integral: f64 = 0.0;
-    let mut res_signal: Vec<f64> = Vec::with_capacity(s.len());
+/*
+* @brief Process a frame of a signal, reporting why it was rejected.
+* @param socket Pointer to the MetrologyInsightSocket structure.
+* @param frame Raw samples of the channel.
+* @param config Pointer to the MetrologyInsightConfig structure.
+* @param state Processing state of the frame's channel.
+* @return Ok if the socket was updated, or the reason the frame was rejected (the socket is left unchanged)
+* @note The conversion to physical units and the frequency source come from the channel configuration. The
+*       results are written only to the socket signal of the channel.
+* @note The output of a Rogowski coil is integrated before any other calculation, with unity gain at the
+*       nominal frequency of the channel.
+* @note The filter chain of the channel runs before any other calculation but the Rogowski integration. Its
+*       delay and the sampling skew of the channel are matched with the other channel through a fractional
+*       delay, and the timestamp of real_wave is corrected by both.
+* @note The correction curve of the channel is evaluated at the last RMS value of the channel: its ratio error
+*       in the conversion to physical units and its phase error in the delay compensation. The temperature
+*       drift is compensated in the same places with the temperature of the socket.
+* @note Every result is on the primary side of the instrument transformer of the channel; the peak and RMS
+*       values at its secondary are kept for commissioning checks. Frames of a channel whose transformer ratio
+*       is not valid are rejected.
+* @note The frequency response of the sensor is removed from the harmonics, relative to the fundamental.
+* @note The DC level is tracked across frames by the channel's OffsetTracker and removed from real_wave; its
+*       distance to the calibrated zero of the channel is reported in dc_offset.
+* @note When the estimator cannot give a frequency for the frame (no zero crossings, still settling) the last
+*       known frequency is kept with quality 0; a measured frequency outside the tolerance band of every
+*       configured nominal frequency is rejected.
+* @note The cycle length is derived from the selected nominal frequency and the configured sample rate, which
+*       must match the sample rate of the frame.
+* @note The quality flags of the signal describe this frame: ADC saturation or under range, frequency kept
+*       from an earlier frame (or copied from the voltage channel with its flag), a frame shorter than a cycle
+*       and a failed harmonic analysis. Flags of rejected frames are raised by the caller.
+* @note peak keeps the highest sample of every frame processed, while rms follows the averaging window.
+*/
+pub fn try_process_signal<T: AdcSample>(
+    socket: &mut MetrologyInsightSocket,
+    frame: &SampleFrame<T>,
+    config: &MetrologyInsightConfig,
+    state: &mut ChannelState,
+) -> Result<(), MetrologyError> {
+    let adc_samples_second = config.adc_samples_seconds;
+    let avg_sec = config.avg_sec;
+    let channel = config.channel(frame.channel);
+
+    if (frame.sample_rate - adc_samples_second).abs() > adc_samples_second * SAMPLE_RATE_TOLERANCE {
+        return Err(MetrologyError::InvalidParameter("sample_rate"));
+    }
+
+    // La relación del transformador se usa en toda la conversión: una relación inválida rechaza la trama
+    channel.transformer.validate()?;
+
+    let mut wave: Vec<f64> = frame.samples.iter().map(|s| s.to_f64()).collect();
 
-    // RMS original (sin integrar)
-    let orms = calculate_rms(&s, s.len(), frequency_zc, adc_samples_second);
+    // Saturación y rango se comprueban sobre los códigos del ADC, antes de cualquier proceso
+    let mut quality = check_adc_range(&wave, config.adc_max_code);
 
-    // Integración trapezoidal acumulativa
-    for i in 0..s.len() {
-        let y_x = s[i];
-        let y_x1 = if i + 1 < s.len() { s[i + 1] } else { y_x };
-        integral += (y_x + y_x1) / 2.0;
-        res_signal.push(integral);
+    // La salida de una bobina Rogowski es la derivada de la corriente: se integra antes que nada
+    if channel.sensor_type == SensorType::Rogowski {
+        let nominal = socket.signal(frame.channel).freq_nominal;
+        wave = state.integrator.integrate(&wave, adc_samples_second, nominal)?;
     }
 
-    // Calcular RMS de la señal integrada
-    let integral_rms = calculate_rms(&res_signal, s.len(), frequency_zc, adc_samples_second);
+    // Cadena de filtros del canal, con el retardo y el desfase de muestreo igualados al otro canal
+    let freq_filters = socket.signal(frame.channel).freq_nominal;
+    let compensation = filter_compensation(config, socket, frame.channel)?;
+    state.filters.configure(&channel.filters, compensation)?;
+    state.filters.process(&mut wave);
+    let filter_delay_us = state.filters.delay(freq_filters, adc_samples_second) * 1e6 / adc_samples_second;
 
-    // Normalizar a 0 dB
-    let int_k = if orms != 0.0 { integral_rms / orms } else { 1.0 };
+    check_signal(&wave, frame.channel)?;
 
-    for i in 0..res_signal.len() {
-        res_signal[i] = res_signal[i] / int_k;
-    }
+    // Conversión a unidades físicas con la calibración del canal, con la curva evaluada en el último RMS
+    let level = socket.signal(frame.channel).rms;
+    let mut real_wave: Vec<f64> = convert_raw_to_physical(&wave, channel, frame.channel, level, socket.temperature);
 
-    res_signal
-}
+    // El offset se sigue entre tramas con la media de ciclos completos
+    let samples_per_cycle = adc_samples_second / socket.signal(frame.channel).fundamental_frequency();
+    let offset = state
+        .offset
+        .remove(&mut real_wave, samples_per_cycle, adc_samples_second);
 
-/*
-* @brief Process a signal.
-* @param socket Pointer to the MetrologyInsightSocket structure.
-* @param signal Pointer to the MetrologyInsightSignal structure.
-* @param adc_samples_second Number of ADC samples per second.
-* @param avg_sec Average time in seconds
-* @note This function processes a signal.
-*/
-pub fn process_signal(
-    socket: &mut MetrologyInsightSocket,
-    signal: &mut MetrologyInsightSignal,
-    adc_samples_second: f64,
-    avg_sec: f64,
-) {
-    moving_average(&mut signal.wave, 3);
-    if is_signal_valid(&signal.wave, signal.signal_type) {
-        remove_signal_offset(&mut signal.wave);
-
-        // Remove offset from the signal
-        let real_wave: Vec<f64> = convert_raw_to_physical(signal);
-
-        // Convert to volts
-        let freq_zc = if signal.calc_freq {
-            let f = calculate_zero_crossing_frequency(&real_wave, adc_samples_second);
-            if f == -1.0 {
-                FREQ_NOMINAL_50
-            } else {
-                f
+    // Componente continua respecto al cero calibrado (la salida integrada de una Rogowski no la contiene)
+    let dc_offset = if channel.sensor_type == SensorType::Rogowski {
+        0.0
+    } else {
+        offset
+    };
+
+    // Convert to volts
+    let (freq_zc, freq_uncertainty, freq_quality, freq_estimator) = if channel.calc_freq {
+        let target = socket.signal(frame.channel);
+        let estimate = estimate_frequency(state, config, frame.channel, &real_wave, target.freq_nominal)?;
+        quality.set(QualityFlags::FREQUENCY_FALLBACK, estimate.is_none());
+        (
+            estimate.map_or(target.fundamental_frequency(), |e| e.frequency),
+            estimate.map_or(0.0, |e| e.uncertainty),
+            estimate.map_or(0.0, |e| e.quality),
+            state.frequency_estimator.kind(),
+        )
+    } else {
+        let voltage = &socket.voltage_signal;
+        quality.set(
+            QualityFlags::FREQUENCY_FALLBACK,
+            voltage.quality.contains(QualityFlags::FREQUENCY_FALLBACK),
+        );
+        (
+            voltage.freq_zc,
+            voltage.freq_uncertainty,
+            voltage.freq_quality,
+            voltage.freq_estimator,
+        )
+    };
+
+    // Calculate frequency
+    let freq_nominal = calculate_nominal_frequency(freq_zc, config);
+
+    // Longitud de un ciclo a la frecuencia nominal, sin superar el buffer
+    let length_cycle = config.samples_per_cycle(freq_nominal).min(real_wave.len());
+    quality.set(
+        QualityFlags::INSUFFICIENT_CYCLES,
+        length_cycle < config.samples_per_cycle(freq_nominal),
+    );
+
+    // Calculate Peak
+    let peak = real_wave.iter().copied().fold(f64::MIN, f64::max);
+
+    // Calculate RMS
+    let rms = calculate_rms(&real_wave, length_cycle, freq_zc, adc_samples_second);
+
+    // Calcular armónicos y THD después de RMS
+    let harmonics = if config.num_harmonics > 0 {
+        match compute_harmonics_and_thd(
+            &real_wave,
+            freq_zc,
+            adc_samples_second,
+            config.num_harmonics,
+            &channel.frequency_response,
+        ) {
+            Ok(harmonics) => Some(harmonics),
+            // Los armónicos no invalidan la trama: se conservan los anteriores
+            Err(err) => {
+                log::debug!("Harmonics not updated: {}", err);
+                quality |= QualityFlags::HARMONICS_FAILED;
+                None
             }
-        } else {
-            socket.voltage_signal.freq_zc
-        };
-
-        // Calculate frequency
-        signal.freq_zc = freq_zc;
-        signal.freq_nominal = calculate_nominal_frequency(freq_zc, &mut signal.length, signal.freq_nominal);
-        signal.length_cycle = limit_length_to_cycles(signal.length, signal.freq_nominal, adc_samples_second);
-        signal.length = signal.length_cycle + EXTRA_SAMPLES;
-
-        if signal.is_current() {
-            signal_integrate(&real_wave, signal.freq_zc, adc_samples_second);
         }
+    } else {
+        None
+    };
 
-        // Calculate Peak
-        let peak = real_wave.iter().copied().fold(f64::MIN, f64::max);
-        if peak > signal.peak {
-            signal.peak = peak;
-        }
+    // Asign values to signal
+    let target = socket.signal_mut(frame.channel);
 
-        // Calculate RMS
-        let rms = calculate_rms(&real_wave, signal.length_cycle, signal.freq_zc, adc_samples_second);
-
-        // Calcular armónicos después de RMS
-        if signal.length_cycle >= FFT_RESOLUTION {
-            // Resamplear a 128 puntos (FFT_RESOLUTION)
-            let mut resampled = resample_signal(&real_wave, FFT_RESOLUTION);
-
-            // Calcular FFT, armónicos y THD
-            if let Some((harmonics, thd)) =
-                compute_harmonics_and_thd(&mut resampled, signal.freq_zc, adc_samples_second)
-            {
-                match signal.signal_type {
-                    MetrologyInsightSignalType::Voltage => {
-                        // Actualizar promedio de armónicos
-                        for i in 0..harmonics.len() {
-                            update_average(harmonics[i], &mut socket.voltage_signal.harmonics[i], avg_sec);
-                        }
-
-                        // Actualizar promedio de THD
-                        update_average(thd, &mut socket.voltage_signal.thd, avg_sec);
-                    }
-                    MetrologyInsightSignalType::Current => {
-                        // Actualizar promedio de armónicos
-                        for i in 0..harmonics.len() {
-                            update_average(harmonics[i], &mut socket.current_signal.harmonics[i], avg_sec);
-                        }
-
-                        // Actualizar promedio de THD
-                        update_average(thd, &mut socket.current_signal.thd, avg_sec);
-                    }
-                }
-            }
-        }
+    if let Some((harmonics, thd_percent)) = harmonics {
+        // Actualizar promedio de armónicos y THD
+        update_harmonics_average(&harmonics, &mut target.harmonics, avg_sec);
+        update_average(thd_percent, &mut target.thd_percent, avg_sec);
+        target.thd = thd_percent_to_db(target.thd_percent);
+    }
 
-        // Asign values to signal
-        match signal.signal_type {
-            MetrologyInsightSignalType::Voltage => {
-                socket.voltage_signal.real_wave = real_wave;
-                socket.voltage_signal.freq_nominal = signal.freq_nominal;
-                socket.voltage_signal.length_cycle = signal.length_cycle;
-                socket.voltage_signal.length = signal.length;
-                socket.voltage_signal.peak = signal.peak;
-                update_average(rms, &mut socket.voltage_signal.rms, avg_sec);
-                update_average(freq_zc, &mut socket.voltage_signal.freq_zc, avg_sec);
-            }
-            MetrologyInsightSignalType::Current => {
-                socket.current_signal.real_wave = real_wave;
-                socket.current_signal.freq_nominal = signal.freq_nominal;
-                socket.current_signal.length_cycle = signal.length_cycle;
-                socket.current_signal.length = signal.length;
-                socket.current_signal.sc_thres = signal.sc_thres;
-                socket.current_signal.peak = signal.peak;
-                socket.current_signal.freq_zc = freq_zc;
-                update_average(rms, &mut socket.current_signal.rms, avg_sec);
-                update_average(freq_zc, &mut socket.current_signal.freq_zc, avg_sec);
-            }
-        }
+    target.real_wave = real_wave;
+    target.signal_type = frame.channel;
+    target.timestamp_us = (frame.timestamp_us as f64 + channel.skew_us - filter_delay_us)
+        .max(0.0)
+        .round() as u64;
+    target.sequence = frame.sequence;
+    target.freq_nominal = freq_nominal;
+    target.length_cycle = length_cycle;
+    target.length = length_cycle + EXTRA_SAMPLES;
+    // El pico es el máximo desde el arranque, no el de la última trama
+    if peak > target.peak {
+        target.peak = peak;
     }
+    target.dc_offset = dc_offset;
+    target.freq_uncertainty = freq_uncertainty;
+    target.freq_quality = freq_quality;
+    target.freq_estimator = freq_estimator;
+    update_average(rms, &mut target.rms, avg_sec);
+    target.transformer_ratio = channel.transformer.ratio();
+    target.secondary_peak = channel.transformer.to_secondary(target.peak);
+    target.secondary_rms = channel.transformer.to_secondary(target.rms);
+    target.quality = quality;
+
+    // La frecuencia medida se promedia; la copiada del canal de tensión ya lo está
+    if channel.calc_freq {
+        update_average(freq_zc, &mut target.freq_zc, avg_sec);
+    } else {
+        target.freq_zc = freq_zc;
+    }
+
+    Ok(())
 }
@@ -0,0 +1,246 @@
+use crate::{AdcSample, MetrologyError, MetrologyInsightConfig, SampleFrame, MIN_AMPLITUDE_VOLTAGE};
+use std::collections::VecDeque;
+
+/// One cycle cut from the sample stream, from a rising zero crossing of the voltage to the next one.
+#[derive(Debug, Clone, Default)]
+pub struct StreamCycle {
+    pub voltage: Vec<f64>, // Voltage samples of the cycle (raw ADC counts)
+    pub current: Vec<f64>, // Current samples taken at the same instants
+    pub timestamp_us: u64, // Acquisition time of the first sample (µs)
+    pub sequence: u64,     // Cycle number since the segmenter was created
+    pub contiguous: bool,  // The cycle follows the previous one without missing samples
+}
+
+/// Ring buffer that turns back-to-back chunks of samples into zero-crossing-aligned cycles.
+#[derive(Debug, Clone, Default)]
+pub struct CycleSegmenter {
+    voltage: VecDeque<f64>,     // Voltage samples not yet assigned to a cycle
+    current: VecDeque<f64>,     // Current samples not yet assigned to a cycle
+    timestamp_us: f64,          // Acquisition time of the first buffered sample (µs)
+    sample_rate: f64,           // Sample rate of the last frame pushed
+    next_sequence: Option<u64>, // Sequence number expected for the next frame
+    cycles: u64,                // Cycles cut so far
+    aligned: bool,              // The first buffered sample is a rising zero crossing
+    contiguous: bool,           // No samples were discarded since the last cycle
+}
+
+/*
+* @brief Range of cycle lengths accepted by the segmenter.
+* @param config Pointer to the MetrologyInsightConfig structure.
+* @return (shortest, longest) cycle in samples, from the tolerance bands of the configured nominal frequencies
+*/
+fn cycle_length_range(config: &MetrologyInsightConfig) -> (usize, usize) {
+    let nominals = || config.nominal_frequencies.iter().copied().filter(|&f| f > 0.0);
+    let highest = nominals().fold(config.default_nominal_frequency(), f64::max) * (1.0 + config.freq_tolerance_high);
+    let lowest = nominals().fold(config.default_nominal_frequency(), f64::min) * (1.0 - config.freq_tolerance_low);
+
+    let shortest = ((config.adc_samples_seconds / highest).floor() as usize).max(2);
+    let longest = (config.adc_samples_seconds / lowest).ceil() as usize;
+
+    (shortest, longest.max(shortest))
+}
+
+/*
+* @brief Find the next rising crossing of a threshold.
+* @param signal Buffered samples
+* @param from First sample that may be the crossing
+* @param threshold Level of the crossing (ADC counts)
+* @return Index of the first sample at or above the threshold after one below it
+*/
+fn next_rising_crossing(signal: &VecDeque<f64>, from: usize, threshold: f64) -> Option<usize> {
+    (from.max(1)..signal.len()).find(|&i| signal[i - 1] < threshold && signal[i] >= threshold)
+}
+
+impl CycleSegmenter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /*
+     * @brief Append a pair of frames to the stream.
+     * @param voltage Voltage frame (any length)
+     * @param current Current frame acquired at the same instants
+     * @param config Pointer to the MetrologyInsightConfig structure.
+     * @return Ok, or LengthMismatch if the frames do not have the same length (they are dropped)
+     * @note A frame whose sequence number does not follow the previous one means that samples were lost: the
+     *       buffered samples are discarded and the stream is aligned again.
+     * @note The same is done when the timestamp of the frame is further than the gap tolerance of the energy
+     *       registers from the end of the buffered samples, so a lost frame is seen even if the sequence numbers
+     *       follow. Timestamps that do not advance (a source without a clock) are not checked.
+     */
+    pub fn push<T: AdcSample>(
+        &mut self,
+        voltage: &SampleFrame<T>,
+        current: &SampleFrame<T>,
+        config: &MetrologyInsightConfig,
+    ) -> Result<(), MetrologyError> {
+        if voltage.samples.len() != current.samples.len() {
+            return Err(MetrologyError::LengthMismatch {
+                voltage: voltage.samples.len(),
+                current: current.samples.len(),
+            });
+        }
+
+        // Instante en que debería empezar la trama, a continuación de las muestras del buffer
+        let timestamp_us = voltage.timestamp_us as f64;
+        let expected_us = if self.sample_rate > 0.0 {
+            self.timestamp_us + self.voltage.len() as f64 * 1e6 / self.sample_rate
+        } else {
+            self.timestamp_us
+        };
+
+        if self.next_sequence.is_some_and(|sequence| sequence != voltage.sequence) {
+            log::debug!(
+                "Sample stream gap: frame {} expected, {} received",
+                self.next_sequence.unwrap_or_default(),
+                voltage.sequence
+            );
+            self.clear();
+        } else if !self.voltage.is_empty()
+            && timestamp_us > self.timestamp_us
+            && (timestamp_us - expected_us).abs() > config.energy_gaps.tolerance_sec * 1e6
+        {
+            log::debug!(
+                "Sample stream gap: frame at {} µs expected, {} µs received",
+                expected_us.round(),
+                voltage.timestamp_us
+            );
+            self.clear();
+        }
+        self.next_sequence = Some(voltage.sequence.wrapping_add(1));
+
+        if self.voltage.is_empty() {
+            self.timestamp_us = voltage.timestamp_us as f64;
+        }
+        self.sample_rate = voltage.sample_rate;
+
+        self.voltage.extend(voltage.samples.iter().map(|s| s.to_f64()));
+        self.current.extend(current.samples.iter().map(|s| s.to_f64()));
+
+        Ok(())
+    }
+
+    /*
+     * @brief Samples waiting in the ring buffer.
+     */
+    pub fn len(&self) -> usize {
+        self.voltage.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.voltage.is_empty()
+    }
+
+    /*
+     * @brief Forget the buffered samples and the alignment.
+     */
+    pub fn clear(&mut self) {
+        self.voltage.clear();
+        self.current.clear();
+        self.next_sequence = None;
+        self.aligned = false;
+        self.contiguous = false;
+    }
+
+    /*
+     * @brief Remove the oldest samples, advancing the time of the first buffered sample.
+     * @param count Samples to remove
+     * @return (voltage, current) samples removed
+     */
+    fn take(&mut self, count: usize) -> (Vec<f64>, Vec<f64>) {
+        let count = count.min(self.voltage.len());
+        if self.sample_rate > 0.0 {
+            self.timestamp_us += count as f64 * 1e6 / self.sample_rate;
+        }
+
+        (
+            self.voltage.drain(..count).collect(),
+            self.current.drain(..count).collect(),
+        )
+    }
+
+    /*
+     * @brief Discard the oldest samples.
+     * @param count Samples to drop
+     * @note The next cycle is flagged as not contiguous.
+     */
+    fn discard(&mut self, count: usize) {
+        if count > 0 {
+            self.take(count);
+            self.contiguous = false;
+        }
+    }
+
+    /*
+     * @brief Cut the next complete cycle from the buffer.
+     * @param config Pointer to the MetrologyInsightConfig structure.
+     * @return Cycle, or None until a complete one is buffered
+     * @note Cycles start at a rising crossing of the voltage midpoint and end just before the next one, so
+     *       consecutive cycles are back to back and may span any number of chunks. The threshold is the
+     *       midpoint between the maximum and minimum of the buffer, which holds at least one cycle.
+     * @note Crossings closer than the shortest accepted cycle are ignored (noise, harmonics). When no crossing
+     *       is found within the longest accepted cycle, or the voltage is too low, the samples are discarded
+     *       and the stream is aligned again on the next crossing.
+     */
+    pub fn next_cycle(&mut self, config: &MetrologyInsightConfig) -> Option<StreamCycle> {
+        let (shortest, longest) = cycle_length_range(config);
+
+        loop {
+            // Se necesita más de un ciclo completo para fijar el umbral y encontrar el cruce final
+            if self.voltage.len() <= longest {
+                return None;
+            }
+
+            let (min, max) = self
+                .voltage
+                .iter()
+                .fold((f64::MAX, f64::MIN), |(min, max), &x| (min.min(x), max.max(x)));
+
+            if max - min < MIN_AMPLITUDE_VOLTAGE {
+                self.aligned = false;
+                self.discard(self.voltage.len() - longest);
+                return None;
+            }
+
+            let threshold = (max + min) / 2.0;
+
+            if !self.aligned {
+                match next_rising_crossing(&self.voltage, 1, threshold) {
+                    Some(start) => {
+                        self.discard(start);
+                        self.aligned = true;
+                    }
+                    None => {
+                        self.discard(self.voltage.len() - longest);
+                        return None;
+                    }
+                }
+                continue;
+            }
+
+            match next_rising_crossing(&self.voltage, shortest, threshold) {
+                Some(end) if end <= longest => {
+                    let timestamp_us = self.timestamp_us.round() as u64;
+                    let (voltage, current) = self.take(end);
+                    let cycle = StreamCycle {
+                        voltage,
+                        current,
+                        timestamp_us,
+                        sequence: self.cycles,
+                        contiguous: self.contiguous,
+                    };
+                    self.cycles += 1;
+                    self.contiguous = true;
+                    return Some(cycle);
+                }
+                // Ciclo demasiado largo: se descarta hasta el cruce encontrado
+                Some(end) => self.discard(end),
+                // Sin cruce en el ciclo más largo: se vuelve a buscar la alineación
+                None => {
+                    self.aligned = false;
+                    self.discard(self.voltage.len() - longest);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,114 @@
+use crate::MetrologyError;
+use core::fmt::Debug;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+pub const THERMAL_ZONE_DIR: &str = "/sys/class/thermal"; // Zonas térmicas del kernel
+
+/// Source of the temperature used by the temperature compensation of the calibration.
+pub trait TemperatureSensor: Send + Debug {
+    /*
+     * @brief Read the current temperature.
+     * @return Temperature in °C, or TemperatureSensor if it cannot be read
+     */
+    fn read(&mut self) -> Result<f64, MetrologyError>;
+
+    fn clone_box(&self) -> Box<dyn TemperatureSensor>;
+}
+
+impl Clone for Box<dyn TemperatureSensor> {
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
+}
+
+/* ----------------- Sysfs thermal zone ------------------ */
+
+/// Temperature of a Linux thermal zone, read from sysfs.
+#[derive(Debug, Clone)]
+pub struct SysfsTemperatureSensor {
+    path: PathBuf, // File holding the temperature in millidegrees
+}
+
+impl SysfsTemperatureSensor {
+    /*
+     * @brief Sensor of a thermal zone.
+     * @param zone Index of the zone (/sys/class/thermal/thermal_zone<zone>)
+     */
+    pub fn new(zone: usize) -> Self {
+        Self::from_path(
+            Path::new(THERMAL_ZONE_DIR)
+                .join(format!("thermal_zone{}", zone))
+                .join("temp"),
+        )
+    }
+
+    /*
+     * @brief Sensor read from any file with the sysfs format (an integer in millidegrees).
+     * @param path Path of the file
+     */
+    pub fn from_path(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl TemperatureSensor for SysfsTemperatureSensor {
+    fn read(&mut self) -> Result<f64, MetrologyError> {
+        let text = std::fs::read_to_string(&self.path)
+            .map_err(|err| MetrologyError::TemperatureSensor(format!("{}: {}", self.path.display(), err)))?;
+        let millidegrees: i64 = text
+            .trim()
+            .parse()
+            .map_err(|_| MetrologyError::TemperatureSensor(format!("{}: invalid value", self.path.display())))?;
+
+        Ok(millidegrees as f64 / 1000.0)
+    }
+
+    fn clone_box(&self) -> Box<dyn TemperatureSensor> {
+        Box::new(self.clone())
+    }
+}
+
+/* ----------------- Mock ------------------ */
+
+/// Temperature set by the caller, for tests and bench setups. Clones share the same value.
+#[derive(Debug, Clone)]
+pub struct MockTemperatureSensor {
+    temperature: Arc<Mutex<Option<f64>>>, // None makes the reads fail
+}
+
+impl MockTemperatureSensor {
+    pub fn new(temperature: f64) -> Self {
+        Self {
+            temperature: Arc::new(Mutex::new(Some(temperature))),
+        }
+    }
+
+    /*
+     * @brief Change the temperature returned by this sensor and its clones.
+     * @param temperature Temperature in °C, or None to simulate a failed read
+     */
+    pub fn set(&self, temperature: Option<f64>) {
+        if let Ok(mut value) = self.temperature.lock() {
+            *value = temperature;
+        }
+    }
+}
+
+impl TemperatureSensor for MockTemperatureSensor {
+    fn read(&mut self) -> Result<f64, MetrologyError> {
+        self.temperature
+            .lock()
+            .ok()
+            .and_then(|value| *value)
+            .ok_or_else(|| MetrologyError::TemperatureSensor("mock sensor unavailable".to_string()))
+    }
+
+    fn clone_box(&self) -> Box<dyn TemperatureSensor> {
+        Box::new(self.clone())
+    }
+}
@@ -0,0 +1,168 @@
+use crate::{MetrologyInsightSignal, MetrologyInsightSocket};
+
+pub const GAP_TOLERANCE_SEC: f64 = 0.01; // Desfase entre tramas atribuido al jitter de los timestamps (s)
+pub const MAX_BRIDGE_SEC: f64 = 1.0; // Hueco más largo que se rellena con la última potencia (s)
+
+/// What the energy registers do with the time between two processed frames that no frame covered.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EnergyGapPolicy {
+    #[default]
+    Bridge, // Register the gap at the power of the last valid frame (gaps up to max_bridge_sec)
+    Skip, // Register nothing and count the gap as uncovered time
+}
+
+impl EnergyGapPolicy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EnergyGapPolicy::Bridge => "Bridge",
+            EnergyGapPolicy::Skip => "Skip",
+        }
+    }
+}
+
+/// Handling of the gaps between the frames that reach the energy registers.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EnergyGapConfig {
+    pub policy: EnergyGapPolicy, // What to do with a gap
+    pub tolerance_sec: f64,      // Shorter gaps are timestamp jitter and are ignored (s)
+    pub max_bridge_sec: f64,     // Longer gaps are never bridged (s)
+}
+
+impl Default for EnergyGapConfig {
+    fn default() -> Self {
+        Self {
+            policy: EnergyGapPolicy::default(),
+            tolerance_sec: GAP_TOLERANCE_SEC,
+            max_bridge_sec: MAX_BRIDGE_SEC,
+        }
+    }
+}
+
+/// Time covered by a frame and the gap that precedes it.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct FrameTiming {
+    pub duration: f64, // Duration of the samples of the frame (s)
+    pub gap: f64,      // Time since the end of the previous frame not covered by any frame (s)
+}
+
+/// Powers that feed the energy registers during a frame.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct EnergyPowers {
+    pub real_power: f64,        // P (W)
+    pub reactive_power: f64,    // Signed Q (VAR)
+    pub apparent_power: f64,    // S (VA)
+    pub fundamental_power: f64, // P1 (W)
+    pub harmonic_power: f64,    // PH (W)
+}
+
+impl EnergyPowers {
+    /*
+     * @brief Powers of the last frame processed in a socket.
+     * @param socket Pointer to the MetrologyInsightSocket structure.
+     */
+    pub fn from_socket(socket: &MetrologyInsightSocket) -> Self {
+        Self {
+            real_power: socket.power_metrics.real_power,
+            reactive_power: socket.power_metrics.reactive_power,
+            apparent_power: socket.power_metrics.apparent_power,
+            fundamental_power: socket.power_decomposition.fundamental_active_power,
+            harmonic_power: socket.power_decomposition.harmonic_active_power,
+        }
+    }
+}
+
+/// Acquisition time of the last frame that reached the energy registers, carried from frame to frame.
+#[derive(Debug, Clone, Default)]
+pub struct EnergyTimeBase {
+    last: Option<(u64, f64, u64)>, // Start (µs), end (µs) and sequence number of the last frame
+    last_power: Option<EnergyPowers>, // Powers registered for the last frame
+}
+
+impl EnergyTimeBase {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /*
+     * @brief Forget the last frame; the next one starts a new time base without a gap.
+     */
+    pub fn reset(&mut self) {
+        self.last = None;
+        self.last_power = None;
+    }
+
+    /*
+     * @brief Power registered for the last frame, used to bridge the next gap.
+     * @return Powers, or None before the first frame
+     */
+    pub fn last_power(&self) -> Option<EnergyPowers> {
+        self.last_power
+    }
+
+    pub fn set_last_power(&mut self, power: EnergyPowers) {
+        self.last_power = Some(power);
+    }
+
+    /*
+     * @brief Timing of a frame against the last one, before the tolerance is applied.
+     */
+    fn timing(&self, signal: &MetrologyInsightSignal, adc_samples_second: f64) -> Option<FrameTiming> {
+        if signal.real_wave.is_empty() || adc_samples_second <= 0.0 {
+            return None;
+        }
+
+        let duration = signal.real_wave.len() as f64 / adc_samples_second;
+        let start_us = signal.timestamp_us;
+        let sequence = signal.sequence;
+
+        let gap = match self.last {
+            None => 0.0,
+            Some((last_start_us, last_end_us, _)) if start_us > last_start_us => {
+                ((start_us as f64 - last_end_us) * 1e-6).max(0.0)
+            }
+            Some((_, _, last_sequence)) if sequence > last_sequence => (sequence - last_sequence - 1) as f64 * duration,
+            Some(_) => 0.0,
+        };
+
+        Some(FrameTiming { duration, gap })
+    }
+
+    /*
+     * @brief Time since the end of the last registered frame up to the end of a new one, gaps included.
+     * @param signal Signal of the socket that times the registers (the voltage)
+     * @param adc_samples_second Number of ADC samples per second.
+     * @return Elapsed time in seconds, 0 if the frame holds no samples
+     * @note The time base is not advanced; the energy registers do it afterwards with the same frame.
+     */
+    pub fn elapsed(&self, signal: &MetrologyInsightSignal, adc_samples_second: f64) -> f64 {
+        self.timing(signal, adc_samples_second)
+            .map_or(0.0, |timing| timing.duration + timing.gap)
+    }
+
+    /*
+     * @brief Time a frame covers and the gap since the previous one.
+     * @param signal Signal of the socket that times the registers (the voltage)
+     * @param adc_samples_second Number of ADC samples per second.
+     * @param tolerance_sec Shorter gaps are taken as timestamp jitter
+     * @return Timing of the frame, or None if it holds no samples
+     * @note The duration comes from the number of samples. The gap comes from the acquisition timestamps; if
+     *       they do not advance (a source without a clock), from the frames missing in the sequence numbers,
+     *       each as long as this one.
+     */
+    pub fn advance(
+        &mut self,
+        signal: &MetrologyInsightSignal,
+        adc_samples_second: f64,
+        tolerance_sec: f64,
+    ) -> Option<FrameTiming> {
+        let timing = self.timing(signal, adc_samples_second)?;
+        let start_us = signal.timestamp_us;
+
+        self.last = Some((start_us, start_us as f64 + timing.duration * 1e6, signal.sequence));
+
+        Some(FrameTiming {
+            duration: timing.duration,
+            gap: if timing.gap > tolerance_sec { timing.gap } else { 0.0 },
+        })
+    }
+}
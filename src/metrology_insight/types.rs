@@ -1,22 +1,223 @@
+use crate::{
+    build_frequency_estimator, AntiCreepConfig, ChannelCalibration, CreepDetector, CreepState, CycleSegmenter,
+    EnergyGapConfig, EnergyRegister, EnergyTimeBase, FilterChain, FrequencyEstimator, FrequencyResponse, OffsetTracker,
+    QualityFlags, RogowskiIntegrator, TemperatureSensor, TransformerRatio, REGISTER_DIGITS,
+};
+
 pub const FREQ_NOMINAL_50: f64 = 50.0;
 pub const FREQ_NOMINAL_60: f64 = 60.0;
 
-pub const ADC_SAMPLES_50HZ_CYCLE: f64 = 156.0; /* N=fs​×Tciclo​=7812,5Hz×0,02s=156,25 */
-pub const ADC_SAMPLES_60HZ_CYCLE: usize = 131;
+pub const ADC_SAMPLES_SECOND: f64 = 7812.5; // Default sampling rate of the CV180x SAR ADC
+
+pub const NUMBER_HARMONICS: usize = 50; // Default highest harmonic order
 
-pub const NUMBER_HARMONICS: usize = 21;
+pub const FREQ_TOLERANCE_LOW: f64 = 0.05; // Default tolerance below the nominal frequency (-5 %)
+pub const FREQ_TOLERANCE_HIGH: f64 = 0.07; // Default tolerance above the nominal frequency (+7 %)
 
 pub const MIN_AMPLITUDE_VOLTAGE: f64 = 80.0;
 pub const MIN_AMPLITUDE_CURRENT: f64 = 0.001;
 
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FrequencyEstimatorKind {
+    #[default]
+    ZeroCrossing, // Interpolated rising zero crossings within the frame
+    InterpolatedDft, // Hann-windowed DFT over several cycles with peak interpolation
+    Pll,             // SOGI software PLL
+    Kalman,          // Extended Kalman filter on phase, frequency and amplitude
+}
+
+impl FrequencyEstimatorKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FrequencyEstimatorKind::ZeroCrossing => "Zero crossing",
+            FrequencyEstimatorKind::InterpolatedDft => "Interpolated DFT",
+            FrequencyEstimatorKind::Pll => "PLL",
+            FrequencyEstimatorKind::Kalman => "Kalman",
+        }
+    }
+}
+
+/// Type of sensor connected to a channel.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SensorType {
+    #[default]
+    CurrentTransformer, // Output proportional to the current
+    Rogowski,           // Output proportional to the derivative of the current
+    Shunt,              // Resistive shunt
+    Hall,               // Hall-effect sensor
+    VoltageTransformer, // Output proportional to the voltage
+}
+
+impl SensorType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SensorType::CurrentTransformer => "Current transformer",
+            SensorType::Rogowski => "Rogowski coil",
+            SensorType::Shunt => "Shunt",
+            SensorType::Hall => "Hall effect",
+            SensorType::VoltageTransformer => "Voltage transformer",
+        }
+    }
+}
+
+/// Digital filter of a channel's filter chain.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum FilterKind {
+    #[default]
+    PassThrough, // Samples unchanged
+    MovingAverage {
+        window: usize,
+    }, // Boxcar average, zero phase once its delay is compensated
+    Fir {
+        taps: Vec<f64>,
+    }, // FIR filter, taps from the newest sample backwards
+    Biquad {
+        b: [f64; 3],
+        a: [f64; 2],
+    }, // Second-order IIR section, a0 normalised to 1
+}
+
+impl FilterKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FilterKind::PassThrough => "Pass-through",
+            FilterKind::MovingAverage { .. } => "Moving average",
+            FilterKind::Fir { .. } => "FIR",
+            FilterKind::Biquad { .. } => "Biquad",
+        }
+    }
+}
+
+/// Frequency given by a FrequencyEstimator.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FrequencyEstimate {
+    pub frequency: f64,   // Estimated frequency (Hz)
+    pub uncertainty: f64, // Standard uncertainty (Hz)
+    pub quality: f64,     // 0 (unusable) to 1 (exact)
+}
+
+/// Processing options of one channel.
+#[derive(Debug, Clone)]
+pub struct ChannelConfig {
+    pub calc_freq: bool,                             // Measure the frequency (else copied from voltage)
+    pub frequency_estimator: FrequencyEstimatorKind, // Used when calc_freq is set
+    pub adc_factor: f64,                             // ADC factor (counts to volts at the ADC input)
+    pub adc_scale: f64,                              // ADC scale (volts at the ADC input to amperes)
+    pub calibration: ChannelCalibration,             // Gain, offset and phase correction of the channel
+    pub sc_thres: f64,                               // Short circuit threshold
+    pub sensor_type: SensorType,                     // Sensor type (a Rogowski output is integrated)
+    pub filters: Vec<FilterKind>,                    // Filter chain applied to the samples, in order
+    pub skew_us: f64,                                // Sampling instant after the frame timestamp (µs)
+    pub frequency_response: FrequencyResponse,       // Sensor response removed from the harmonics (empty = flat)
+    pub transformer: TransformerRatio,               // Instrument transformer; results are on its primary side
+}
+
+impl Default for ChannelConfig {
+    fn default() -> Self {
+        Self {
+            calc_freq: false,
+            frequency_estimator: FrequencyEstimatorKind::default(),
+            adc_factor: 1.0,
+            adc_scale: 1.0,
+            calibration: ChannelCalibration::default(),
+            sc_thres: 0.0,
+            sensor_type: SensorType::default(),
+            filters: vec![], // Sin filtros: el promedio de 3 muestras que se aplicaba siempre ahora se configura
+            skew_us: 0.0,
+            frequency_response: FrequencyResponse::default(),
+            transformer: TransformerRatio::default(),
+        }
+    }
+}
+
 /// Inititial configuration
 #[derive(Clone)]
 pub struct MetrologyInsightConfig {
     pub avg_sec: f64,
     pub adc_samples_seconds: f64,
-    pub adc_samples_per_cycle: f64,
-    #[allow(dead_code)]
-    pub num_harmonics: usize,
+    pub nominal_frequencies: Vec<f64>, // Candidate nominal frequencies (Hz); the first one is the default
+    pub freq_tolerance_low: f64,       // Tolerance below the nominal, as a fraction (0.05 = -5 %)
+    pub freq_tolerance_high: f64,      // Tolerance above the nominal, as a fraction (0.07 = +7 %)
+    pub num_harmonics: usize,          // Highest harmonic order analysed (0 disables the harmonic analysis)
+    pub adc_max_code: f64,             // Highest ADC code, for the range checks (0, for signed input, disables them)
+    pub harmonic_grouping: bool,       // IEC 61000-4-7 Class I groups over a 10/12-cycle window
+    pub anti_creep: AntiCreepConfig,   // No-load thresholds of the energy registers
+    pub register_digits: u32,          // Whole kWh digits of the energy registers (0 = no rollover)
+    pub energy_gaps: EnergyGapConfig,  // Handling of the time between frames not covered by any frame
+    pub voltage: ChannelConfig,        // Voltage channel options
+    pub current: ChannelConfig,        // Current channel options
+}
+
+impl MetrologyInsightConfig {
+    /*
+     * @brief Options of the channel that carries a type of signal.
+     * @param signal_type Type of the signal (voltage or current)
+     */
+    pub fn channel(&self, signal_type: MetrologyInsightSignalType) -> &ChannelConfig {
+        match signal_type {
+            MetrologyInsightSignalType::Voltage => &self.voltage,
+            MetrologyInsightSignalType::Current => &self.current,
+        }
+    }
+
+    /*
+     * @brief Nominal frequency used before any frequency has been measured.
+     * @return First candidate nominal frequency, or 50 Hz if the list is empty
+     */
+    pub fn default_nominal_frequency(&self) -> f64 {
+        self.nominal_frequencies.first().copied().unwrap_or(FREQ_NOMINAL_50)
+    }
+
+    /*
+     * @brief Select the nominal frequency that matches a measured frequency.
+     * @param freq Measured frequency in Hz
+     * @return Candidate whose tolerance band contains the frequency (the closest one if several do), or None
+     */
+    pub fn select_nominal_frequency(&self, freq: f64) -> Option<f64> {
+        self.nominal_frequencies
+            .iter()
+            .copied()
+            .filter(|&nominal| {
+                freq > nominal * (1.0 - self.freq_tolerance_low) && freq < nominal * (1.0 + self.freq_tolerance_high)
+            })
+            .min_by(|a, b| ((freq - a).abs() / a).total_cmp(&((freq - b).abs() / b)))
+    }
+
+    /*
+     * @brief Whole samples in one cycle of a frequency at the configured sample rate.
+     * @param freq Frequency in Hz
+     * @return Samples per cycle, rounded to the nearest integer (0 for a non-positive frequency)
+     */
+    pub fn samples_per_cycle(&self, freq: f64) -> usize {
+        if freq > 0.0 {
+            (self.adc_samples_seconds / freq).round() as usize
+        } else {
+            0
+        }
+    }
+}
+
+impl Default for MetrologyInsightConfig {
+    fn default() -> Self {
+        Self {
+            avg_sec: 0.02,
+            adc_samples_seconds: ADC_SAMPLES_SECOND,
+            nominal_frequencies: vec![FREQ_NOMINAL_50, FREQ_NOMINAL_60],
+            freq_tolerance_low: FREQ_TOLERANCE_LOW,
+            freq_tolerance_high: FREQ_TOLERANCE_HIGH,
+            num_harmonics: NUMBER_HARMONICS,
+            adc_max_code: 0.0,
+            harmonic_grouping: false,
+            anti_creep: AntiCreepConfig::default(),
+            register_digits: REGISTER_DIGITS,
+            energy_gaps: EnergyGapConfig::default(),
+            voltage: ChannelConfig {
+                calc_freq: true,
+                ..Default::default()
+            },
+            current: ChannelConfig::default(),
+        }
+    }
 }
 
 /// Represents a three-phase socket with current, voltage, power, and energy data.
@@ -34,18 +235,113 @@ pub struct MetrologyInsightSocket {
     // Power metrics
     pub power_metrics: PowerMetrics,
 
+    // IEEE 1459 power decomposition
+    pub power_decomposition: PowerDecomposition,
+
     // Energy metrics
     pub energy_metrics: EnergyMetrics,
+
+    // Temperature used by the temperature compensation (°C), None without a sensor
+    pub temperature: Option<f64>,
+
+    // Quality of the snapshot: flags of both signals and of the combined metrics
+    pub quality: QualityFlags,
+
+    // No-load state of the energy registers
+    pub creep_state: CreepState,
 }
 
 impl MetrologyInsightSocket {
+    /*
+     * @brief Signal of the socket that holds the results of a channel.
+     * @param signal_type Type of the signal (voltage or current)
+     */
+    pub fn signal(&self, signal_type: MetrologyInsightSignalType) -> &MetrologyInsightSignal {
+        match signal_type {
+            MetrologyInsightSignalType::Voltage => &self.voltage_signal,
+            MetrologyInsightSignalType::Current => &self.current_signal,
+        }
+    }
+
+    pub fn signal_mut(&mut self, signal_type: MetrologyInsightSignalType) -> &mut MetrologyInsightSignal {
+        match signal_type {
+            MetrologyInsightSignalType::Voltage => &mut self.voltage_signal,
+            MetrologyInsightSignalType::Current => &mut self.current_signal,
+        }
+    }
+
     pub fn into_proto(self) -> metrology_proto::metrology_insight::MetrologyInsightSocket {
         metrology_proto::metrology_insight::MetrologyInsightSocket {
             voltage_signal: Some(self.voltage_signal.into_proto()),
             current_signal: Some(self.current_signal.into_proto()),
             phase_angles: Some(self.phase_angles.into_proto()),
             power_metrics: Some(self.power_metrics.into_proto()),
+            power_decomposition: Some(self.power_decomposition.into_proto()),
             energy_metrics: Some(self.energy_metrics.into_proto()),
+            temperature: self.temperature,
+            quality: self.quality.bits(),
+            creep_state: self.creep_state.as_str().to_string(),
+        }
+    }
+}
+
+/// Processing state of one channel that is carried from frame to frame.
+#[derive(Debug, Clone)]
+pub struct ChannelState {
+    pub grouping_buffer: Vec<f64>,                        // Samples of the next grouping window
+    pub frequency_estimator: Box<dyn FrequencyEstimator>, // Estimator selected in the configuration
+    pub integrator: RogowskiIntegrator,                   // Integrator of a Rogowski coil output
+    pub offset: OffsetTracker,                            // DC level of the channel
+    pub filters: FilterChain,                             // Filter chain built from the configuration
+}
+
+impl ChannelState {
+    pub fn new(config: &ChannelConfig) -> Self {
+        Self {
+            grouping_buffer: vec![],
+            frequency_estimator: build_frequency_estimator(config.frequency_estimator),
+            integrator: RogowskiIntegrator::new(),
+            offset: OffsetTracker::new(),
+            filters: FilterChain::new(),
+        }
+    }
+
+    /*
+     * @brief Forget the state that assumes contiguous frames, after samples have been lost.
+     */
+    pub fn reset(&mut self) {
+        self.grouping_buffer.clear();
+        self.frequency_estimator.reset();
+        self.integrator.reset();
+        self.offset.reset();
+        self.filters.reset();
+    }
+}
+
+impl Default for ChannelState {
+    fn default() -> Self {
+        Self::new(&ChannelConfig::default())
+    }
+}
+
+/// Processing state of the voltage and current channels.
+#[derive(Debug, Default, Clone)]
+pub struct MetrologyInsightState {
+    pub voltage: ChannelState,
+    pub current: ChannelState,
+    pub stream: CycleSegmenter, // Samples received with push_samples, pending to complete a cycle
+    pub creep: CreepDetector,   // No-load state of the energy registers
+    pub time_base: EnergyTimeBase, // Acquisition time of the last frame registered
+}
+
+impl MetrologyInsightState {
+    pub fn new(config: &MetrologyInsightConfig) -> Self {
+        Self {
+            voltage: ChannelState::new(&config.voltage),
+            current: ChannelState::new(&config.current),
+            stream: CycleSegmenter::new(),
+            creep: CreepDetector::new(),
+            time_base: EnergyTimeBase::new(),
         }
     }
 }
@@ -54,6 +350,8 @@ impl MetrologyInsightSocket {
 pub struct MetrologyInsight {
     pub socket: MetrologyInsightSocket,
     pub config: MetrologyInsightConfig,
+    pub state: MetrologyInsightState,
+    pub temperature_sensor: Option<Box<dyn TemperatureSensor>>, // Source of the temperature compensation
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -77,48 +375,121 @@ impl Default for MetrologyInsightSignalType {
     }
 }
 
-/// Represents a current or voltage signal.
+/// Samples that can be delivered by the acquisition.
+pub trait AdcSample: Copy {
+    fn to_f64(self) -> f64;
+}
+
+impl AdcSample for i16 {
+    fn to_f64(self) -> f64 {
+        self as f64
+    }
+}
+
+impl AdcSample for i32 {
+    fn to_f64(self) -> f64 {
+        self as f64
+    }
+}
+
+impl AdcSample for f32 {
+    fn to_f64(self) -> f64 {
+        self as f64
+    }
+}
+
+impl AdcSample for f64 {
+    fn to_f64(self) -> f64 {
+        self
+    }
+}
+
+/// Block of raw samples of one channel, as delivered by the acquisition.
+#[derive(Debug, Clone, Default)]
+pub struct SampleFrame<T: AdcSample> {
+    pub channel: MetrologyInsightSignalType, // Channel that acquired the samples
+    pub samples: Vec<T>,                     // Raw ADC samples
+    pub sample_rate: f64,                    // Samples per second
+    pub timestamp_us: u64,                   // Acquisition time of the first sample (µs)
+    pub sequence: u64,                       // Frame number, incremented by one for every frame of the channel
+}
+
+impl<T: AdcSample> SampleFrame<T> {
+    pub fn new(channel: MetrologyInsightSignalType, samples: Vec<T>, sample_rate: f64) -> Self {
+        Self {
+            channel,
+            samples,
+            sample_rate,
+            timestamp_us: 0,
+            sequence: 0,
+        }
+    }
+
+    // Duración de la trama en segundos
+    pub fn duration_seconds(&self) -> f64 {
+        if self.sample_rate > 0.0 {
+            self.samples.len() as f64 / self.sample_rate
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Results of a current or voltage signal.
 #[derive(Clone, Debug)]
 pub struct MetrologyInsightSignal {
-    pub wave: Vec<i32>,                          // Signal buffer
     pub real_wave: Vec<f64>,                     // Real signal buffer
     pub length: usize,                           // Length of the sample buffer (usually greater than 1 cycle)
     pub length_cycle: usize,                     // Samples in 1 cycle of the signal (less than the buffer length)
-    pub calc_freq: bool,                         // Indicates if the frequency should be calculated from the signal
-    pub peak: f64,                               // Peak value of the signal
+    pub timestamp_us: u64,                       // Acquisition time of the last frame processed (µs)
+    pub sequence: u64,                           // Sequence number of the last frame processed
+    pub peak: f64,                               // Highest sample since the socket was created
     pub rms: f64,                                // RMS value of the signal
-    pub freq_nominal: f64,                       // Nominal frequency (50Hz or 60Hz)
-    pub freq_zc: f64,                            // Frequency of the signal based on zero crossing
-    pub harmonics: [f64; NUMBER_HARMONICS],      // Array of amplitudes and phases of harmonics
-    pub thd: f64,                                // Total harmonic distortion
-    pub sc_thres: f64,                           // Short circuit threshold
+    pub freq_nominal: f64,                       // Nominal frequency selected from the configuration
+    pub freq_zc: f64,                            // Measured frequency of the signal
+    pub freq_uncertainty: f64,                   // Standard uncertainty of the measured frequency (Hz)
+    pub freq_quality: f64,                       // Quality of the measured frequency (0 to 1)
+    pub freq_estimator: FrequencyEstimatorKind,  // Estimator that measured the frequency
+    pub harmonics: Vec<HarmonicComponent>,       // Harmonic orders 1..=num_harmonics
+    pub thd: f64,                                // Total harmonic distortion (dB)
+    pub thd_percent: f64,                        // Total harmonic distortion (% of fundamental)
+    pub harmonic_groups: HarmonicGroups,         // IEC 61000-4-7 grouped spectrum of the last window
     pub signal_type: MetrologyInsightSignalType, // Tipo de señal (tensión o corriente)
-    pub adc_factor: f64,                         // ADC factor
-    pub adc_scale: f64,                          // ADC scale
-    pub dc_offset: f64,                          // DC offset component
+    pub dc_offset: f64,                          // DC component, relative to the ADC zero of the channel
+    pub transformer_ratio: f64,                  // Primary over secondary of the instrument transformer
+    pub secondary_peak: f64,                     // Peak value at the secondary of the instrument transformer
+    pub secondary_rms: f64,                      // RMS value at the secondary of the instrument transformer
+    pub quality: QualityFlags,                   // Conditions that make the values suspect
 }
 
 impl MetrologyInsightSignal {
     pub fn into_proto(self) -> metrology_proto::metrology_insight::MetrologyInsightSignal {
         metrology_proto::metrology_insight::MetrologyInsightSignal {
-            wave: self.wave,
             real_wave: self.real_wave,
             length: self.length as u32,
             length_cycle: self.length_cycle as u32,
-            calc_freq: self.calc_freq,
+            timestamp_us: self.timestamp_us,
+            sequence: self.sequence,
             peak: self.peak,
             rms: self.rms,
             freq_nominal: self.freq_nominal,
             freq_zc: self.freq_zc,
-            harmonics: self.harmonics.to_vec(),
+            freq_uncertainty: self.freq_uncertainty,
+            freq_quality: self.freq_quality,
+            freq_estimator: self.freq_estimator.as_str().to_string(),
+            harmonics: self.harmonics.into_iter().map(HarmonicComponent::into_proto).collect(),
             thd: self.thd,
-            sc_thres: self.sc_thres,
+            thd_percent: self.thd_percent,
+            harmonic_groups: Some(self.harmonic_groups.into_proto()),
             signal_type: match self.signal_type {
                 MetrologyInsightSignalType::Voltage => "Voltage".to_string(),
                 MetrologyInsightSignalType::Current => "Current".to_string(),
             },
-            adc_factor: self.adc_factor,
-            adc_scale: self.adc_scale,
+            dc_offset: self.dc_offset,
+            transformer_ratio: self.transformer_ratio,
+            secondary_peak: self.secondary_peak,
+            secondary_rms: self.secondary_rms,
+            quality: self.quality.bits(),
         }
     }
 
@@ -126,27 +497,88 @@ impl MetrologyInsightSignal {
     pub fn is_current(&self) -> bool {
         matches!(self.signal_type, MetrologyInsightSignalType::Current)
     }
+
+    // Frecuencia medida, o la nominal si aún no se ha medido
+    pub fn fundamental_frequency(&self) -> f64 {
+        if self.freq_zc > 0.0 {
+            self.freq_zc
+        } else {
+            self.freq_nominal
+        }
+    }
 }
 
 impl Default for MetrologyInsightSignal {
     fn default() -> Self {
         Self {
-            wave: vec![],
             real_wave: vec![],
             length: 0,
             length_cycle: 0,
-            calc_freq: false,
+            timestamp_us: 0,
+            sequence: 0,
             peak: 0.0,
             rms: 0.0,
             freq_nominal: FREQ_NOMINAL_50,
             freq_zc: 0.0,
-            harmonics: [0.0; NUMBER_HARMONICS],
+            freq_uncertainty: 0.0,
+            freq_quality: 0.0,
+            freq_estimator: FrequencyEstimatorKind::default(),
+            harmonics: vec![],
             thd: 0.0,
-            sc_thres: 0.0,
+            thd_percent: 0.0,
+            harmonic_groups: HarmonicGroups::default(),
             signal_type: MetrologyInsightSignalType::Voltage,
-            adc_factor: 1.0,
-            adc_scale: 1.0,
             dc_offset: 0.0,
+            transformer_ratio: 1.0,
+            secondary_peak: 0.0,
+            secondary_rms: 0.0,
+            quality: QualityFlags::empty(),
+        }
+    }
+}
+
+/// Amplitude and phase of one harmonic order.
+#[derive(Debug, Clone, Default)]
+pub struct HarmonicComponent {
+    pub order: usize, // Harmonic order (1 = fundamental)
+    pub rms: f64,     // RMS value in signal units (V or A)
+    pub percent: f64, // Percentage of the fundamental
+    pub phase: f64,   // Phase angle in degrees, cosine reference
+}
+
+impl HarmonicComponent {
+    pub fn into_proto(self) -> metrology_proto::metrology_insight::HarmonicComponent {
+        metrology_proto::metrology_insight::HarmonicComponent {
+            order: self.order as u32,
+            rms: self.rms,
+            percent: self.percent,
+            phase: self.phase,
+        }
+    }
+}
+
+/// Harmonic and interharmonic groups defined by IEC 61000-4-7 (RMS values in signal units).
+#[derive(Debug, Clone, Default)]
+pub struct HarmonicGroups {
+    pub window_cycles: usize,              // Cycles in the window (10 at 50 Hz, 12 at 60 Hz)
+    pub harmonic_groups: Vec<f64>,         // G_g,n for orders 1..=num_harmonics
+    pub harmonic_subgroups: Vec<f64>,      // G_sg,n for orders 1..=num_harmonics
+    pub interharmonic_groups: Vec<f64>,    // C_ig,n between orders n and n+1, n = 0..num_harmonics-1
+    pub interharmonic_subgroups: Vec<f64>, // C_isg,n centred subgroup between orders n and n+1
+    pub thdg_percent: f64,                 // Group total harmonic distortion (THDG)
+    pub thds_percent: f64,                 // Subgroup total harmonic distortion (THDS)
+}
+
+impl HarmonicGroups {
+    pub fn into_proto(self) -> metrology_proto::metrology_insight::HarmonicGroups {
+        metrology_proto::metrology_insight::HarmonicGroups {
+            window_cycles: self.window_cycles as u32,
+            harmonic_groups: self.harmonic_groups,
+            harmonic_subgroups: self.harmonic_subgroups,
+            interharmonic_groups: self.interharmonic_groups,
+            interharmonic_subgroups: self.interharmonic_subgroups,
+            thdg_percent: self.thdg_percent,
+            thds_percent: self.thds_percent,
         }
     }
 }
@@ -174,12 +606,32 @@ impl PhaseDirection {
     }
 }
 
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PhaseAngleMethod {
+    SpectralPhasor, // Fundamental DFT bin of each signal
+    ZeroCrossing,   // Interpolated rising zero crossings (fallback)
+    #[default]
+    None, // Angles could not be computed
+}
+
+impl PhaseAngleMethod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PhaseAngleMethod::SpectralPhasor => "Spectral phasor",
+            PhaseAngleMethod::ZeroCrossing => "Zero crossing",
+            PhaseAngleMethod::None => "None",
+        }
+    }
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct PhaseAngleMetrics {
-    pub c2v_angle: f64,            // Current-to-voltage phase difference (signed)
+    pub c2v_angle: f64,            // Current-to-voltage phase difference (signed, lagging > 0)
     pub v_angle: f64,              // Absolute voltage angle (0-360°)
     pub c_angle: f64,              // Absolute current angle (0-360°)
     pub direction: PhaseDirection, // Phase direction (inductive, capacitive, in-phase)
+    pub harmonic_angles: Vec<f64>, // Signed V-I angle per harmonic, from the fundamental (NaN if too weak)
+    pub method: PhaseAngleMethod,  // Method that produced the angles
 }
 
 impl PhaseAngleMetrics {
@@ -193,6 +645,8 @@ impl PhaseAngleMetrics {
             v_angle: self.v_angle,
             c_angle: self.c_angle,
             direction: self.direction.as_str().to_string(),
+            harmonic_angles: self.harmonic_angles,
+            method: self.method.as_str().to_string(),
         }
     }
 }
@@ -216,8 +670,43 @@ impl PowerMetrics {
     }
 }
 
+/// Power components defined by IEEE 1459 for non-sinusoidal single-phase systems.
+#[derive(Debug, Clone, Default)]
+pub struct PowerDecomposition {
+    pub fundamental_active_power: f64,       // P1 (W)
+    pub fundamental_reactive_power: f64,     // Q1 (var), positive when inductive
+    pub fundamental_apparent_power: f64,     // S1 = V1·I1 (VA)
+    pub harmonic_active_power: f64,          // PH = Σ Vh·Ih·cos θh for h > 1 (W)
+    pub current_distortion_power: f64,       // DI = V1·IH (var)
+    pub voltage_distortion_power: f64,       // DV = VH·I1 (var)
+    pub harmonic_apparent_power: f64,        // SH = VH·IH (VA)
+    pub non_fundamental_apparent_power: f64, // SN = sqrt(DI² + DV² + SH²) (VA)
+    pub nonactive_power: f64,                // N = sqrt(S² - P²) (var)
+    pub displacement_power_factor: f64,      // cos φ1 = P1/S1
+    pub distortion_factor: f64,              // S1/S
+}
+
+impl PowerDecomposition {
+    pub fn into_proto(self) -> metrology_proto::metrology_insight::PowerDecomposition {
+        metrology_proto::metrology_insight::PowerDecomposition {
+            fundamental_active_power: self.fundamental_active_power,
+            fundamental_reactive_power: self.fundamental_reactive_power,
+            fundamental_apparent_power: self.fundamental_apparent_power,
+            harmonic_active_power: self.harmonic_active_power,
+            current_distortion_power: self.current_distortion_power,
+            voltage_distortion_power: self.voltage_distortion_power,
+            harmonic_apparent_power: self.harmonic_apparent_power,
+            non_fundamental_apparent_power: self.non_fundamental_apparent_power,
+            nonactive_power: self.nonactive_power,
+            displacement_power_factor: self.displacement_power_factor,
+            distortion_factor: self.distortion_factor,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct ActiveEnergyMetrics {
+    pub registers: [EnergyRegister; 4], // Registers of Q1 to Q4 (Ws); the kWh fields are views of them
     pub imported: f64,
     pub exported: f64,
     pub balance: f64,
@@ -254,6 +743,7 @@ impl ActiveEnergyMetrics {
 
     pub fn into_proto(self) -> metrology_proto::metrology_insight::ActiveEnergyMetrics {
         metrology_proto::metrology_insight::ActiveEnergyMetrics {
+            registers_ws: self.registers.iter().map(|r| r.ws()).collect(),
             imported: self.imported,
             exported: self.exported,
             balance: self.balance,
@@ -267,6 +757,7 @@ impl ActiveEnergyMetrics {
 
 #[derive(Debug, Clone, Default)]
 pub struct ReactiveEnergyMetrics {
+    pub registers: [EnergyRegister; 4], // Registers of Q1 to Q4 (vars); the kvarh fields are views of them
     pub capacitive: f64,
     pub inductive: f64,
     pub balance: f64,
@@ -303,6 +794,7 @@ impl ReactiveEnergyMetrics {
 
     pub fn into_proto(self) -> metrology_proto::metrology_insight::ReactiveEnergyMetrics {
         metrology_proto::metrology_insight::ReactiveEnergyMetrics {
+            registers_ws: self.registers.iter().map(|r| r.ws()).collect(),
             capacitive: self.capacitive,
             inductive: self.inductive,
             balance: self.balance,
@@ -314,10 +806,72 @@ impl ReactiveEnergyMetrics {
     }
 }
 
+/// Energy registered by direction of the power flow: apparent, fundamental or harmonic energy.
+#[derive(Debug, Clone, Default)]
+pub struct DirectionalEnergyMetrics {
+    pub registers: [EnergyRegister; 2], // Imported and exported registers (Ws or VAs); the fields below are views
+    pub imported: f64,
+    pub exported: f64,
+    pub balance: f64,
+}
+
+impl DirectionalEnergyMetrics {
+    /*
+     * @brief Imported energy, in kWh (kVAh for apparent energy).
+     */
+    pub fn imported(&self) -> f64 {
+        self.registers[0].kwh()
+    }
+
+    /*
+     * @brief Exported energy, in kWh (kVAh for apparent energy).
+     */
+    pub fn exported(&self) -> f64 {
+        self.registers[1].kwh()
+    }
+
+    pub fn balance(&self) -> f64 {
+        self.imported() - self.exported()
+    }
+
+    pub fn into_proto(self) -> metrology_proto::metrology_insight::DirectionalEnergyMetrics {
+        metrology_proto::metrology_insight::DirectionalEnergyMetrics {
+            registers_ws: self.registers.iter().map(|r| r.ws()).collect(),
+            imported: self.imported,
+            exported: self.exported,
+            balance: self.balance,
+        }
+    }
+}
+
+/// Time accounted by the energy registers since start-up.
+#[derive(Debug, Clone, Default)]
+pub struct EnergyCoverage {
+    pub covered_sec: f64,   // Time covered by the samples of the processed frames (s)
+    pub bridged_sec: f64,   // Gaps registered at the power of the frame before them (s)
+    pub uncovered_sec: f64, // Gaps without registered energy (s)
+    pub gaps: u64,          // Gaps detected between frames
+}
+
+impl EnergyCoverage {
+    pub fn into_proto(self) -> metrology_proto::metrology_insight::EnergyCoverage {
+        metrology_proto::metrology_insight::EnergyCoverage {
+            covered_sec: self.covered_sec,
+            bridged_sec: self.bridged_sec,
+            uncovered_sec: self.uncovered_sec,
+            gaps: self.gaps,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct EnergyMetrics {
     pub active: ActiveEnergyMetrics,
     pub reactive: ReactiveEnergyMetrics,
+    pub apparent: DirectionalEnergyMetrics, // Apparent energy (kVAh), by direction of the active power
+    pub fundamental: DirectionalEnergyMetrics, // Active energy of the fundamental (kWh), by direction of P1
+    pub harmonic: DirectionalEnergyMetrics, // Active energy of the harmonics (kWh), by direction of PH
+    pub coverage: EnergyCoverage,
 }
 
 impl EnergyMetrics {
@@ -325,6 +879,10 @@ impl EnergyMetrics {
         metrology_proto::metrology_insight::EnergyMetrics {
             active: Some(self.active.into_proto()),
             reactive: Some(self.reactive.into_proto()),
+            apparent: Some(self.apparent.into_proto()),
+            fundamental: Some(self.fundamental.into_proto()),
+            harmonic: Some(self.harmonic.into_proto()),
+            coverage: Some(self.coverage.into_proto()),
         }
     }
 }
@@ -0,0 +1,83 @@
+mod common;
+
+use common::{board_config, board_frames, tone, FRAME, FS};
+use metrology_insight::{AntiCreepConfig, CreepDetector, CreepState, MetrologyInsight};
+
+const FRAME_SEC: f64 = FRAME as f64 / FS;
+
+#[test]
+fn below_the_starting_current_the_meter_stays_stopped() {
+    let config = AntiCreepConfig::default();
+    let mut detector = CreepDetector::new();
+
+    // 15 mA a 230 V: por debajo de los 20 mA de arranque aunque la potencia supere 1 VA
+    for _ in 0..100 {
+        assert_eq!(detector.update(&config, 0.015, 3.45, FRAME_SEC), CreepState::NoLoad);
+    }
+}
+
+#[test]
+fn load_sustained_for_the_qualifying_time_starts_the_meter() {
+    let config = AntiCreepConfig {
+        qualify_sec: 0.25,
+        ..Default::default()
+    };
+    let mut detector = CreepDetector::new();
+
+    assert_eq!(detector.update(&config, 5.0, 1150.0, 0.125), CreepState::NoLoad);
+    assert_eq!(detector.update(&config, 5.0, 1150.0, 0.0625), CreepState::NoLoad);
+    assert_eq!(detector.update(&config, 5.0, 1150.0, 0.0625), CreepState::Running);
+
+    // Una interrupción reinicia la cuenta
+    detector.reset();
+    assert_eq!(detector.update(&config, 5.0, 1150.0, 0.125), CreepState::NoLoad);
+    assert_eq!(detector.update(&config, 0.0, 0.0, 0.02), CreepState::NoLoad);
+    assert_eq!(detector.update(&config, 5.0, 1150.0, 0.125), CreepState::NoLoad);
+}
+
+#[test]
+fn dropping_into_the_hysteresis_band_keeps_the_meter_running() {
+    let config = AntiCreepConfig::default();
+    let mut detector = CreepDetector::new();
+    detector.update(&config, 5.0, 1150.0, config.qualify_sec);
+    assert_eq!(detector.state(), CreepState::Running);
+
+    // 19 mA: por debajo del arranque (20 mA) pero sobre la parada (18 mA)
+    for _ in 0..100 {
+        assert_eq!(detector.update(&config, 0.019, 4.37, FRAME_SEC), CreepState::Running);
+    }
+
+    // 17 mA: se para al cabo del tiempo de cualificación
+    assert_eq!(detector.update(&config, 0.017, 3.91, 0.05), CreepState::Running);
+    assert_eq!(detector.update(&config, 0.017, 3.91, 0.05), CreepState::NoLoad);
+
+    // Y parado, 19 mA no bastan para volver a arrancar
+    for _ in 0..100 {
+        assert_eq!(detector.update(&config, 0.019, 4.37, FRAME_SEC), CreepState::NoLoad);
+    }
+}
+
+#[test]
+fn qualifying_time_runs_on_the_acquisition_time() {
+    let config = board_config();
+    let process = |insight: &mut MetrologyInsight, sequence| {
+        let (v, c) = board_frames(&config, sequence, &[tone(50.0, 230.0, 0.0)], &[tone(50.0, 5.0, 0.0)]);
+        insight.try_process_and_update_metrics(&v, &c).unwrap();
+        insight.socket.creep_state
+    };
+
+    // Tramas seguidas: 0,1 s son algo más de cinco tramas de 20 ms
+    let mut insight = MetrologyInsight::new(config.clone());
+    for sequence in 0..4 {
+        assert_eq!(process(&mut insight, sequence), CreepState::NoLoad);
+    }
+    for sequence in 6..10 {
+        process(&mut insight, sequence);
+    }
+    assert_eq!(insight.socket.creep_state, CreepState::Running);
+
+    // Con una trama cada 100 ms el tiempo entre tramas también cuenta
+    let mut insight = MetrologyInsight::new(config.clone());
+    assert_eq!(process(&mut insight, 0), CreepState::NoLoad);
+    assert_eq!(process(&mut insight, 5), CreepState::Running);
+}
@@ -0,0 +1,123 @@
+use metrology_insight::{
+    Calibration, ChannelCalibration, CorrectionCurve, CorrectionPoint, MetrologyError, TemperatureCompensation,
+};
+
+fn calibration() -> Calibration {
+    Calibration {
+        voltage: ChannelCalibration {
+            gain: 1.0123,
+            offset: 1876.4,
+            phase_deg: 0.35,
+            curve: CorrectionCurve::default(),
+            temperature: TemperatureCompensation {
+                reference: 23.0,
+                gain: vec![-5e-5, 1e-7],
+                phase: vec![0.002],
+            },
+        },
+        current: ChannelCalibration {
+            gain: 0.987,
+            offset: 1879.0,
+            phase_deg: -1.2,
+            curve: CorrectionCurve::new(vec![
+                CorrectionPoint {
+                    level: 0.5,
+                    ratio_error: -1.0,
+                    phase_error: 0.8,
+                },
+                CorrectionPoint {
+                    level: 10.0,
+                    ratio_error: -0.2,
+                    phase_error: 0.1,
+                },
+            ])
+            .unwrap(),
+            temperature: TemperatureCompensation::default(),
+        },
+    }
+}
+
+/*
+* @brief Replace the checksum of a calibration file by the one of its (edited) contents.
+*/
+fn resign(text: &str) -> String {
+    let body = &text[..text.rfind("crc32=").unwrap()];
+    let mut crc = 0xFFFF_FFFFu32;
+    for &byte in body.as_bytes() {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    format!("{}crc32={:08x}\n", body, !crc)
+}
+
+fn file_error(text: &str) -> String {
+    match Calibration::from_file_string(text) {
+        Err(MetrologyError::CalibrationFile(reason)) => reason,
+        other => panic!("expected a calibration file error, got {:?}", other),
+    }
+}
+
+#[test]
+fn calibration_file_round_trip() {
+    let calibration = calibration();
+    let text = calibration.to_file_string();
+    assert_eq!(Calibration::from_file_string(&text), Ok(calibration.clone()));
+
+    let path = std::env::temp_dir().join(format!("metrology_insight_calibration_{}.txt", std::process::id()));
+    calibration.save(&path).unwrap();
+    let loaded = Calibration::load(&path);
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(loaded, Ok(calibration));
+}
+
+#[test]
+fn edited_file_fails_the_checksum() {
+    let text = calibration()
+        .to_file_string()
+        .replace("voltage.gain=1.0123", "voltage.gain=1.0124");
+    assert_eq!(file_error(&text), "checksum mismatch");
+
+    // Con la suma recalculada el valor editado se acepta
+    assert_eq!(
+        Calibration::from_file_string(&resign(&text)).unwrap().voltage.gain,
+        1.0124
+    );
+}
+
+#[test]
+fn newer_version_is_rejected() {
+    let text = calibration().to_file_string().replace("version=1", "version=9");
+    assert_eq!(file_error(&resign(&text)), "unsupported version 9");
+}
+
+#[test]
+fn unknown_key_is_rejected() {
+    let text = calibration()
+        .to_file_string()
+        .replace("current.gain=", "current.burden_ohm=10\ncurrent.gain=");
+    assert_eq!(file_error(&resign(&text)), "unknown key: current.burden_ohm");
+
+    let text = calibration()
+        .to_file_string()
+        .replace("voltage.temp_reference", "voltage.temp_slope=1\nvoltage.temp_reference");
+    assert_eq!(file_error(&resign(&text)), "unknown key: voltage.temp_slope");
+}
+
+#[test]
+fn file_without_curves_or_temperature_is_read() {
+    let text = resign(
+        "# metrology_insight calibration\nversion=1\nvoltage.gain=1.01\nvoltage.offset=1877\nvoltage.phase_deg=0\n\
+         current.gain=0.99\ncurrent.offset=1878\ncurrent.phase_deg=-0.5\ncrc32=0\n",
+    );
+    let calibration = Calibration::from_file_string(&text).unwrap();
+
+    assert_eq!(calibration.current.phase_deg, -0.5);
+    assert!(calibration.current.curve.is_empty());
+    assert!(calibration.voltage.temperature.is_empty());
+}
@@ -0,0 +1,153 @@
+#![allow(dead_code)]
+
+use metrology_insight::{
+    AdcFrontEnd, ChannelConfig, MetrologyInsightConfig, MetrologyInsightSignal, MetrologyInsightSignalType,
+    SampleFrame, SensorModel,
+};
+use std::f64::consts::PI;
+
+pub const FS: f64 = 7812.5;
+pub const FRAME: usize = 156;
+
+/// Harmonic component of a test waveform.
+#[derive(Debug, Clone, Copy)]
+pub struct Tone {
+    pub freq: f64,      // Frequency (Hz)
+    pub rms: f64,       // RMS value
+    pub phase_deg: f64, // Phase of the sine at t = 0 (degrees)
+}
+
+pub fn tone(freq: f64, rms: f64, phase_deg: f64) -> Tone {
+    Tone { freq, rms, phase_deg }
+}
+
+/*
+* @brief Samples of a sum of sines.
+* @param tones Components of the waveform
+* @param offset DC level added to every sample
+* @param fs Sampling frequency in Hz
+* @param start Index of the first sample
+* @param len Number of samples
+*/
+pub fn waveform(tones: &[Tone], offset: f64, fs: f64, start: usize, len: usize) -> Vec<f64> {
+    (start..start + len)
+        .map(|n| {
+            let t = n as f64 / fs;
+            offset
+                + tones
+                    .iter()
+                    .map(|c| c.rms * 2f64.sqrt() * (2.0 * PI * c.freq * t + c.phase_deg.to_radians()).sin())
+                    .sum::<f64>()
+        })
+        .collect()
+}
+
+/*
+* @brief Socket signal holding a waveform in physical units, as left by the signal processing.
+* @param wave Samples of the signal
+* @param freq Fundamental frequency in Hz
+*/
+pub fn signal(wave: Vec<f64>, freq: f64) -> MetrologyInsightSignal {
+    let rms = (wave.iter().map(|x| x * x).sum::<f64>() / wave.len() as f64).sqrt();
+    MetrologyInsightSignal {
+        real_wave: wave,
+        rms,
+        freq_zc: freq,
+        freq_nominal: freq,
+        ..Default::default()
+    }
+}
+
+/*
+* @brief Frame of ADC codes, rounded as the ADC would.
+* @param channel Channel of the frame
+* @param samples Samples in ADC codes
+* @param sequence Sequence number; the timestamp follows from it and the frame length
+*/
+pub fn frame(channel: MetrologyInsightSignalType, samples: &[f64], sequence: u64) -> SampleFrame<i32> {
+    SampleFrame {
+        channel,
+        samples: samples.iter().map(|x| x.round() as i32).collect(),
+        sample_rate: FS,
+        timestamp_us: (sequence as f64 * samples.len() as f64 * 1e6 / FS).round() as u64,
+        sequence,
+    }
+}
+
+/*
+* @brief Configuration of the Milk-V Duo board: ZMPT101B and SCT013-030 on the 12-bit ADC, biased at mid-scale.
+*/
+pub fn board_config() -> MetrologyInsightConfig {
+    let frontend = AdcFrontEnd::milk_v_duo();
+    let mut config = MetrologyInsightConfig {
+        adc_samples_seconds: FS,
+        adc_max_code: frontend.max_code(),
+        ..Default::default()
+    };
+    SensorModel::zmpt101b()
+        .configure(&frontend, &mut config.voltage)
+        .unwrap();
+    SensorModel::sct013_030()
+        .configure(&frontend, &mut config.current)
+        .unwrap();
+    config
+}
+
+/*
+* @brief ADC codes of a waveform in physical units, inverting the conversion of a channel.
+* @param wave Samples in volts or amperes
+* @param channel Channel the samples are read from
+* @param signal_type Type of the signal (the current channel also applies adc_scale)
+*/
+pub fn to_codes(wave: &[f64], channel: &ChannelConfig, signal_type: MetrologyInsightSignalType) -> Vec<f64> {
+    let units_per_code = match signal_type {
+        MetrologyInsightSignalType::Voltage => channel.adc_factor,
+        MetrologyInsightSignalType::Current => channel.adc_factor * channel.adc_scale,
+    } * channel.transformer.ratio();
+
+    wave.iter()
+        .map(|x| channel.calibration.offset + x / units_per_code)
+        .collect()
+}
+
+/*
+* @brief Voltage and current frames of the board, continuous from one sequence number to the next.
+* @param config Configuration the codes are generated for
+* @param sequence Sequence number of the frames
+* @param voltage Components of the voltage (V)
+* @param current Components of the current (A)
+*/
+pub fn board_frames(
+    config: &MetrologyInsightConfig,
+    sequence: u64,
+    voltage: &[Tone],
+    current: &[Tone],
+) -> (SampleFrame<i32>, SampleFrame<i32>) {
+    let start = sequence as usize * FRAME;
+    let v = waveform(voltage, 0.0, FS, start, FRAME);
+    let i = waveform(current, 0.0, FS, start, FRAME);
+
+    (
+        frame(
+            MetrologyInsightSignalType::Voltage,
+            &to_codes(&v, &config.voltage, MetrologyInsightSignalType::Voltage),
+            sequence,
+        ),
+        frame(
+            MetrologyInsightSignalType::Current,
+            &to_codes(&i, &config.current, MetrologyInsightSignalType::Current),
+            sequence,
+        ),
+    )
+}
+
+pub fn assert_close(actual: f64, expected: f64, tolerance: f64, what: &str) {
+    assert!(
+        (actual - expected).abs() <= tolerance,
+        "{}: {} (expected {} ± {})",
+        what,
+        actual,
+        expected,
+        tolerance
+    );
+}
@@ -0,0 +1,72 @@
+mod common;
+
+use common::assert_close;
+use metrology_insight::{ChannelCalibration, CorrectionCurve, CorrectionPoint, MetrologyError};
+
+fn point(level: f64, ratio_error: f64, phase_error: f64) -> CorrectionPoint {
+    CorrectionPoint {
+        level,
+        ratio_error,
+        phase_error,
+    }
+}
+
+/*
+* @brief Errors of a current transformer, given out of order.
+*/
+fn curve() -> CorrectionCurve {
+    CorrectionCurve::new(vec![
+        point(10.0, -0.2, 0.1),
+        point(0.5, -1.0, 0.8),
+        point(5.0, -0.5, 0.3),
+    ])
+    .unwrap()
+}
+
+#[test]
+fn errors_are_interpolated_between_points() {
+    let curve = curve();
+    let levels: Vec<f64> = curve.points().iter().map(|p| p.level).collect();
+    assert_eq!(levels, [0.5, 5.0, 10.0]);
+
+    let (ratio, phase) = curve.errors_at(2.75);
+    assert_close(ratio, -0.75, 1e-12, "amplitude error halfway");
+    assert_close(phase, 0.55, 1e-12, "phase error halfway");
+
+    let (ratio, phase) = curve.errors_at(5.0);
+    assert_close(ratio, -0.5, 1e-12, "amplitude error at a point");
+    assert_close(phase, 0.3, 1e-12, "phase error at a point");
+
+    // Fuera de la tabla se mantienen los errores del extremo
+    assert_eq!(curve.errors_at(0.1), (-1.0, 0.8));
+    assert_eq!(curve.errors_at(40.0), (-0.2, 0.1));
+    assert_eq!(CorrectionCurve::default().errors_at(5.0), (0.0, 0.0));
+}
+
+#[test]
+fn calibration_applies_the_curve_at_the_level() {
+    let calibration = ChannelCalibration {
+        gain: 1.02,
+        phase_deg: 0.5,
+        curve: curve(),
+        ..Default::default()
+    };
+
+    assert_close(calibration.gain_at(2.75, None), 1.02 / (1.0 - 0.0075), 1e-12, "gain");
+    assert_close(calibration.phase_at(2.75, None), 0.5 + 0.55, 1e-12, "phase");
+}
+
+#[test]
+fn invalid_points_are_rejected() {
+    for points in [
+        vec![point(1.0, 0.0, 0.0), point(1.0, 0.1, 0.0)],
+        vec![point(-1.0, 0.0, 0.0)],
+        vec![point(1.0, -100.0, 0.0)],
+        vec![point(1.0, 0.0, f64::NAN)],
+    ] {
+        assert_eq!(
+            CorrectionCurve::new(points),
+            Err(MetrologyError::InvalidParameter("correction point"))
+        );
+    }
+}
@@ -0,0 +1,100 @@
+mod common;
+
+use common::{board_config, frame, to_codes, tone, waveform, FS};
+use metrology_insight::{CycleSegmenter, MetrologyInsightConfig, MetrologyInsightSignalType, SampleFrame, StreamCycle};
+
+const CHUNK: usize = 100; // Frames shorter than a cycle, so that cycles span several of them
+
+/*
+* @brief Pair of board frames of a 50 Hz load, cut in chunks that do not follow the cycles.
+*/
+fn chunk(config: &MetrologyInsightConfig, sequence: u64) -> (SampleFrame<i32>, SampleFrame<i32>) {
+    let start = sequence as usize * CHUNK;
+    let v = waveform(&[tone(50.0, 230.0, 90.0)], 0.0, FS, start, CHUNK);
+    let i = waveform(&[tone(50.0, 5.0, 60.0)], 0.0, FS, start, CHUNK);
+
+    (
+        frame(
+            MetrologyInsightSignalType::Voltage,
+            &to_codes(&v, &config.voltage, MetrologyInsightSignalType::Voltage),
+            sequence,
+        ),
+        frame(
+            MetrologyInsightSignalType::Current,
+            &to_codes(&i, &config.current, MetrologyInsightSignalType::Current),
+            sequence,
+        ),
+    )
+}
+
+fn push(
+    segmenter: &mut CycleSegmenter,
+    config: &MetrologyInsightConfig,
+    sequences: impl Iterator<Item = u64>,
+) -> Vec<StreamCycle> {
+    let mut cycles = Vec::new();
+    for sequence in sequences {
+        let (v, c) = chunk(config, sequence);
+        segmenter.push(&v, &c, config).unwrap();
+        while let Some(cycle) = segmenter.next_cycle(config) {
+            cycles.push(cycle);
+        }
+    }
+    cycles
+}
+
+#[test]
+fn unaligned_frames_are_cut_in_back_to_back_cycles() {
+    let config = board_config();
+    let mut segmenter = CycleSegmenter::new();
+    let cycles = push(&mut segmenter, &config, 0..40);
+
+    // 4000 muestras = 25,6 ciclos; el último queda en el buffer hasta el siguiente cruce
+    assert!(cycles.len() >= 23, "{} cycles", cycles.len());
+    assert!(!cycles[0].contiguous);
+
+    for pair in cycles.windows(2) {
+        let (previous, cycle) = (&pair[0], &pair[1]);
+        assert!(cycle.contiguous);
+        assert_eq!(cycle.sequence, previous.sequence + 1);
+
+        // Cada ciclo empieza donde acaba el anterior
+        let elapsed_us = previous.voltage.len() as f64 * 1e6 / FS;
+        assert!((cycle.timestamp_us as f64 - previous.timestamp_us as f64 - elapsed_us).abs() <= 1.0);
+    }
+
+    for cycle in &cycles {
+        // 7812,5 Hz / 50 Hz = 156,25 muestras por ciclo
+        assert!(cycle.voltage.len() == 156 || cycle.voltage.len() == 157);
+        assert_eq!(cycle.voltage.len(), cycle.current.len());
+
+        // Arranca en un cruce ascendente del punto medio
+        let midpoint = config.voltage.calibration.offset;
+        assert!(cycle.voltage[0] >= midpoint && cycle.voltage[cycle.voltage.len() - 1] < midpoint);
+    }
+}
+
+#[test]
+fn sequence_gap_resets_the_buffer() {
+    let config = board_config();
+    let mut segmenter = CycleSegmenter::new();
+    let before = push(&mut segmenter, &config, 0..10);
+    assert!(!before.is_empty());
+    assert!(!segmenter.is_empty());
+
+    // Falta la trama 10: las muestras pendientes se descartan y solo queda la trama 11
+    let (v, c) = chunk(&config, 11);
+    segmenter.push(&v, &c, &config).unwrap();
+    assert_eq!(segmenter.len(), CHUNK);
+
+    let mut after = Vec::new();
+    while let Some(cycle) = segmenter.next_cycle(&config) {
+        after.push(cycle);
+    }
+    after.extend(push(&mut segmenter, &config, 12..30));
+
+    assert!(!after[0].contiguous, "the first cycle after the gap is flagged");
+    assert!(after[1..].iter().all(|cycle| cycle.contiguous));
+    assert!(after[0].timestamp_us >= v.timestamp_us);
+    assert_eq!(after[0].sequence, before.last().unwrap().sequence + 1);
+}
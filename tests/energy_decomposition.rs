@@ -0,0 +1,67 @@
+mod common;
+
+use common::{assert_close, board_config, board_frames, tone, Tone};
+use metrology_insight::{EnergyRegister, MetrologyInsight, MetrologyInsightSocket};
+
+/*
+* @brief Socket after one second of a load on the board.
+*/
+fn run(voltage: &[Tone], current: &[Tone]) -> MetrologyInsightSocket {
+    let config = board_config();
+    let mut insight = MetrologyInsight::new(config.clone());
+    for sequence in 0..50 {
+        let (v, c) = board_frames(&config, sequence, voltage, current);
+        insight.try_process_and_update_metrics(&v, &c).unwrap();
+    }
+    insight.socket
+}
+
+/*
+* @brief Energy of a register, fractions included (Ws).
+*/
+fn ws(register: &EnergyRegister) -> f64 {
+    register.ws() as f64 + register.remainder()
+}
+
+#[test]
+fn fundamental_and_harmonic_energy_add_up_to_active_energy() {
+    // El 5º armónico de la corriente está en oposición: PH ≈ -10 W fluye contra P1 ≈ 1150 W
+    let socket = run(
+        &[tone(50.0, 230.0, 0.0), tone(250.0, 10.0, 0.0)],
+        &[tone(50.0, 5.0, 0.0), tone(250.0, 1.0, 180.0)],
+    );
+    let energy = &socket.energy_metrics;
+
+    let [q1, q2, q3, q4] = energy.active.registers.each_ref().map(ws);
+    let active = q1 + q4 - q2 - q3;
+    let fundamental = ws(&energy.fundamental.registers[0]) - ws(&energy.fundamental.registers[1]);
+    let harmonic = ws(&energy.harmonic.registers[0]) - ws(&energy.harmonic.registers[1]);
+
+    assert!(active > 0.0);
+    assert!(ws(&energy.harmonic.registers[0]) == 0.0 && harmonic < 0.0);
+    // P se integra sobre 156 muestras de un ciclo de 156,25 y P1, PH sobre el ciclo exacto: hasta un 0,2 %
+    assert_close(fundamental + harmonic, active, active * 5e-3, "P1 + PH energy");
+    assert_close(harmonic / fundamental, -10.0 / 1150.0, 1e-3, "PH over P1");
+}
+
+#[test]
+fn apparent_energy_follows_the_direction_of_active_power() {
+    let imported = run(&[tone(50.0, 230.0, 0.0)], &[tone(50.0, 5.0, 30.0)]);
+    let exported = run(&[tone(50.0, 230.0, 0.0)], &[tone(50.0, 5.0, 210.0)]);
+
+    let apparent = &imported.energy_metrics.apparent;
+    assert!(ws(&apparent.registers[0]) > 0.0);
+    assert_eq!(ws(&apparent.registers[1]), 0.0);
+
+    let apparent = &exported.energy_metrics.apparent;
+    assert_eq!(ws(&apparent.registers[0]), 0.0);
+    assert!(ws(&apparent.registers[1]) > 0.0);
+
+    // Misma carga en sentido contrario: misma energía aparente
+    assert_close(
+        ws(&exported.energy_metrics.apparent.registers[1]),
+        ws(&imported.energy_metrics.apparent.registers[0]),
+        ws(&imported.energy_metrics.apparent.registers[0]) * 1e-3,
+        "apparent energy",
+    );
+}
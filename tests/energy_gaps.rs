@@ -0,0 +1,178 @@
+mod common;
+
+use common::{assert_close, board_config, board_frames, tone, FRAME, FS};
+use metrology_insight::{
+    update_total_energy, CreepState, EnergyGapConfig, EnergyGapPolicy, EnergyTimeBase, MetrologyInsight,
+    MetrologyInsightConfig, MetrologyInsightSocket,
+};
+
+const POWER: f64 = 1000.0; // W
+const DURATION: f64 = FRAME as f64 / FS; // 19,968 ms
+
+/// How the frames tell their acquisition time.
+#[derive(Clone, Copy)]
+enum Clock {
+    Timestamp, // Timestamps of the acquisition
+    Sequence,  // Timestamps stuck at 0: only the sequence numbers advance
+}
+
+fn config(policy: EnergyGapPolicy) -> MetrologyInsightConfig {
+    MetrologyInsightConfig {
+        adc_samples_seconds: FS,
+        energy_gaps: EnergyGapConfig {
+            policy,
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
+
+/*
+* @brief Socket of a running meter with a constant resistive load and frames of FRAME samples.
+*/
+fn loaded_socket() -> MetrologyInsightSocket {
+    let mut socket = MetrologyInsightSocket {
+        creep_state: CreepState::Running,
+        ..Default::default()
+    };
+    socket.power_metrics.real_power = POWER;
+    socket.power_metrics.apparent_power = POWER;
+    socket.voltage_signal.real_wave = vec![0.0; FRAME];
+    socket
+}
+
+/*
+* @brief Register a run of frames of a constant load.
+* @param config Pointer to the MetrologyInsightConfig structure.
+* @param sequences Sequence numbers of the frames that reach the registers
+* @param clock Source of the acquisition time
+* @return Socket after the last frame
+*/
+fn register(config: &MetrologyInsightConfig, sequences: &[u64], clock: Clock) -> MetrologyInsightSocket {
+    let mut socket = loaded_socket();
+    let mut time_base = EnergyTimeBase::new();
+
+    for &sequence in sequences {
+        socket.voltage_signal.sequence = sequence;
+        socket.voltage_signal.timestamp_us = match clock {
+            Clock::Timestamp => (sequence as f64 * DURATION * 1e6).round() as u64,
+            Clock::Sequence => 0,
+        };
+        update_total_energy(&mut socket, &mut time_base, config);
+    }
+    socket
+}
+
+/*
+* @brief Active energy registered in the four quadrants, fractions included (Ws).
+*/
+fn active_ws(socket: &MetrologyInsightSocket) -> f64 {
+    socket
+        .energy_metrics
+        .active
+        .registers
+        .iter()
+        .map(|r| r.ws() as f64 + r.remainder())
+        .sum()
+}
+
+#[test]
+fn bridged_gap_is_registered_at_the_last_power() {
+    for clock in [Clock::Timestamp, Clock::Sequence] {
+        // Faltan las tramas 3 y 4
+        let socket = register(&config(EnergyGapPolicy::Bridge), &[0, 1, 2, 5], clock);
+        let coverage = &socket.energy_metrics.coverage;
+
+        assert_eq!(coverage.gaps, 1);
+        assert_close(coverage.covered_sec, 4.0 * DURATION, 1e-6, "covered time");
+        assert_close(coverage.bridged_sec, 2.0 * DURATION, 1e-6, "bridged time");
+        assert_eq!(coverage.uncovered_sec, 0.0);
+        assert_close(active_ws(&socket), POWER * 6.0 * DURATION, 1e-3, "active energy");
+    }
+}
+
+#[test]
+fn skipped_gap_is_counted_as_uncovered() {
+    for clock in [Clock::Timestamp, Clock::Sequence] {
+        let socket = register(&config(EnergyGapPolicy::Skip), &[0, 1, 2, 5], clock);
+        let coverage = &socket.energy_metrics.coverage;
+
+        assert_eq!(coverage.gaps, 1);
+        assert_close(coverage.covered_sec, 4.0 * DURATION, 1e-6, "covered time");
+        assert_eq!(coverage.bridged_sec, 0.0);
+        assert_close(coverage.uncovered_sec, 2.0 * DURATION, 1e-6, "uncovered time");
+        assert_close(active_ws(&socket), POWER * 4.0 * DURATION, 1e-3, "active energy");
+    }
+}
+
+#[test]
+fn long_gap_is_never_bridged() {
+    // 100 tramas perdidas, unos 2 s, por encima de max_bridge_sec
+    let socket = register(&config(EnergyGapPolicy::Bridge), &[0, 101], Clock::Timestamp);
+    let coverage = &socket.energy_metrics.coverage;
+
+    assert_eq!(coverage.gaps, 1);
+    assert_eq!(coverage.bridged_sec, 0.0);
+    assert_close(coverage.uncovered_sec, 100.0 * DURATION, 1e-6, "uncovered time");
+    assert_close(active_ws(&socket), POWER * 2.0 * DURATION, 1e-3, "active energy");
+}
+
+#[test]
+fn timestamp_jitter_is_not_a_gap() {
+    let config = config(EnergyGapPolicy::Skip);
+    let mut socket = loaded_socket();
+    let mut time_base = EnergyTimeBase::new();
+
+    // Cada trama llega 5 ms tarde respecto al final de la anterior, dentro de la tolerancia de 10 ms
+    for frame in 0..10u64 {
+        socket.voltage_signal.sequence = frame;
+        socket.voltage_signal.timestamp_us = (frame as f64 * (DURATION + 0.005) * 1e6).round() as u64;
+        update_total_energy(&mut socket, &mut time_base, &config);
+    }
+
+    let coverage = &socket.energy_metrics.coverage;
+    assert_eq!(coverage.gaps, 0);
+    assert_eq!(coverage.uncovered_sec, 0.0);
+    assert_close(active_ws(&socket), POWER * 10.0 * DURATION, 1e-3, "active energy");
+}
+
+#[test]
+fn bridge_is_the_default_policy() {
+    assert_eq!(EnergyGapPolicy::default(), EnergyGapPolicy::Bridge);
+}
+
+/*
+* @brief Stream a constant load through push_samples.
+* @param acquired Frames acquired by the board, by their position in the signal
+* @return Instance after the last frame
+* @note The sequence numbers count the frames delivered, as a driver that does not know it lost one would.
+*/
+fn stream(acquired: impl Iterator<Item = u64>) -> MetrologyInsight {
+    let config = board_config();
+    let mut insight = MetrologyInsight::new(config.clone());
+
+    for (delivered, position) in acquired.enumerate() {
+        let (mut v, mut c) = board_frames(&config, position, &[tone(50.0, 230.0, 0.0)], &[tone(50.0, 5.0, 0.0)]);
+        v.sequence = delivered as u64;
+        c.sequence = delivered as u64;
+        insight.push_samples(&v, &c).unwrap();
+    }
+    insight
+}
+
+#[test]
+fn frame_lost_in_the_stream_is_a_gap() {
+    // Sin pérdidas no hay huecos
+    let insight = stream(0..40);
+    assert_eq!(insight.socket.energy_metrics.coverage.gaps, 0);
+
+    // Se pierde la trama 20: la secuencia sigue, pero su timestamp no
+    let insight = stream((0..20).chain(21..40));
+    let coverage = &insight.socket.energy_metrics.coverage;
+    assert!(coverage.gaps >= 1, "gaps = {}", coverage.gaps);
+    assert!(
+        coverage.uncovered_sec + coverage.bridged_sec >= DURATION,
+        "{:?}",
+        coverage
+    );
+}
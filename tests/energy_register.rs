@@ -0,0 +1,60 @@
+use metrology_insight::{register_rollover, EnergyRegister, MAX_REGISTER_DIGITS, REGISTER_DIGITS, WS_PER_KWH};
+
+#[test]
+fn small_increments_add_up_through_the_remainder() {
+    // Un millón de tramas de 20 ms a 1 W: 0,02 Ws cada una, sin llegar nunca a un Ws por sí solas
+    let mut register = EnergyRegister::new();
+    for _ in 0..1_000_000 {
+        assert!(!register.add(0.02, 0));
+    }
+
+    assert!((register.ws() as f64 + register.remainder() - 20_000.0).abs() < 1e-6);
+    assert!(register.ws() == 19_999 || register.ws() == 20_000);
+    assert!((0.0..1.0).contains(&register.remainder()));
+}
+
+#[test]
+fn increments_stay_exact_on_a_large_register() {
+    // Cerca de 10^12 kWh un f64 ya no distingue 0,02 Ws; el registro entero sí
+    let start = register_rollover(MAX_REGISTER_DIGITS) - 10 * WS_PER_KWH;
+    let mut register = EnergyRegister::from_ws(start);
+    for _ in 0..500 {
+        register.add(0.02, 0);
+    }
+
+    assert_eq!(register.ws(), start + 10);
+}
+
+#[test]
+fn invalid_increments_are_ignored() {
+    let mut register = EnergyRegister::from_ws(5);
+    for energy in [-1.0, f64::NAN, f64::INFINITY, 0.0] {
+        assert!(!register.add(energy, 0));
+    }
+    assert_eq!(register, EnergyRegister::from_ws(5));
+}
+
+#[test]
+fn register_wraps_at_its_digits() {
+    let rollover = register_rollover(REGISTER_DIGITS);
+    assert_eq!(rollover, 1_000_000 * WS_PER_KWH);
+
+    // 999999,5 kWh más 1 kWh: 0,5 kWh tras el rebose
+    let mut register = EnergyRegister::from_ws(rollover - WS_PER_KWH / 2);
+    assert!(register.add(WS_PER_KWH as f64, rollover));
+    assert_eq!(register.ws(), WS_PER_KWH / 2);
+    assert_eq!(register.kwh(), 0.5);
+
+    assert!(!register.add(WS_PER_KWH as f64, rollover));
+    assert_eq!(register.kwh(), 1.5);
+}
+
+#[test]
+fn rollover_digits_are_clamped() {
+    assert_eq!(register_rollover(0), 0);
+    assert_eq!(
+        register_rollover(MAX_REGISTER_DIGITS + 5),
+        register_rollover(MAX_REGISTER_DIGITS)
+    );
+    assert_eq!(register_rollover(u32::MAX), 10u64.pow(MAX_REGISTER_DIGITS) * WS_PER_KWH);
+}
@@ -0,0 +1,76 @@
+mod common;
+
+use common::{board_config, board_frames, tone};
+use metrology_insight::{MetrologyError, MetrologyInsight, MetrologyInsightSignalType, SampleFrame};
+
+fn running_insight() -> MetrologyInsight {
+    let config = board_config();
+    let mut insight = MetrologyInsight::new(config.clone());
+    for sequence in 0..5 {
+        let (v, c) = board_frames(&config, sequence, &[tone(50.0, 230.0, 0.0)], &[tone(50.0, 5.0, 0.0)]);
+        insight.try_process_and_update_metrics(&v, &c).unwrap();
+    }
+    insight
+}
+
+#[test]
+fn swapped_channels_are_rejected() {
+    let config = board_config();
+    let mut insight = MetrologyInsight::new(config.clone());
+    let (v, c) = board_frames(&config, 0, &[tone(50.0, 230.0, 0.0)], &[tone(50.0, 5.0, 0.0)]);
+
+    assert_eq!(
+        insight.try_process_and_update_metrics(&c, &v),
+        Err(MetrologyError::InvalidParameter("channel"))
+    );
+}
+
+#[test]
+fn missing_voltage_keeps_the_last_metrics() {
+    let mut insight = running_insight();
+    let power = insight.socket.power_metrics.real_power;
+    let config = insight.config.clone();
+
+    let (v, c) = board_frames(&config, 5, &[], &[tone(50.0, 5.0, 0.0)]);
+    let result = insight.try_process_and_update_metrics(&v, &c);
+
+    match result {
+        Err(MetrologyError::SignalTooLow { signal_type, .. }) => {
+            assert_eq!(signal_type, MetrologyInsightSignalType::Voltage)
+        }
+        other => panic!("expected SignalTooLow, got {:?}", other),
+    }
+    assert_eq!(insight.socket.power_metrics.real_power, power);
+}
+
+#[test]
+fn frame_at_another_sample_rate_is_rejected() {
+    let mut insight = running_insight();
+    let config = insight.config.clone();
+    let (mut v, mut c) = board_frames(&config, 5, &[tone(50.0, 230.0, 0.0)], &[tone(50.0, 5.0, 0.0)]);
+    v.sample_rate = 8000.0;
+    c.sample_rate = 8000.0;
+
+    assert_eq!(
+        insight.try_process_and_update_metrics(&v, &c),
+        Err(MetrologyError::InvalidParameter("sample_rate"))
+    );
+}
+
+#[test]
+fn frames_of_different_length_are_rejected() {
+    let mut insight = running_insight();
+    let v = SampleFrame::new(MetrologyInsightSignalType::Voltage, vec![0i32; 100], common::FS);
+    let c = SampleFrame::new(MetrologyInsightSignalType::Current, vec![0i32; 90], common::FS);
+
+    let error = insight.push_samples(&v, &c).unwrap_err();
+    assert_eq!(
+        error,
+        MetrologyError::LengthMismatch {
+            voltage: 100,
+            current: 90
+        }
+    );
+    assert_eq!(error.as_str(), "Length mismatch");
+    assert_eq!(error.to_string(), "voltage and current lengths differ: 100 vs 90");
+}
@@ -0,0 +1,79 @@
+mod common;
+
+use common::{assert_close, tone, waveform, FS};
+use metrology_insight::{
+    filter_compensation, FilterChain, FilterKind, MetrologyInsightConfig, MetrologyInsightSignalType,
+    MetrologyInsightSocket,
+};
+
+const LEN: usize = 1560;
+
+fn config() -> MetrologyInsightConfig {
+    MetrologyInsightConfig {
+        adc_samples_seconds: FS,
+        ..Default::default()
+    }
+}
+
+fn socket() -> MetrologyInsightSocket {
+    let mut socket = MetrologyInsightSocket::default();
+    socket.voltage_signal.freq_nominal = 50.0;
+    socket.current_signal.freq_nominal = 50.0;
+    socket
+}
+
+fn compensation(config: &MetrologyInsightConfig, signal_type: MetrologyInsightSignalType) -> f64 {
+    filter_compensation(config, &socket(), signal_type).unwrap()
+}
+
+#[test]
+fn later_sampled_current_is_aligned_with_the_voltage() {
+    // La corriente se muestrea medio periodo de muestreo después que la tensión
+    let mut config = config();
+    config.current.skew_us = 0.5e6 / FS;
+
+    let voltage = compensation(&config, MetrologyInsightSignalType::Voltage);
+    let current = compensation(&config, MetrologyInsightSignalType::Current);
+    assert_close(current - voltage, 0.5, 1e-9, "extra delay of the current");
+    assert!(voltage >= 1.0, "the interpolator is kept centred");
+
+    let mut v = waveform(&[tone(50.0, 230.0, 0.0)], 0.0, FS, 0, LEN);
+    let skew_deg = 360.0 * 50.0 * config.current.skew_us * 1e-6;
+    let mut i = waveform(&[tone(50.0, 230.0, skew_deg)], 0.0, FS, 0, LEN);
+
+    let mut voltage_chain = FilterChain::new();
+    let mut current_chain = FilterChain::new();
+    voltage_chain.configure(&config.voltage.filters, voltage).unwrap();
+    current_chain.configure(&config.current.filters, current).unwrap();
+    voltage_chain.process(&mut v);
+    current_chain.process(&mut i);
+
+    for (v, i) in v.iter().zip(&i).skip(LEN / 2) {
+        assert_close(*i, *v, 0.05, "aligned sample");
+    }
+}
+
+#[test]
+fn calibrated_phase_lead_is_delayed() {
+    // 1,8° a 50 Hz son 0,1 ms, 0,78125 muestras
+    let mut config = config();
+    config.current.calibration.phase_deg = 1.8;
+
+    let voltage = compensation(&config, MetrologyInsightSignalType::Voltage);
+    let current = compensation(&config, MetrologyInsightSignalType::Current);
+    assert_close(current - voltage, 0.78125, 1e-9, "extra delay of the current");
+}
+
+#[test]
+fn filter_chain_of_one_channel_delays_the_other() {
+    let mut config = config();
+    config.voltage.filters = vec![FilterKind::MovingAverage { window: 5 }];
+
+    assert_eq!(compensation(&config, MetrologyInsightSignalType::Voltage), 0.0);
+    assert_close(
+        compensation(&config, MetrologyInsightSignalType::Current),
+        2.0,
+        1e-9,
+        "delay of the current",
+    );
+}
@@ -0,0 +1,86 @@
+mod common;
+
+use common::{assert_close, tone, waveform, FS};
+use core::f64::consts::{FRAC_1_SQRT_2, PI};
+use metrology_insight::{
+    biquad_low_pass, build_signal_filter, filter_chain_delay, FilterChain, FilterKind, MetrologyError,
+};
+
+const LEN: usize = 1560;
+
+fn filters() -> Vec<FilterKind> {
+    vec![
+        FilterKind::Fir {
+            taps: vec![0.25, 0.5, 0.25],
+        },
+        FilterKind::MovingAverage { window: 5 },
+        biquad_low_pass(1000.0, FRAC_1_SQRT_2, FS),
+    ]
+}
+
+#[test]
+fn filters_have_unity_gain_at_dc() {
+    for kind in filters() {
+        let mut filter = build_signal_filter(&kind).unwrap();
+        assert_close(filter.response(0.0, FS).norm(), 1.0, 1e-9, kind.as_str());
+
+        // El arranque toma la primera muestra como historia: sin transitorio con un offset del ADC
+        let mut signal = vec![2048.0; 64];
+        filter.process(&mut signal);
+        for sample in signal {
+            assert_close(sample, 2048.0, 1e-9, kind.as_str());
+        }
+    }
+}
+
+#[test]
+fn output_is_the_input_delayed_by_the_stated_delay() {
+    let freq = 50.0;
+    let omega = 2.0 * PI * freq / FS;
+
+    for kind in filters() {
+        let mut filter = build_signal_filter(&kind).unwrap();
+        let delay = filter.delay(freq, FS);
+        let gain = filter.response(freq, FS).norm();
+
+        let mut signal = waveform(&[tone(freq, 1.0, 0.0)], 0.0, FS, 0, LEN);
+        filter.process(&mut signal);
+
+        // Tras el transitorio, salida = ganancia × entrada retrasada
+        for (n, y) in signal.iter().enumerate().skip(LEN / 2) {
+            let expected = gain * 2f64.sqrt() * (omega * (n as f64 - delay)).sin();
+            assert_close(*y, expected, 1e-6, kind.as_str());
+        }
+    }
+
+    assert_close(
+        filter_chain_delay(&filters()[..2], freq, FS).unwrap(),
+        3.0,
+        1e-9,
+        "FIR and moving average delay",
+    );
+}
+
+#[test]
+fn unstable_biquad_is_rejected() {
+    // Polos fuera del círculo unidad
+    let unstable = FilterKind::Biquad {
+        b: [1.0, 0.0, 0.0],
+        a: [-2.1, 1.2],
+    };
+
+    assert_eq!(
+        build_signal_filter(&unstable).unwrap_err(),
+        MetrologyError::InvalidParameter("biquad")
+    );
+    assert_eq!(
+        filter_chain_delay(&[FilterKind::default(), unstable.clone()], 50.0, FS),
+        Err(MetrologyError::InvalidParameter("biquad"))
+    );
+
+    // La cadena queda como estaba
+    let mut chain = FilterChain::new();
+    chain.configure(&filters()[..1], 0.0).unwrap();
+    assert!(chain.configure(&[unstable], 0.0).is_err());
+    assert_close(chain.delay(50.0, FS), 1.0, 1e-9, "delay of the previous chain");
+}
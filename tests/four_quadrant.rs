@@ -0,0 +1,141 @@
+use metrology_insight::{
+    generate_load_signals, ChannelConfig, MetrologyInsight, MetrologyInsightConfig, MetrologyInsightSignalType,
+    SampleFrame, AMPS_TO_COUNTS, NUMBER_HARMONICS, VIN_TO_COUNTS,
+};
+
+const ADC_SAMPLE_SECONDS: f64 = 7812.5;
+const FRAMES: usize = 10;
+
+fn run_load(phase_deg: f64) -> MetrologyInsight {
+    let mut insight = MetrologyInsight::new(MetrologyInsightConfig {
+        avg_sec: 0.02,
+        adc_samples_seconds: ADC_SAMPLE_SECONDS,
+        num_harmonics: NUMBER_HARMONICS,
+        harmonic_grouping: false,
+        voltage: ChannelConfig {
+            calc_freq: true,
+            adc_factor: 1.0 / VIN_TO_COUNTS,
+            ..Default::default()
+        },
+        current: ChannelConfig {
+            adc_factor: 1.0 / AMPS_TO_COUNTS,
+            ..Default::default()
+        },
+        ..Default::default()
+    });
+
+    for _ in 0..FRAMES {
+        let signals = generate_load_signals(phase_deg);
+
+        let voltage_frame = SampleFrame::new(
+            MetrologyInsightSignalType::Voltage,
+            signals[0].clone(),
+            ADC_SAMPLE_SECONDS,
+        );
+        let current_frame = SampleFrame::new(
+            MetrologyInsightSignalType::Current,
+            signals[1].clone(),
+            ADC_SAMPLE_SECONDS,
+        );
+
+        insight.process_and_update_metrics(&voltage_frame, &current_frame);
+    }
+
+    insight
+}
+
+fn assert_only_quadrant(registers: [f64; 4], quadrant: usize) {
+    for (i, value) in registers.iter().enumerate() {
+        if i + 1 == quadrant {
+            assert!(
+                *value > 0.0,
+                "Q{} should accumulate energy, registers: {:?}",
+                i + 1,
+                registers
+            );
+        } else {
+            assert_eq!(*value, 0.0, "Q{} should stay empty, registers: {:?}", i + 1, registers);
+        }
+    }
+}
+
+fn assert_quadrant(phase_deg: f64, quadrant: usize) {
+    let insight = run_load(phase_deg);
+    let energy = &insight.socket.energy_metrics;
+
+    assert_only_quadrant(
+        [energy.active.q1, energy.active.q2, energy.active.q3, energy.active.q4],
+        quadrant,
+    );
+    assert_only_quadrant(
+        [
+            energy.reactive.q1,
+            energy.reactive.q2,
+            energy.reactive.q3,
+            energy.reactive.q4,
+        ],
+        quadrant,
+    );
+}
+
+#[test]
+fn lagging_load_is_inductive() {
+    let insight = run_load(30.0);
+    let power = &insight.socket.power_metrics;
+
+    // Q = V·I·sin(30°) with 325 V and 100 A peak
+    let expected = 325.0 * 100.0 / 2.0 * 30f64.to_radians().sin();
+    assert!(power.reactive_power > 0.0);
+    assert!(
+        (power.reactive_power - expected).abs() / expected < 0.02,
+        "Q = {}",
+        power.reactive_power
+    );
+}
+
+#[test]
+fn leading_load_is_capacitive() {
+    let insight = run_load(-30.0);
+    let power = &insight.socket.power_metrics;
+
+    let expected = -325.0 * 100.0 / 2.0 * 30f64.to_radians().sin();
+    assert!(power.reactive_power < 0.0);
+    assert!(
+        (power.reactive_power - expected).abs() / expected.abs() < 0.02,
+        "Q = {}",
+        power.reactive_power
+    );
+}
+
+#[test]
+fn importing_inductive_load_books_q1() {
+    assert_quadrant(30.0, 1);
+}
+
+#[test]
+fn exporting_capacitive_load_books_q2() {
+    assert_quadrant(150.0, 2);
+}
+
+#[test]
+fn exporting_inductive_load_books_q3() {
+    assert_quadrant(-150.0, 3);
+}
+
+#[test]
+fn importing_capacitive_load_books_q4() {
+    assert_quadrant(-30.0, 4);
+}
+
+#[test]
+fn reactive_register_views_follow_iec_62053_23() {
+    let inductive = run_load(30.0);
+    let reactive = &inductive.socket.energy_metrics.reactive;
+    assert!(reactive.inductive > 0.0);
+    assert_eq!(reactive.capacitive, 0.0);
+
+    let capacitive = run_load(-30.0);
+    let reactive = &capacitive.socket.energy_metrics.reactive;
+    assert!(reactive.capacitive > 0.0);
+    assert_eq!(reactive.inductive, 0.0);
+}
@@ -0,0 +1,62 @@
+mod common;
+
+use common::{tone, waveform, FRAME, FS};
+use metrology_insight::{build_frequency_estimator, FrequencyEstimate, FrequencyEstimatorKind};
+
+const NOMINAL: f64 = 50.0;
+const OFF_NOMINAL: f64 = 49.5;
+
+/*
+* @brief Feed one second of contiguous frames of an off-nominal sine to an estimator.
+* @return Last estimate given
+*/
+fn last_estimate(kind: FrequencyEstimatorKind) -> FrequencyEstimate {
+    let mut estimator = build_frequency_estimator(kind);
+    assert_eq!(estimator.kind(), kind);
+
+    let mut last = None;
+    for frame in 0..50 {
+        let samples = waveform(&[tone(OFF_NOMINAL, 230.0, 0.0)], 0.0, FS, frame * FRAME, FRAME);
+        if let Ok(estimate) = estimator.estimate(&samples, FS, NOMINAL) {
+            last = Some(estimate);
+        }
+    }
+
+    last.unwrap_or_else(|| panic!("{} gave no estimate", kind.as_str()))
+}
+
+#[test]
+fn estimators_converge_on_an_off_nominal_frequency() {
+    for kind in [
+        FrequencyEstimatorKind::ZeroCrossing,
+        FrequencyEstimatorKind::InterpolatedDft,
+        FrequencyEstimatorKind::Pll,
+        FrequencyEstimatorKind::Kalman,
+    ] {
+        let estimate = last_estimate(kind);
+
+        assert!(
+            (estimate.frequency - OFF_NOMINAL).abs() < 0.01,
+            "{}: {} Hz",
+            kind.as_str(),
+            estimate.frequency
+        );
+        assert!(
+            estimate.uncertainty > 0.0 && estimate.uncertainty < 0.2,
+            "{}: uncertainty {} Hz",
+            kind.as_str(),
+            estimate.uncertainty
+        );
+        assert!(
+            estimate.quality > 0.0 && estimate.quality < 1.0,
+            "{}: quality {}",
+            kind.as_str(),
+            estimate.quality
+        );
+    }
+}
+
+#[test]
+fn zero_crossing_is_the_default_estimator() {
+    assert_eq!(FrequencyEstimatorKind::default(), FrequencyEstimatorKind::ZeroCrossing);
+}
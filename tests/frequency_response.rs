@@ -0,0 +1,64 @@
+mod common;
+
+use common::{assert_close, tone, waveform};
+use metrology_insight::{compute_harmonics_and_thd, FrequencyResponse, MetrologyError, ResponsePoint};
+
+const FS: f64 = 8000.0; // 160 muestras por ciclo a 50 Hz
+
+#[test]
+fn response_is_interpolated_between_points() {
+    let response = FrequencyResponse::zmpt101b();
+
+    // A medio camino entre 150 Hz (0,995, -0,5°) y 250 Hz (0,985, -1,2°)
+    let at = response.at(200.0);
+    assert_close(at.norm(), 0.990, 1e-12, "gain at 200 Hz");
+    assert_close(at.arg().to_degrees(), -0.85, 1e-9, "phase at 200 Hz");
+
+    let relative = response.relative(50.0, 50.0);
+    assert_close(relative.norm(), 1.0, 1e-12, "gain at the fundamental");
+    assert_close(relative.arg(), 0.0, 1e-12, "phase at the fundamental");
+
+    assert_close(response.at(10_000.0).norm(), 0.620, 1e-12, "gain above the table");
+    assert_eq!(FrequencyResponse::default().at(250.0).norm(), 1.0);
+}
+
+#[test]
+fn sensor_response_is_removed_from_the_harmonics() {
+    // Salida del ZMPT101B para 230 V y un 5º armónico de 10 V: ganancia relativa 0,985 y -1,5° a 250 Hz
+    let wave = waveform(
+        &[tone(50.0, 230.0, 0.0), tone(250.0, 10.0 * 0.985, 30.0 - 1.5)],
+        0.0,
+        FS,
+        0,
+        160,
+    );
+
+    let (flat, _) = compute_harmonics_and_thd(&wave, 50.0, FS, 5, &FrequencyResponse::default()).unwrap();
+    let (corrected, thd) = compute_harmonics_and_thd(&wave, 50.0, FS, 5, &FrequencyResponse::zmpt101b()).unwrap();
+
+    assert_close(flat[4].rms, 9.85, 1e-6, "5th harmonic as measured");
+    assert_close(corrected[4].rms, 10.0, 1e-6, "5th harmonic at the primary");
+    assert_close(corrected[4].phase - flat[4].phase, 1.5, 1e-6, "phase correction");
+    assert_close(corrected[0].rms, 230.0, 1e-6, "fundamental");
+    assert_close(thd, 10.0 / 230.0 * 100.0, 1e-6, "THD");
+}
+
+#[test]
+fn invalid_points_are_rejected() {
+    let point = |freq, gain| ResponsePoint {
+        freq,
+        gain,
+        phase_deg: 0.0,
+    };
+
+    for points in [
+        vec![point(50.0, 1.0), point(50.0, 0.9)],
+        vec![point(0.0, 1.0)],
+        vec![point(50.0, 0.0)],
+    ] {
+        assert_eq!(
+            FrequencyResponse::new(points),
+            Err(MetrologyError::InvalidParameter("response point"))
+        );
+    }
+}
@@ -0,0 +1,96 @@
+mod common;
+
+use common::{assert_close, signal, tone, waveform};
+use metrology_insight::{
+    compute_harmonic_groups, grouping_window_cycles, update_harmonic_groups, FrequencyResponse, QualityFlags,
+};
+
+// 10 ciclos de 50 Hz a 8 kHz: 1600 muestras, líneas espectrales cada 5 Hz
+const FS: f64 = 8000.0;
+const WINDOW: usize = 1600;
+
+#[test]
+fn window_spans_200_ms() {
+    assert_eq!(grouping_window_cycles(50.0), 10);
+    assert_eq!(grouping_window_cycles(60.0), 12);
+}
+
+#[test]
+fn lines_are_grouped_as_iec_61000_4_7() {
+    // 155 Hz está junto al 3er armónico (k + 1); 175 Hz en el centro del intervalo entre el 3º y el 4º
+    let window = waveform(
+        &[
+            tone(50.0, 230.0, 0.0),
+            tone(155.0, 3.0, 0.0),
+            tone(175.0, 5.0, 0.0),
+            tone(250.0, 10.0, 0.0),
+        ],
+        0.0,
+        FS,
+        0,
+        WINDOW,
+    );
+
+    let groups = compute_harmonic_groups(&window, 10, 5, &FrequencyResponse::default(), 50.0, FS).unwrap();
+    let tolerance = 1e-6;
+
+    assert_close(groups.harmonic_groups[0], 230.0, tolerance, "G1");
+    assert_close(groups.harmonic_groups[4], 10.0, tolerance, "G5");
+
+    // La línea k + 1 cuenta en el grupo y el subgrupo del 3er armónico y en el grupo interarmónico,
+    // pero no en el subgrupo interarmónico centrado
+    // 175 Hz es la línea frontera k ± 5 de los grupos del 3º y el 4º: entra con la mitad en cada uno
+    assert_close(groups.harmonic_groups[2], (9.0 + 25.0 / 2.0f64).sqrt(), tolerance, "G3");
+    assert_close(groups.harmonic_groups[3], (25.0 / 2.0f64).sqrt(), tolerance, "G4");
+    assert_close(groups.harmonic_subgroups[2], 3.0, tolerance, "Gsg3");
+    assert_close(
+        groups.interharmonic_groups[3],
+        (3f64.powi(2) + 5f64.powi(2)).sqrt(),
+        tolerance,
+        "Cig3",
+    );
+    assert_close(groups.interharmonic_subgroups[3], 5.0, tolerance, "Cisg3");
+
+    // Ni los subgrupos armónicos ni los demás intervalos la recogen
+    assert_close(groups.harmonic_subgroups[3], 0.0, tolerance, "Gsg4");
+    assert_close(groups.interharmonic_groups[1], 0.0, tolerance, "Cig1");
+
+    let thdg = (9.0 + 25.0 + 100.0f64).sqrt() / 230.0 * 100.0;
+    assert_close(groups.thdg_percent, thdg, 1e-6, "THDG");
+}
+
+#[test]
+fn fractional_window_does_not_leak_the_fundamental() {
+    // 10 ciclos a 7812.5 S/s son 1562.5 muestras; se entrega la muestra siguiente para la fracción
+    let window = waveform(&[tone(50.0, 230.0, 0.0)], 0.0, common::FS, 0, 1563);
+
+    let groups = compute_harmonic_groups(&window, 10, 5, &FrequencyResponse::default(), 50.0, common::FS).unwrap();
+
+    assert_close(groups.harmonic_groups[0], 230.0, 0.05, "G1");
+    assert!(groups.thdg_percent < 0.05, "THDG = {}", groups.thdg_percent);
+    assert!(
+        groups.interharmonic_groups[1] < 0.1,
+        "Cig1 = {}",
+        groups.interharmonic_groups[1]
+    );
+}
+
+#[test]
+fn failed_window_raises_harmonics_failed() {
+    let mut wave = signal(waveform(&[tone(50.0, 230.0, 0.0)], 0.0, common::FS, 0, 1600), 50.0);
+    let mut buffer = Vec::new();
+
+    // Sin armónicos que agrupar la ventana se rechaza y se conserva el resultado anterior
+    update_harmonic_groups(
+        &mut wave,
+        &mut buffer,
+        true,
+        common::FS,
+        0,
+        &FrequencyResponse::default(),
+    );
+
+    assert!(wave.quality.contains(QualityFlags::HARMONICS_FAILED));
+    assert!(wave.harmonic_groups.harmonic_groups.is_empty());
+    assert!(buffer.len() < 1563, "buffer = {}", buffer.len());
+}
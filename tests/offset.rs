@@ -0,0 +1,43 @@
+mod common;
+
+use common::{assert_close, tone, waveform, FS};
+use metrology_insight::{OffsetTracker, OFFSET_TIME_CONSTANT};
+
+const CYCLE: f64 = FS / 50.0; // 156,25 muestras
+const FRAME: usize = 625; // Cuatro ciclos completos, 80 ms
+
+#[test]
+fn asymmetric_waveform_does_not_bias_the_offset() {
+    // Fundamental más segundo armónico: el punto medio de los picos no es el nivel de continua
+    let wave = waveform(&[tone(50.0, 10.0, 0.0), tone(100.0, 4.0, 90.0)], 1.5, FS, 0, FRAME);
+    let (min, max) = wave
+        .iter()
+        .fold((f64::MAX, f64::MIN), |(min, max), &x| (min.min(x), max.max(x)));
+    assert!(((max + min) / 2.0 - 1.5).abs() > 1.0);
+
+    let mut tracker = OffsetTracker::new();
+    assert_close(tracker.update(&wave, CYCLE, FS), 1.5, 1e-9, "offset of the first frame");
+}
+
+#[test]
+fn offset_step_is_followed_with_the_time_constant() {
+    let mut tracker = OffsetTracker::new();
+    tracker.update(&waveform(&[tone(50.0, 10.0, 0.0)], 0.0, FS, 0, FRAME), CYCLE, FS);
+
+    // Escalón de 2 unidades durante dos constantes de tiempo
+    let frames = (2.0 * OFFSET_TIME_CONSTANT * FS / FRAME as f64).round() as usize;
+    let mut wave = Vec::new();
+    for frame in 1..=frames {
+        wave = waveform(&[tone(50.0, 10.0, 0.0)], 2.0, FS, frame * FRAME, FRAME);
+        tracker.update(&wave, CYCLE, FS);
+    }
+    assert_close(tracker.offset(), 2.0 * (1.0 - (-2f64).exp()), 1e-6, "tracked offset");
+
+    // remove resta el nivel seguido de la trama
+    let offset = tracker.remove(&mut wave, CYCLE, FS);
+    let mean = wave.iter().sum::<f64>() / wave.len() as f64;
+    assert_close(mean, 2.0 - offset, 1e-9, "mean after removal");
+
+    tracker.reset();
+    assert_eq!(tracker.offset(), 0.0);
+}
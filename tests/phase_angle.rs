@@ -0,0 +1,56 @@
+mod common;
+
+use common::{assert_close, signal, tone, waveform};
+use metrology_insight::{
+    update_phase_angles, FrequencyResponse, MetrologyInsightSocket, PhaseAngleMethod, PhaseDirection,
+};
+
+fn phase_angles(phase_deg: f64) -> MetrologyInsightSocket {
+    let mut socket = MetrologyInsightSocket {
+        voltage_signal: signal(waveform(&[tone(50.0, 230.0, 0.0)], 0.0, common::FS, 0, 312), 50.0),
+        current_signal: signal(
+            waveform(
+                &[tone(50.0, 5.0, -phase_deg), tone(150.0, 1.0, -phase_deg)],
+                0.0,
+                common::FS,
+                0,
+                312,
+            ),
+            50.0,
+        ),
+        ..Default::default()
+    };
+
+    let flat = FrequencyResponse::default();
+    update_phase_angles(&mut socket, common::FS, 5, [&flat, &flat]);
+    socket
+}
+
+#[test]
+fn lagging_and_leading_currents_give_signed_angles() {
+    for (phase, direction) in [
+        (30.0, PhaseDirection::Inductive),
+        (120.0, PhaseDirection::Inductive),
+        (-30.0, PhaseDirection::Capacitive),
+        (-120.0, PhaseDirection::Capacitive),
+    ] {
+        let angles = phase_angles(phase).phase_angles;
+        assert_eq!(angles.method, PhaseAngleMethod::SpectralPhasor);
+        assert_close(angles.c2v_angle, phase, 0.05, &format!("angle at {}°", phase));
+        assert_eq!(angles.direction.as_str(), direction.as_str(), "direction at {}°", phase);
+    }
+}
+
+#[test]
+fn harmonics_missing_in_one_channel_have_no_angle() {
+    // La tensión no tiene armónicos: solo el fundamental tiene ángulo
+    let angles = phase_angles(30.0).phase_angles;
+
+    assert_eq!(angles.harmonic_angles.len(), 5);
+    assert_close(angles.harmonic_angles[0], 30.0, 0.05, "fundamental angle");
+    assert!(
+        angles.harmonic_angles[1..].iter().all(|a| a.is_nan()),
+        "angles: {:?}",
+        angles.harmonic_angles
+    );
+}
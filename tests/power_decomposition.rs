@@ -0,0 +1,79 @@
+mod common;
+
+use common::{assert_close, signal, tone, waveform};
+use metrology_insight::{update_power_decomposition, update_power_metrics, FrequencyResponse, MetrologyInsightSocket};
+
+// 156 muestras por ciclo exactas a 50 Hz: los fasores y las medias no tienen fuga
+const FS: f64 = 7800.0;
+const CYCLE: usize = 156;
+
+fn socket(voltage: &[common::Tone], current: &[common::Tone]) -> MetrologyInsightSocket {
+    let mut socket = MetrologyInsightSocket {
+        voltage_signal: signal(waveform(voltage, 0.0, FS, 0, CYCLE), 50.0),
+        current_signal: signal(waveform(current, 0.0, FS, 0, CYCLE), 50.0),
+        ..Default::default()
+    };
+
+    let flat = FrequencyResponse::default();
+    update_power_metrics(&mut socket, FS).unwrap();
+    update_power_decomposition(&mut socket, FS, 10, [&flat, &flat]).unwrap();
+    socket
+}
+
+#[test]
+fn fundamental_reactive_power_is_signed() {
+    for phase in [-150.0, -90.0, -60.0, 0.0, 45.0, 90.0, 120.0] {
+        let socket = socket(&[tone(50.0, 230.0, 0.0)], &[tone(50.0, 10.0, -phase)]);
+        let power = &socket.power_metrics;
+        let expected_q = 2300.0 * f64::sin(phase.to_radians());
+        let expected_p = 2300.0 * f64::cos(phase.to_radians());
+
+        assert_close(power.reactive_power, expected_q, 1.0, &format!("Q at {}°", phase));
+        assert_close(power.real_power, expected_p, 1.0, &format!("P at {}°", phase));
+    }
+}
+
+#[test]
+fn distorted_waveform_matches_ieee_1459_by_hand() {
+    // V = 230 V + 23 V de 3er armónico; I = 10 A a -30° + 2 A de 3er armónico a -60°
+    let socket = socket(
+        &[tone(50.0, 230.0, 0.0), tone(150.0, 23.0, 0.0)],
+        &[tone(50.0, 10.0, -30.0), tone(150.0, 2.0, -60.0)],
+    );
+    let d = &socket.power_decomposition;
+
+    let p1 = 230.0 * 10.0 * 30f64.to_radians().cos(); // 1991.86 W
+    let q1 = 230.0 * 10.0 * 30f64.to_radians().sin(); // 1150 var
+    let ph = 23.0 * 2.0 * 60f64.to_radians().cos(); // 23 W
+
+    assert_close(d.fundamental_active_power, p1, 0.05, "P1");
+    assert_close(d.fundamental_reactive_power, q1, 0.05, "Q1");
+    assert_close(d.fundamental_apparent_power, 2300.0, 0.05, "S1");
+    assert_close(d.harmonic_active_power, ph, 0.05, "PH");
+    assert_close(d.current_distortion_power, 230.0 * 2.0, 0.05, "DI = V1·IH");
+    assert_close(d.voltage_distortion_power, 23.0 * 10.0, 0.05, "DV = VH·I1");
+    assert_close(d.harmonic_apparent_power, 23.0 * 2.0, 0.05, "SH = VH·IH");
+    assert_close(socket.power_metrics.real_power, p1 + ph, 0.05, "P = P1 + PH");
+
+    let sn = (460f64.powi(2) + 230f64.powi(2) + 46f64.powi(2)).sqrt();
+    assert_close(d.non_fundamental_apparent_power, sn, 0.05, "SN");
+    assert_close(d.displacement_power_factor, 30f64.to_radians().cos(), 1e-4, "DPF");
+}
+
+#[test]
+fn reactive_power_is_the_fundamental_one_with_fractional_cycles() {
+    // 156.25 muestras por ciclo y 3er armónico en fase: Q = Q1, sin fuga ni aporte armónico
+    let fs = common::FS;
+    let voltage = [tone(50.0, 230.0, 0.0), tone(150.0, 23.0, 0.0)];
+    let current = [tone(50.0, 10.0, -60.0), tone(150.0, 2.0, 0.0)];
+    let mut socket = MetrologyInsightSocket {
+        voltage_signal: signal(waveform(&voltage, 0.0, fs, 0, common::FRAME), 50.0),
+        current_signal: signal(waveform(&current, 0.0, fs, 0, common::FRAME), 50.0),
+        ..Default::default()
+    };
+
+    update_power_metrics(&mut socket, fs).unwrap();
+
+    let q1 = 230.0 * 10.0 * 60f64.to_radians().sin(); // 1991.86 var
+    assert_close(socket.power_metrics.reactive_power, q1, 0.5, "Q");
+}
@@ -0,0 +1,73 @@
+mod common;
+
+use common::{board_config, board_frames, tone, FS};
+use metrology_insight::{
+    check_adc_range, generate_load_signals, MetrologyInsight, MetrologyInsightConfig, MetrologyInsightSignalType,
+    QualityFlags, SampleFrame,
+};
+
+#[test]
+fn clean_mid_scale_sine_carries_no_flags() {
+    let config = board_config();
+    let mut insight = MetrologyInsight::new(config.clone());
+
+    for sequence in 0..10 {
+        let (v, c) = board_frames(&config, sequence, &[tone(50.0, 230.0, 0.0)], &[tone(50.0, 10.0, 0.0)]);
+        insight.try_process_and_update_metrics(&v, &c).unwrap();
+    }
+
+    assert!(insight.socket.voltage_signal.quality.is_empty());
+    assert!(insight.socket.current_signal.quality.is_empty());
+}
+
+#[test]
+fn clamped_sine_is_flagged() {
+    // 40 A eficaces superan los 47,9 A de pico que admite el SCT013-030 en el ADC
+    let config = board_config();
+    let mut insight = MetrologyInsight::new(config.clone());
+
+    let (v, mut c) = board_frames(&config, 0, &[tone(50.0, 230.0, 0.0)], &[tone(50.0, 40.0, 0.0)]);
+    for sample in c.samples.iter_mut() {
+        *sample = (*sample).clamp(0, config.adc_max_code as i32);
+    }
+    insight.try_process_and_update_metrics(&v, &c).unwrap();
+
+    assert!(insight.socket.current_signal.quality.contains(QualityFlags::SATURATION));
+    assert!(!insight.socket.voltage_signal.quality.contains(QualityFlags::SATURATION));
+    assert!(insight.socket.quality.contains(QualityFlags::SATURATION));
+}
+
+#[test]
+fn small_signal_is_under_range() {
+    let config = board_config();
+    let (v, _) = board_frames(&config, 0, &[tone(50.0, 20.0, 0.0)], &[]);
+    let samples: Vec<f64> = v.samples.iter().map(|&s| s as f64).collect();
+
+    assert_eq!(
+        check_adc_range(&samples, config.adc_max_code),
+        QualityFlags::UNDER_RANGE
+    );
+}
+
+#[test]
+fn signed_input_is_not_checked_by_default() {
+    let config = MetrologyInsightConfig {
+        adc_samples_seconds: FS,
+        ..Default::default()
+    };
+    let signals = generate_load_signals(30.0);
+    let mut insight = MetrologyInsight::new(config);
+
+    let v = SampleFrame::new(MetrologyInsightSignalType::Voltage, signals[0].clone(), FS);
+    let c = SampleFrame::new(MetrologyInsightSignalType::Current, signals[1].clone(), FS);
+    insight.process_and_update_metrics(&v, &c);
+
+    for quality in [
+        insight.socket.voltage_signal.quality,
+        insight.socket.current_signal.quality,
+    ] {
+        assert!(!quality.contains(QualityFlags::STALE));
+        assert!(!quality.contains(QualityFlags::SATURATION));
+        assert!(!quality.contains(QualityFlags::UNDER_RANGE));
+    }
+}
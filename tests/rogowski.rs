@@ -0,0 +1,91 @@
+mod common;
+
+use common::{assert_close, board_config, board_frames, tone, waveform, FRAME, FS};
+use metrology_insight::{AdcFrontEnd, MetrologyError, MetrologyInsight, RogowskiIntegrator, SensorModel};
+
+const NOMINAL: f64 = 50.0;
+
+/*
+* @brief Integrate a coil output, frame by frame.
+* @param frames Frames to integrate
+* @param coil Coil output at sample n
+* @return Output of the last frame and its first sample index
+*/
+fn integrate(frames: usize, coil: impl Fn(usize) -> f64) -> (Vec<f64>, usize) {
+    let mut integrator = RogowskiIntegrator::new();
+    let mut output = Vec::new();
+
+    for frame in 0..frames {
+        let input: Vec<f64> = (frame * FRAME..(frame + 1) * FRAME).map(&coil).collect();
+        output = integrator.integrate(&input, FS, NOMINAL).unwrap();
+    }
+
+    (output, (frames - 1) * FRAME)
+}
+
+#[test]
+fn unity_gain_and_quarter_cycle_lag_at_nominal_frequency() {
+    // La bobina da la derivada de la corriente: cos a la entrada, sin a la salida
+    let (output, start) = integrate(150, |n| waveform(&[tone(NOMINAL, 10.0, 90.0)], 0.0, FS, n, 1)[0]);
+    let expected = waveform(&[tone(NOMINAL, 10.0, 0.0)], 0.0, FS, start, FRAME);
+
+    for (y, x) in output.iter().zip(&expected) {
+        assert_close(*y, *x, 10.0 * 2f64.sqrt() * 0.005, "integrated sample");
+    }
+}
+
+#[test]
+fn harmonics_are_divided_by_their_order() {
+    let mut integrator = RogowskiIntegrator::new();
+    integrator.integrate(&[0.0; FRAME], FS, NOMINAL).unwrap();
+
+    for order in [1, 3, 5, 11, 21] {
+        let response = integrator.response(order as f64 * NOMINAL);
+        assert_close(response.norm() * order as f64, 1.0, 0.005, "gain × order");
+        assert_close(response.arg().to_degrees(), -90.0, 0.5, "phase");
+    }
+}
+
+#[test]
+fn offset_and_offset_steps_do_not_drift() {
+    // Offset del ADC desde el arranque y un escalón de offset al cabo de un segundo, que se extingue
+    // con la constante de tiempo del paso alto (unos 6 s hasta quedar por debajo del ruido)
+    let (output, _) = integrate(400, |n| {
+        let step = if n >= 50 * FRAME { 40.0 } else { 0.0 };
+        waveform(&[tone(NOMINAL, 10.0, 90.0)], 2048.0 + step, FS, n, 1)[0]
+    });
+
+    let mean = output.iter().sum::<f64>() / output.len() as f64;
+    let peak = output.iter().copied().fold(0.0, |m: f64, y| m.max(y.abs()));
+    assert!(mean.abs() < 0.1, "mean {}", mean);
+    assert_close(peak, 10.0 * 2f64.sqrt(), 0.3, "peak");
+}
+
+#[test]
+fn nominal_above_nyquist_is_rejected() {
+    let mut integrator = RogowskiIntegrator::new();
+    assert_eq!(
+        integrator.integrate(&[0.0; FRAME], FS, FS),
+        Err(MetrologyError::InvalidParameter("nominal"))
+    );
+}
+
+#[test]
+fn first_frame_rms_has_no_bias_offset() {
+    // Bobina de 30 A con 1 V a plena carga, polarizada a media escala como el resto de la placa
+    let mut config = board_config();
+    SensorModel::Rogowski {
+        sensitivity: 1.0 / 30.0,
+        rated_a: 30.0,
+    }
+    .configure(&AdcFrontEnd::milk_v_duo(), &mut config.current)
+    .unwrap();
+
+    let (voltage, current) = board_frames(&config, 0, &[tone(NOMINAL, 230.0, 0.0)], &[tone(NOMINAL, 10.0, 90.0)]);
+    let mut insight = MetrologyInsight::new(config);
+    insight.try_process_and_update_metrics(&voltage, &current).unwrap();
+
+    // El integrador ya quita la continua: solo queda su transitorio de arranque, no los ~50 A de la polarización
+    assert_close(insight.socket.current_signal.rms, 10.0, 0.2, "first frame RMS");
+    assert_close(insight.state.current.offset.offset(), 0.0, 1.0, "tracked DC level");
+}
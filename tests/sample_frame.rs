@@ -0,0 +1,50 @@
+mod common;
+
+use common::{assert_close, board_config, board_frames, tone, FRAME, FS};
+use metrology_insight::MetrologyInsight;
+
+#[test]
+fn board_frames_are_converted_to_physical_units() {
+    let config = board_config();
+    let mut insight = MetrologyInsight::new(config.clone());
+
+    for sequence in 0..25 {
+        let (v, c) = board_frames(&config, sequence, &[tone(50.0, 230.0, 0.0)], &[tone(50.0, 5.0, 0.0)]);
+        insight.try_process_and_update_metrics(&v, &c).unwrap();
+    }
+
+    let socket = &insight.socket;
+    assert_close(socket.voltage_signal.rms, 230.0, 1.0, "voltage RMS");
+    assert_close(socket.current_signal.rms, 5.0, 0.05, "current RMS");
+    assert_close(socket.voltage_signal.freq_zc, 50.0, 0.05, "frequency");
+    assert_close(socket.power_metrics.real_power, 1150.0, 15.0, "real power");
+    assert_eq!(socket.voltage_signal.sequence, 24);
+    assert_eq!(
+        socket.voltage_signal.timestamp_us,
+        (24.0 * FRAME as f64 * 1e6 / FS).round() as u64
+    );
+}
+
+#[test]
+fn peak_is_the_highest_sample_since_start() {
+    let config = board_config();
+    let mut insight = MetrologyInsight::new(config.clone());
+
+    for sequence in 0..10 {
+        let (v, c) = board_frames(&config, sequence, &[tone(50.0, 230.0, 0.0)], &[tone(50.0, 5.0, 0.0)]);
+        insight.try_process_and_update_metrics(&v, &c).unwrap();
+    }
+    let peak = insight.socket.voltage_signal.peak;
+    assert_close(peak, 230.0 * 2f64.sqrt(), 3.0, "voltage peak");
+
+    // Una caída de tensión no baja el pico registrado
+    for sequence in 10..60 {
+        let (v, c) = board_frames(&config, sequence, &[tone(50.0, 115.0, 0.0)], &[tone(50.0, 5.0, 0.0)]);
+        insight.try_process_and_update_metrics(&v, &c).unwrap();
+    }
+    assert_eq!(insight.socket.voltage_signal.peak, peak);
+    assert!(
+        insight.socket.voltage_signal.rms < 200.0,
+        "the averaged RMS follows the sag"
+    );
+}
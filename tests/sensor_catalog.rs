@@ -0,0 +1,65 @@
+mod common;
+
+use common::assert_close;
+use metrology_insight::{AdcFrontEnd, ChannelConfig, MetrologyError, SensorModel, SensorType};
+
+#[test]
+fn milk_v_duo_front_end() {
+    let frontend = AdcFrontEnd::milk_v_duo();
+
+    assert_eq!(frontend.validate(), Ok(()));
+    assert_eq!(frontend.max_code(), 4095.0);
+    assert_close(frontend.pin_full_scale(), 3.6, 1e-12, "full scale at the pin");
+    assert_close(frontend.zero_code(), 1876.875, 1e-9, "code of the bias");
+    assert_close(frontend.headroom(), 1.65, 1e-12, "headroom");
+}
+
+#[test]
+fn sensors_give_the_factors_of_their_channel() {
+    let frontend = AdcFrontEnd::milk_v_duo();
+    let volts_per_count = 3.6 / 4095.0;
+
+    // SCT013-030: 1800 espiras sobre 62 Ω, factor en V por código y escala en A por V
+    let ct = SensorModel::sct013_030();
+    let (factor, scale) = ct.adc_factors(&frontend);
+    assert_close(factor, volts_per_count, 1e-15, "CT factor");
+    assert_close(scale, 1800.0 / 62.0, 1e-12, "CT scale");
+    assert_close(
+        ct.clipping_peak(&frontend),
+        1.65 * 1800.0 / 62.0,
+        1e-9,
+        "CT clipping peak",
+    );
+
+    // ZMPT101B: V por código directamente
+    let vt = SensorModel::zmpt101b();
+    let (factor, scale) = vt.adc_factors(&frontend);
+    assert_close(factor, volts_per_count * 1170.0, 1e-12, "VT factor");
+    assert_eq!(scale, 1.0);
+
+    let mut channel = ChannelConfig::default();
+    ct.configure(&frontend, &mut channel).unwrap();
+    assert_eq!(channel.sensor_type, SensorType::CurrentTransformer);
+    assert_eq!(channel.calibration.offset, frontend.zero_code());
+    assert!(!channel.frequency_response.is_empty());
+}
+
+#[test]
+fn invalid_front_end_or_sensor_leaves_the_channel_unchanged() {
+    let mut channel = ChannelConfig::default();
+    let before = format!("{:?}", channel);
+
+    let frontend = AdcFrontEnd {
+        bias: 4.0,
+        ..AdcFrontEnd::milk_v_duo()
+    };
+    assert_eq!(
+        SensorModel::sct013_030().configure(&frontend, &mut channel),
+        Err(MetrologyError::InvalidParameter("bias"))
+    );
+    assert_eq!(
+        SensorModel::sct013_000(0.0).configure(&AdcFrontEnd::milk_v_duo(), &mut channel),
+        Err(MetrologyError::InvalidParameter("sensor model"))
+    );
+    assert_eq!(format!("{:?}", channel), before);
+}
@@ -0,0 +1,79 @@
+mod common;
+
+use common::{assert_close, board_config, board_frames, tone};
+use metrology_insight::{
+    MetrologyError, MetrologyInsight, MockTemperatureSensor, SysfsTemperatureSensor, TemperatureCompensation,
+    TemperatureSensor,
+};
+
+/*
+* @brief Current RMS after some frames of a 5 A load read 2 % low by the sensor.
+*/
+fn current_rms(insight: &mut MetrologyInsight, frames: std::ops::Range<u64>) -> f64 {
+    let config = insight.config.clone();
+    for sequence in frames {
+        let (v, c) = board_frames(
+            &config,
+            sequence,
+            &[tone(50.0, 230.0, 0.0)],
+            &[tone(50.0, 5.0 * 0.98, 0.0)],
+        );
+        insight.try_process_and_update_metrics(&v, &c).unwrap();
+    }
+    insight.socket.current_signal.rms
+}
+
+#[test]
+fn gain_drift_is_compensated_at_the_sensor_temperature() {
+    // -0,1 %/°C respecto a 25 °C: a 45 °C el sensor da un 2 % menos
+    let mut config = board_config();
+    config.current.calibration.temperature = TemperatureCompensation {
+        reference: 25.0,
+        gain: vec![-1e-3],
+        phase: vec![],
+    };
+
+    let mut uncompensated = MetrologyInsight::new(config.clone());
+    assert_close(
+        current_rms(&mut uncompensated, 0..10),
+        4.9,
+        0.02,
+        "current without a sensor",
+    );
+    assert_eq!(uncompensated.socket.temperature, None);
+
+    let sensor = MockTemperatureSensor::new(45.0);
+    let mut insight = MetrologyInsight::new(config);
+    insight.set_temperature_sensor(Some(Box::new(sensor.clone())));
+    assert_close(current_rms(&mut insight, 0..10), 5.0, 0.02, "compensated current");
+    assert_eq!(insight.socket.temperature, Some(45.0));
+
+    // Una lectura fallida conserva la última temperatura
+    sensor.set(None);
+    assert_close(
+        current_rms(&mut insight, 10..12),
+        5.0,
+        0.02,
+        "current after a failed read",
+    );
+    assert_eq!(insight.socket.temperature, Some(45.0));
+
+    insight.set_temperature_sensor(None);
+    assert_eq!(insight.socket.temperature, None);
+}
+
+#[test]
+fn sysfs_zone_is_read_in_millidegrees() {
+    let path = std::env::temp_dir().join(format!("metrology_insight_temp_{}", std::process::id()));
+    let mut sensor = SysfsTemperatureSensor::from_path(&path);
+
+    std::fs::write(&path, "41250\n").unwrap();
+    assert_eq!(sensor.read(), Ok(41.25));
+
+    std::fs::write(&path, "n/a\n").unwrap();
+    let invalid = sensor.read();
+    std::fs::remove_file(&path).unwrap();
+    assert!(matches!(invalid, Err(MetrologyError::TemperatureSensor(_))));
+
+    assert!(matches!(sensor.read(), Err(MetrologyError::TemperatureSensor(_))));
+}
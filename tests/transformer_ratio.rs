@@ -0,0 +1,65 @@
+mod common;
+
+use common::{assert_close, board_config, board_frames, tone};
+use metrology_insight::{MetrologyError, MetrologyInsight, TransformerRatio};
+
+#[test]
+fn results_are_referred_to_the_primary_side() {
+    // Medida en media tensión: TT 20000/110 V y TI 400/5 A ante los sensores de la placa
+    let mut config = board_config();
+    config.voltage.transformer = TransformerRatio::new(20000.0, 110.0).unwrap();
+    config.current.transformer = TransformerRatio::new(400.0, 5.0).unwrap();
+    let mut insight = MetrologyInsight::new(config.clone());
+
+    // 19 kV y 200 A en el primario: 104,5 V y 2,5 A en los secundarios
+    for sequence in 0..10 {
+        let (v, c) = board_frames(
+            &config,
+            sequence,
+            &[tone(50.0, 19000.0, 0.0)],
+            &[tone(50.0, 200.0, 0.0)],
+        );
+        insight.try_process_and_update_metrics(&v, &c).unwrap();
+    }
+
+    let voltage = &insight.socket.voltage_signal;
+    let current = &insight.socket.current_signal;
+    assert_close(voltage.rms, 19000.0, 100.0, "primary voltage");
+    assert_close(voltage.secondary_rms, 104.5, 0.5, "secondary voltage");
+    assert_close(voltage.transformer_ratio, 20000.0 / 110.0, 1e-9, "voltage ratio");
+    assert_close(current.rms, 200.0, 2.0, "primary current");
+    assert_close(current.secondary_rms, 2.5, 0.025, "secondary current");
+    assert_close(current.secondary_peak, current.peak / 80.0, 1e-9, "secondary peak");
+
+    assert_close(
+        insight.socket.power_metrics.real_power,
+        3.8e6,
+        5e4,
+        "primary real power",
+    );
+}
+
+#[test]
+fn invalid_ratio_is_rejected() {
+    let invalid = Err(MetrologyError::InvalidParameter("transformer"));
+    assert_eq!(TransformerRatio::new(400.0, 5.0).unwrap().ratio(), 80.0);
+    assert_eq!(TransformerRatio::new(400.0, 0.0), invalid);
+    assert_eq!(TransformerRatio::new(-1.0, 5.0), invalid);
+    assert_eq!(TransformerRatio::new(f64::NAN, 5.0), invalid);
+    assert_eq!(TransformerRatio::default().to_secondary(230.0), 230.0);
+}
+
+#[test]
+fn frames_of_a_channel_with_an_invalid_ratio_are_rejected() {
+    let mut config = board_config();
+    config.current.transformer = TransformerRatio {
+        primary: 400.0,
+        secondary: 0.0,
+    };
+    let (v, c) = board_frames(&board_config(), 0, &[tone(50.0, 230.0, 0.0)], &[tone(50.0, 5.0, 0.0)]);
+    let mut insight = MetrologyInsight::new(config);
+    assert_eq!(
+        insight.try_process_and_update_metrics(&v, &c),
+        Err(MetrologyError::InvalidParameter("transformer"))
+    );
+}